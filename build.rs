@@ -1,5 +1,29 @@
-extern crate napi_build;
+use std::path::Path;
 
 fn main() {
+  #[cfg(feature = "napi")]
   napi_build::setup();
-}
\ No newline at end of file
+  check_vendor();
+}
+
+/// The `vendor` feature embeds the files `vendor/fetch.sh` downloads, they have to be there
+fn check_vendor() {
+  println!("cargo:rerun-if-changed=vendor");
+  if std::env::var_os("CARGO_FEATURE_VENDOR").is_none() {
+    return;
+  }
+
+  let script = std::fs::read_to_string("vendor/fetch.sh").expect("vendor/fetch.sh should list the libraries");
+  let missing = script
+    .lines()
+    .filter_map(|line| line.split(" -o ").nth(1)?.split_whitespace().next())
+    .filter(|name| !Path::new("vendor").join(name).is_file())
+    .collect::<Vec<_>>();
+  if !missing.is_empty() {
+    eprintln!(
+      "error: the `vendor` feature embeds vendor/{}, run vendor/fetch.sh before building",
+      missing.join(", vendor/")
+    );
+    std::process::exit(1);
+  }
+}
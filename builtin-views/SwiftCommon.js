@@ -1,6 +1,8 @@
+import SwiftEnvironment from "./SwiftEnvironment.js";
+
 export default {
   name: 'SwiftCommon',
-  props: ['padding', 'buttonStyle', 'datePickerStyle'],
+  props: ['padding', 'buttonStyle', 'datePickerStyle', 'environmentObjects', 'environmentValues'],
   emits: ['click'],
   setup(props, { emit }) {
     const slotRef = ref()
@@ -28,6 +30,16 @@ export default {
     }
     // ======= styles =======
 
+    // `.environmentObject(store)`, injected by `@EnvironmentObject` with the type name
+    for (const [type, object] of Object.entries(props.environmentObjects ?? {})) {
+      provide(type, object)
+    }
+
+    // `.environment(\.locale, value)`, it follows the value the parent passes in
+    for (const key of Object.keys(props.environmentValues ?? {})) {
+      SwiftEnvironment.provide(key, computed(() => props.environmentValues[key]))
+    }
+
     const handleClick = (e) => {
       emit('click', e)
     }
@@ -11,4 +11,11 @@ if (fs.existsSync(outdir)) {
   fs.rmSync(outdir, { recursive: true });
 }
 
-generate(content, outdir, false);
\ No newline at end of file
+const result = generate(content, outdir, false);
+
+for (const diagnostic of result.diagnostics) {
+  const position = diagnostic.row === undefined ? "" : ` (${diagnostic.row}:${diagnostic.column})`;
+  console.error(`[${diagnostic.code}] ${diagnostic.message}${position}`);
+}
+
+process.exitCode = result.ok ? 0 : 1;
\ No newline at end of file
@@ -3,4 +3,38 @@
 
 /* auto-generated by NAPI-RS */
 
-export function generate(source: string, outdir: string, verbose: boolean): void
+/** A reported problem, as handed to JS */
+export interface Diagnostic {
+  code: string
+  message: string
+  /** `true` when the build stopped because of it */
+  fatal: boolean
+  /** The struct being converted, if any */
+  view?: string
+  /** The Swift file the problem is in */
+  file?: string
+  row?: number
+  column?: number
+  snippet?: string
+}
+export interface GenerateResult {
+  /** `false` if any diagnostic was reported */
+  ok: boolean
+  diagnostics: Array<Diagnostic>
+  /** Absolute paths of the written files */
+  files: Array<string>
+}
+export interface CompileResult {
+  /** `false` if any diagnostic was reported */
+  ok: boolean
+  diagnostics: Array<Diagnostic>
+  /** Generated files keyed by file name, see [`Artifacts`] */
+  files: Record<string, string>
+}
+export function generate(source: string, outdir: string, verbose: boolean): GenerateResult
+/** Convert several Swift files, or directories of them, into one app */
+export function generateProject(inputs: Array<string>, outdir: string, verbose: boolean): GenerateResult
+/** Same as [`generate`], but returns the generated files instead of writing them */
+export function compile(source: string, verbose: boolean): CompileResult
+/** Same as [`generate_project`], but takes the sources keyed by path and returns the generated files */
+export function compileProject(sources: Record<string, string>, verbose: boolean): CompileResult
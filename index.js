@@ -284,6 +284,9 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { generate } = nativeBinding
+const { generate, generateProject, compile, compileProject } = nativeBinding
 
 module.exports.generate = generate
+module.exports.generateProject = generateProject
+module.exports.compile = compile
+module.exports.compileProject = compileProject
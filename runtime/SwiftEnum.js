@@ -0,0 +1,20 @@
+// Cases of a Swift enum are frozen objects with a `case` tag,
+// the ones with associated values keep them in `associatedValues`
+const SwiftEnum = {
+    // the tag a `switch` over the enum matches
+    caseOf(value) {
+        return value == null ? undefined : value.case;
+    },
+
+    // `if case .loaded = state`
+    matches(value, caseName) {
+        return SwiftEnum.caseOf(value) === caseName;
+    },
+
+    // `case .loaded(let items)`, the associated values in order
+    values(value) {
+        return (value && value.associatedValues) || [];
+    },
+};
+
+export default SwiftEnum;
@@ -0,0 +1,68 @@
+// `@Environment(\.colorScheme)` and the other values a view reads from its environment,
+// `.environment(\.locale, value)` overrides one for the views below it with provide/inject
+const KEY_PREFIX = 'SwiftEnvironment.';
+
+const compactWidth = 768;
+// a phone in landscape
+const compactHeight = 500;
+
+// the values the browser suggests, they are refs so the views follow the changes
+const defaults = {
+    colorScheme() {
+        const media = window.matchMedia('(prefers-color-scheme: dark)');
+        const colorScheme = ref(media.matches ? 'dark' : 'light');
+        media.addEventListener('change', (e) => {
+            colorScheme.value = e.matches ? 'dark' : 'light';
+        });
+        return colorScheme;
+    },
+    locale() {
+        return ref(navigator.language);
+    },
+    horizontalSizeClass() {
+        const sizeClass = () => (window.innerWidth < compactWidth ? 'compact' : 'regular');
+        const horizontalSizeClass = ref(sizeClass());
+        window.addEventListener('resize', () => {
+            horizontalSizeClass.value = sizeClass();
+        });
+        return horizontalSizeClass;
+    },
+    verticalSizeClass() {
+        const sizeClass = () => (window.innerHeight < compactHeight ? 'compact' : 'regular');
+        const verticalSizeClass = ref(sizeClass());
+        window.addEventListener('resize', () => {
+            verticalSizeClass.value = sizeClass();
+        });
+        return verticalSizeClass;
+    },
+    editMode() {
+        return ref('inactive');
+    },
+    dismiss() {
+        return ref(() => window.history.back());
+    },
+};
+
+// every view without an override shares the same default
+const cache = {};
+
+const SwiftEnvironment = {
+    // in `setup()`, the value of the closest `.environment()` above the view or the default
+    read(key) {
+        return inject(KEY_PREFIX + key, () => SwiftEnvironment.defaultValue(key), true);
+    },
+
+    defaultValue(key) {
+        if (!(key in cache)) {
+            cache[key] = defaults[key] ? defaults[key]() : ref(undefined);
+        }
+        return cache[key];
+    },
+
+    // `.environment(\.locale, value)`, `value` is a ref of what the views below read
+    provide(key, value) {
+        provide(KEY_PREFIX + key, value);
+    },
+};
+
+export default SwiftEnvironment;
@@ -0,0 +1,36 @@
+// `==` of Swift values: structs and enums with associated values are new objects every time,
+// they are equal if their properties are
+const SwiftEquatable = {
+    equals(a, b) {
+        if (a === b) {
+            return true;
+        }
+        // `undefined` and `null` are both nil
+        if (a == null || b == null) {
+            return a == b;
+        }
+        if (typeof a !== "object" || typeof b !== "object") {
+            return false;
+        }
+        if (Array.isArray(a) || Array.isArray(b)) {
+            return Array.isArray(a) && Array.isArray(b) && a.length === b.length
+                && a.every((value, i) => SwiftEquatable.equals(value, b[i]));
+        }
+        if (a instanceof Date && b instanceof Date) {
+            return a.getTime() === b.getTime();
+        }
+        if (typeof a.equals === "function") {
+            return a.equals(b);
+        }
+        // a case of another enum, or a model of another type
+        if (Object.getPrototypeOf(a) !== Object.getPrototypeOf(b)) {
+            return false;
+        }
+
+        const keys = Object.keys(a);
+        return keys.length === Object.keys(b).length
+            && keys.every((key) => key in b && SwiftEquatable.equals(a[key], b[key]));
+    },
+};
+
+export default SwiftEquatable;
@@ -0,0 +1,38 @@
+// Swift errors are thrown as JavaScript exceptions, an `enum` conforming to `Error` extends this class
+class SwiftError extends Error {
+    constructor(message) {
+        super(message);
+        this.name = new.target.name;
+    }
+
+    // `try? value`, nil if it throws
+    static attempt(body) {
+        try {
+            return body();
+        } catch {
+            return undefined;
+        }
+    }
+
+    // `try! value`, an error is fatal like in Swift
+    static force(body) {
+        try {
+            return body();
+        } catch (error) {
+            throw new Error(`Fatal error: 'try!' expression unexpectedly raised an error: ${error}`);
+        }
+    }
+
+    // `catch let e as LoadError`, the type is matched by name so a Swift type that isn't declared
+    // in JavaScript, like `DecodingError`, doesn't match instead of failing
+    static is(error, typeName) {
+        for (let proto = error == null ? null : Object.getPrototypeOf(error); proto; proto = Object.getPrototypeOf(proto)) {
+            if (proto.constructor && proto.constructor.name === typeName) {
+                return true;
+            }
+        }
+        return false;
+    }
+}
+
+export default SwiftError;
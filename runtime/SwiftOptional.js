@@ -0,0 +1,28 @@
+// `nil` is `undefined`, a `null` from JavaScript is nil as well
+const SwiftOptional = {
+    // `value!`, a nil value is a fatal error like in Swift
+    unwrap(value) {
+        if (value == null) {
+            throw new Error("Fatal error: Unexpectedly found nil while unwrapping an Optional value");
+        }
+        return value;
+    },
+
+    // `Int(value)`, nil if a string isn't an integer, a number is truncated
+    int(value) {
+        if (typeof value === "number") {
+            return Math.trunc(value);
+        }
+        return /^[+-]?\d+$/.test(value) ? Number(value) : undefined;
+    },
+
+    // `Double(value)`, nil if a string isn't a number
+    double(value) {
+        if (typeof value === "number") {
+            return value;
+        }
+        return /^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$/.test(value) ? Number(value) : undefined;
+    },
+};
+
+export default SwiftOptional;
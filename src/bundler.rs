@@ -1,27 +1,36 @@
-use anyhow::Error;
-use std::{collections::HashMap, path::Path};
-use swc_bundler::{Bundle, Bundler, Load, ModuleData, ModuleRecord};
-use swc_common::{sync::Lrc, FileName, FilePathMapping, SourceMap, Span};
+use anyhow::{anyhow, Error};
+use std::collections::HashMap;
+use swc_bundler::{Bundle, Bundler, Load, ModuleData, ModuleRecord, Resolve};
+use swc_common::{source_map::SourceMapGenConfig, sync::Lrc, FileName, FilePathMapping, Globals, SourceMap, Span, Spanned};
 use swc_ecma_ast::*;
 use swc_ecma_codegen::{
     text_writer::{omit_trailing_semi, JsWriter, WriteJs},
     Emitter,
 };
-use swc_ecma_loader::{
-    resolvers::{lru::CachingResolver, node::NodeModulesResolver},
-    TargetEnv,
-};
+use swc_ecma_loader::resolve::Resolution;
 use swc_ecma_parser::{parse_file_as_module, Syntax};
 
+use crate::error::{CompileError, ErrorCode};
+
+/// Files the bundler can see, keyed by file name, e.g. `app.js`
+pub type VirtualFs = HashMap<String, String>;
+
 pub struct Loader {
     pub cm: Lrc<SourceMap>,
+    pub files: Lrc<VirtualFs>,
 }
 
 impl Load for Loader {
     fn load(&self, f: &FileName) -> Result<ModuleData, Error> {
         let fm = match f {
-            FileName::Real(path) => self.cm.load_file(path)?,
-            _ => unreachable!(),
+            FileName::Custom(name) => {
+                let src = self
+                    .files
+                    .get(name)
+                    .ok_or_else(|| anyhow!("module not found: {}", name))?;
+                self.cm.new_source_file(f.clone(), src.clone())
+            }
+            _ => return Err(anyhow!("unsupported module: {}", f)),
         };
 
         let module = parse_file_as_module(
@@ -31,9 +40,7 @@ impl Load for Loader {
             None,
             &mut vec![],
         )
-        .unwrap_or_else(|err| {
-            panic!("failed to parse module: {:?}\n{}", err, fm.src);
-        });
+        .map_err(|err| anyhow!("failed to parse module {}: {:?}\n{}", f, err, fm.src))?;
 
         Ok(ModuleData {
             fm,
@@ -43,6 +50,40 @@ impl Load for Loader {
     }
 }
 
+/// Parse a generated module before it's bundled, a syntax error of one view would fail the whole bundle
+pub fn check_module(name: &str, code: &str) -> Result<(), CompileError> {
+    let cm = SourceMap::new(FilePathMapping::empty());
+    let fm = cm.new_source_file(FileName::Custom(name.to_string()), code.to_string());
+    parse_file_as_module(&fm, Syntax::Es(Default::default()), EsVersion::Es2020, None, &mut vec![]).map_err(|err| {
+        let loc = cm.lookup_char_pos(err.span().lo);
+        CompileError::new(
+            ErrorCode::Bundle,
+            format!("生成的 {} 第 {} 行不是合法的 JavaScript: {}", name, loc.line, err.kind().msg()),
+        )
+    })?;
+    Ok(())
+}
+
+/// Resolves `./Name.js` imports against the flat [`VirtualFs`]
+pub struct Resolver {
+    pub files: Lrc<VirtualFs>,
+}
+
+impl Resolve for Resolver {
+    fn resolve(&self, _base: &FileName, module_specifier: &str) -> Result<Resolution, Error> {
+        let name = module_specifier.trim_start_matches("./");
+
+        if !self.files.contains_key(name) {
+            return Err(anyhow!("module not found: {}", module_specifier));
+        }
+
+        Ok(Resolution {
+            filename: FileName::Custom(name.to_string()),
+            slug: None,
+        })
+    }
+}
+
 #[allow(dead_code)]
 struct Hook;
 
@@ -82,13 +123,49 @@ impl swc_bundler::Hook for Hook {
     }
 }
 
-fn print_bundles(cm: Lrc<SourceMap>, modules: Vec<Bundle>, minify: bool) -> String {
-    for bundled in modules {
+/// Source maps of the modules, keyed by file name, the bundle maps through them to the Swift source
+pub type InputMaps = HashMap<String, sourcemap::SourceMap>;
+
+/// The bundled code, with its source map if [`bundle`] got the input maps
+pub struct Bundled {
+    pub code: String,
+    pub map: Option<sourcemap::SourceMap>,
+}
+
+struct MapConfig;
+
+impl SourceMapGenConfig for MapConfig {
+    fn file_name_to_source(&self, f: &FileName) -> String {
+        match f {
+            FileName::Custom(name) => name.clone(),
+            _ => f.to_string(),
+        }
+    }
+
+    fn inline_sources_content(&self, _: &FileName) -> bool {
+        true
+    }
+}
+
+fn print_bundles(
+    cm: Lrc<SourceMap>,
+    modules: Vec<Bundle>,
+    minify: bool,
+    input_maps: Option<&InputMaps>,
+) -> Result<Bundled, CompileError> {
+    // TODO: only support 1 bundle
+    if let Some(bundled) = modules.into_iter().next() {
+        let mut mappings = vec![];
         let code = {
             let mut buf = vec![];
 
             {
-                let wr = JsWriter::new(cm.clone(), "\n", &mut buf, None);
+                let wr = JsWriter::new(
+                    cm.clone(),
+                    "\n",
+                    &mut buf,
+                    input_maps.is_some().then_some(&mut mappings),
+                );
                 let mut emitter = Emitter {
                     cfg: swc_ecma_codegen::Config::default().with_minify(true),
                     cm: cm.clone(),
@@ -100,32 +177,95 @@ fn print_bundles(cm: Lrc<SourceMap>, modules: Vec<Bundle>, minify: bool) -> Stri
                     },
                 };
 
-                emitter.emit_module(&bundled.module).unwrap();
+                emitter.emit_module(&bundled.module)?;
             }
 
             String::from_utf8_lossy(&buf).to_string()
         };
 
-        println!("Created output.js ({}kb)", code.len() / 1024);
+        let map = input_maps.map(|input_maps| {
+            let map = cm.build_source_map_with_config(&mappings, None, MapConfig);
+            compose_source_maps(&map, input_maps)
+        });
 
-        // TODO: only support 1 bundle
-        return code;
+        return Ok(Bundled { code, map });
     }
 
-    return String::new();
+    Ok(Bundled {
+        code: String::new(),
+        map: None,
+    })
 }
 
-pub fn bundle(entry: &Path, inline: bool, minify: bool) -> String {
+/// Map the tokens of the modules with an input map further back to where the module was generated from,
+/// e.g. a view component back to its `.swift` file. The other modules, like the runtime helpers, are kept
+fn compose_source_maps(map: &sourcemap::SourceMap, input_maps: &InputMaps) -> sourcemap::SourceMap {
+    let mut builder = sourcemap::SourceMapBuilder::new(map.get_file());
+
+    for token in map.tokens() {
+        let Some(source) = token.get_source() else {
+            continue;
+        };
+
+        let (src_line, src_col, source, contents) = match input_maps.get(source) {
+            Some(input) => {
+                // only the marks on the same line, a token may start in the middle of a generated line
+                let original = input
+                    .lookup_token(token.get_src_line(), token.get_src_col())
+                    .filter(|x| x.get_dst_line() == token.get_src_line());
+                let Some(original) = original else {
+                    continue;
+                };
+                let Some(source) = original.get_source() else {
+                    continue;
+                };
+
+                let contents = input.get_source_contents(original.get_src_id());
+                (original.get_src_line(), original.get_src_col(), source, contents)
+            }
+            None => {
+                let contents = map.get_source_contents(token.get_src_id());
+                (token.get_src_line(), token.get_src_col(), source, contents)
+            }
+        };
+
+        let added = builder.add(
+            token.get_dst_line(),
+            token.get_dst_col(),
+            src_line,
+            src_col,
+            Some(source),
+            None,
+            false,
+        );
+        if contents.is_some() && builder.get_source_contents(added.src_id).is_none() {
+            builder.set_source_contents(added.src_id, contents);
+        }
+    }
+
+    builder.into_sourcemap()
+}
+
+/// Bundle `entry` and everything it imports, all of them read from `files`.
+/// With `input_maps`, the bundle gets a source map, see [`compose_source_maps`]
+pub fn bundle(
+    entry: &str,
+    files: VirtualFs,
+    inline: bool,
+    minify: bool,
+    input_maps: Option<&InputMaps>,
+) -> Result<Bundled, CompileError> {
     let cm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
-    let globals = Box::leak(Box::default());
+    let files = Lrc::new(files);
+    let globals = Globals::default();
     let mut bundler = Bundler::new(
-        globals,
+        &globals,
         cm.clone(),
-        Loader { cm: cm.clone() },
-        CachingResolver::new(
-            4096,
-            NodeModulesResolver::new(TargetEnv::Node, Default::default(), true),
-        ),
+        Loader {
+            cm: cm.clone(),
+            files: files.clone(),
+        },
+        Resolver { files },
         swc_bundler::Config {
             require: false,
             disable_inliner: !inline,
@@ -139,16 +279,13 @@ pub fn bundle(entry: &Path, inline: bool, minify: bool) -> String {
     );
 
     let mut entries = HashMap::new();
-    entries.insert("main".to_string(), FileName::Real(entry.to_path_buf()));
+    entries.insert("main".to_string(), FileName::Custom(entry.to_string()));
 
     let modules = bundler
         .bundle(entries)
-        .map_err(|err| println!("{:?}", err))
-        .unwrap();
+        .map_err(|err| CompileError::new(ErrorCode::Bundle, format!("{:#}", err)))?;
     // clean modules
     drop(bundler);
 
-    println!("Bundled as {} modules", modules.len());
-
-    return print_bundles(cm, modules, minify);
+    print_bundles(cm, modules, minify, input_maps)
 }
@@ -1,14 +1,18 @@
+use tree_sitter::Point;
+
+use crate::error::{NodeExt, Result};
+use crate::mapping::MappedCode;
 #[allow(unused_imports)]
 use crate::utils::log_node_tree;
 
-use super::object::callexp2object_with_context;
+use super::object::callexp2mapped;
 
 #[derive(Debug)]
 enum ItemType {
     Literal { value: String },
     PrefixValue { value: String },
-    Object { code: String },
-    Sub { code: String },
+    Object { code: MappedCode },
+    Sub { code: MappedCode },
 }
 
 #[derive(Default, Debug)]
@@ -16,42 +20,49 @@ struct State {
     source: String,
     context: String,
     obj_ctx: Vec<String>,
-    args: Vec<ItemType>,
+    /// With the position of the item
+    args: Vec<(ItemType, Point)>,
 
     in_root: bool,
 }
 
 impl State {
-    fn collect(&mut self, node: &tree_sitter::Node) {
-        let node_code = node.utf8_text(self.source.as_bytes()).unwrap();
+    fn collect(&mut self, node: &tree_sitter::Node) -> Result<()> {
+        let node_code = node.text(&self.source)?;
 
         if !self.in_root {
             match node.kind() {
                 "prefix_expression" => {
-                    let name = node.child(1).unwrap();
-                    let name_code = name.utf8_text(self.source.as_bytes()).unwrap();
-
-                    self.args.push(ItemType::PrefixValue {
-                        value: name_code.to_string(),
-                    });
-                    return;
+                    let name = node.child_at(1, &self.source)?;
+                    let name_code = name.text(&self.source)?;
+
+                    self.args.push((
+                        ItemType::PrefixValue {
+                            value: name_code.to_string(),
+                        },
+                        node.start_position(),
+                    ));
+                    return Ok(());
                 }
                 "call_expression" => {
-                    let code = callexp2object_with_context(&node, &self.source, self.obj_ctx.clone());
-                    self.args.push(ItemType::Object { code });
-                    return;
+                    let code = callexp2mapped(node, &self.source, self.obj_ctx.clone())?;
+                    self.args.push((ItemType::Object { code }, node.start_position()));
+                    return Ok(());
                 }
                 "array_literal" => {
-                    let code = array2js_call(&node, &self.source, self.context.clone());
-                    self.args.push(ItemType::Sub { code });
-                    return;
+                    let code = array2mapped(node, &self.source, self.context.clone(), vec![])?;
+                    self.args.push((ItemType::Sub { code }, node.start_position()));
+                    return Ok(());
                 }
                 _ => {
                     // TODO: 细致处理
                     if node.kind().ends_with("_literal") {
-                        self.args.push(ItemType::Literal {
-                            value: node_code.to_string(),
-                        });
+                        self.args.push((
+                            ItemType::Literal {
+                                value: node_code.to_string(),
+                            },
+                            node.start_position(),
+                        ));
                     }
                 }
             }
@@ -60,64 +71,80 @@ impl State {
         }
 
         for i in 0..node.child_count() {
-            let child = node.child(i).unwrap();
-            self.collect(&child);
+            let child = node.child_at(i, &self.source)?;
+            self.collect(&child)?;
         }
+
+        Ok(())
     }
 
-    fn generate(self) -> String {
-        let mut out = format!("[");
+    fn generate(self) -> MappedCode {
+        let mut out = MappedCode::from(String::from("["));
+
+        for (i, (arg, point)) in self.args.into_iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.mark(point);
 
-        for arg in &self.args {
             match arg {
                 ItemType::Literal { value } => {
-                    out.push_str(&format!("{}, ", value));
+                    out.push_str(&value);
                 }
                 ItemType::PrefixValue { value } => {
-                    out.push_str(&format!("{}.{}, ", self.context, value));
+                    out.push_str(&format!("{}.{}", self.context, value));
                 }
                 ItemType::Object { code } => {
-                    out.push_str(&format!("{}, ", code));
+                    out.push_mapped(code);
                 }
                 ItemType::Sub { code } => {
-                    out.push_str(&format!("{}, ", code));
+                    out.push_mapped(code);
                 }
             }
         }
 
-        if self.args.len() > 0 {
-            out.pop();
-            out.pop();
-        }
-
         out.push_str("]");
         out
     }
 }
 
 #[allow(dead_code)]
-pub fn array2js_call(node: &tree_sitter::Node, source: &String, context: String) -> String {
-    assert_eq!(node.kind(), "array_literal");
-    let mut state = State::default();
-    state.source = source.clone();
-    state.context = context;
-    state.in_root = true;
-    state.collect(node);
-    state.generate()
+pub fn array2js_call(node: &tree_sitter::Node, source: &str, context: String) -> Result<String> {
+    array2js_call_with_obj_context(node, source, context, vec![])
 }
 
 #[allow(dead_code)]
-pub fn array2js_call_with_obj_context(node: &tree_sitter::Node, source: &String, context: String, obj_ctx: Vec<String>) -> String {
-    assert_eq!(node.kind(), "array_literal");
-    let mut state = State::default();
-    state.source = source.clone();
-    state.context = context;
-    state.obj_ctx = obj_ctx;
-    state.in_root = true;
-    state.collect(node);
-    state.generate()
+pub fn array2js_call_with_obj_context(
+    node: &tree_sitter::Node,
+    source: &str,
+    context: String,
+    obj_ctx: Vec<String>,
+) -> Result<String> {
+    Ok(array2mapped(node, source, context, obj_ctx)?.code)
 }
 
+/// Same as [`array2js_call_with_obj_context`], each item is mapped to its position in `source`
+pub fn array2mapped(
+    node: &tree_sitter::Node,
+    source: &str,
+    context: String,
+    obj_ctx: Vec<String>,
+) -> Result<MappedCode> {
+    node.expect_kind("array_literal", source)?;
+    let mut state = State {
+        source: source.to_string(),
+        context,
+        obj_ctx,
+        in_root: true,
+        ..Default::default()
+    };
+    state.collect(node)?;
+
+    let mut out = MappedCode::new();
+    out.mark(node.start_position());
+    out.push_mapped(state.generate());
+    Ok(out)
+}
 
 #[cfg(test)]
 mod test {
@@ -142,7 +169,7 @@ mod test {
         let tree = parser.parse(SOURCE3, None).unwrap();
         let node = tree.root_node().child(0).unwrap();
 
-        let result = super::array2js_call(&node, &SOURCE3.to_string(), "DatePicker".to_string());
+        let result = super::array2js_call(&node, SOURCE3, "DatePicker".to_string()).unwrap();
         // println!("result: {:?}", result);
         assert_eq!(result, "[DatePicker.red, DatePicker.blue, SwiftColor({red: 22})]".to_string());
     }
@@ -0,0 +1,841 @@
+//! Swift `enum` 转换为 JavaScript class：没有关联值的 case 是一个冻结的实例，
+//! 冻结后 Vue 不会把它包装成 Proxy，所以 `===` 比较的都是同一个对象；
+//! 有关联值的 case 是静态方法，返回带 `case` 标签和 `associatedValues` 的冻结对象，
+//! `switch` 和 `if case` 按标签匹配，再从 `associatedValues` 解构出绑定的值
+
+use std::rc::Rc;
+
+use tree_sitter::Node;
+
+use crate::error::{CompileError, NodeExt, Result};
+use crate::paser::Extension;
+use crate::project::SymbolTable;
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+use crate::utils::find_first_node;
+
+use super::expr::ExprFn;
+use super::statement::{rewrite_expr, StatementWriter};
+use super::types::swift_type2ts;
+
+/// Raw types of `enum Flavor: String`, with the TypeScript type of `rawValue`
+const RAW_TYPES: [(&str, &str); 13] = [
+    ("String", "string"),
+    ("Character", "string"),
+    ("Int", "number"),
+    ("Int8", "number"),
+    ("Int16", "number"),
+    ("Int32", "number"),
+    ("Int64", "number"),
+    ("UInt", "number"),
+    ("UInt8", "number"),
+    ("UInt16", "number"),
+    ("UInt32", "number"),
+    ("UInt64", "number"),
+    ("Double", "number"),
+];
+
+/// Statement kinds a computed property or a closure with a single statement doesn't return
+pub(super) const STATEMENT_KINDS: [&str; 10] = [
+    "switch_statement",
+    "if_statement",
+    "guard_statement",
+    "control_transfer_statement",
+    "assignment",
+    "property_declaration",
+    "for_statement",
+    "while_statement",
+    "repeat_while_statement",
+    "do_statement",
+];
+
+/// `.loaded(let items)` of `case .loaded(let items):` or `if case let .loaded(items) = state`
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct CasePattern {
+    pub(super) case: String,
+    /// Names bound to the associated values in order, `None` for `_`
+    bindings: Vec<Option<String>>,
+}
+
+impl CasePattern {
+    /// `nodes` are the children of a pattern, `None` if they don't match an enum case
+    pub(super) fn parse(nodes: &[Node], source: &str) -> Result<Option<Self>> {
+        let mut rest = nodes.iter().skip_while(|x| x.kind() == "value_binding_pattern");
+
+        let case = match (rest.next(), rest.next()) {
+            (Some(dot), Some(name)) if dot.kind() == "." && name.kind() == "simple_identifier" => {
+                name.text(source)?.to_string()
+            }
+            _ => return Ok(None),
+        };
+
+        let mut bindings = Vec::new();
+        for node in rest {
+            // the others are the labels and parentheses
+            if node.kind() != "pattern" {
+                continue;
+            }
+
+            if find_first_node(*node, "wildcard_pattern").is_some() {
+                bindings.push(None);
+            } else if let Some(name) = find_first_node(*node, "simple_identifier") {
+                bindings.push(Some(name.text(source)?.to_string()));
+            } else {
+                return Err(CompileError::unsupported("目前只支持绑定关联值的模式", node, source));
+            }
+        }
+
+        Ok(Some(Self { case, bindings }))
+    }
+
+    /// The names bound to the associated values
+    pub(super) fn names(&self) -> impl Iterator<Item = &String> {
+        self.bindings.iter().flatten()
+    }
+
+    /// `const [items] = SwiftEnum.values(state);`, if anything is bound
+    pub(super) fn destructure(&self, subject: &str) -> Option<String> {
+        let last = self.bindings.iter().rposition(|x| x.is_some())?;
+        let names = self.bindings[..=last]
+            .iter()
+            .map(|x| x.clone().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(format!("const [{}] = SwiftEnum.values({});", names, subject))
+    }
+}
+
+fn all_children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).collect()
+}
+
+fn named_children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|x| !x.kind().ends_with("comment"))
+        .collect()
+}
+
+/// A `switch` matching the `case` tags if every pattern is an enum case, otherwise the values
+pub fn switch2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = named_children(node);
+    let Some(subject) = children.first() else {
+        return Err(CompileError::unexpected("expect a switch subject", node, source));
+    };
+    let subject = writer.expr(subject)?;
+    let entries = children.iter().filter(|x| x.kind() == "switch_entry").collect::<Vec<_>>();
+
+    let mut cases = Vec::new();
+    for entry in &entries {
+        for pattern in named_children(entry).iter().filter(|x| x.kind() == "switch_pattern") {
+            let nodes = pattern.named_child(0).map(|x| all_children(&x)).unwrap_or_default();
+            cases.push(CasePattern::parse(&nodes, source)?);
+        }
+    }
+    let tagged = cases.iter().all(|x| x.is_some());
+    let mut cases = cases.into_iter().flatten();
+
+    let mut out = if tagged {
+        format!("{:indent$}switch (SwiftEnum.caseOf({})) {{\n", "", subject, indent = indent)
+    } else {
+        format!("{:indent$}switch ({}) {{\n", "", subject, indent = indent)
+    };
+
+    for entry in entries {
+        let mut destructure = None;
+        let mut statements = None;
+
+        for item in named_children(entry) {
+            match item.kind() {
+                "switch_pattern" if tagged => {
+                    let Some(case) = cases.next() else {
+                        continue;
+                    };
+                    out.push_str(&format!("{:indent$}case \"{}\":\n", "", case.case, indent = indent + 4));
+                    // the first pattern binds the names, the others bind the same ones
+                    destructure = destructure.or_else(|| case.destructure(&subject));
+                }
+                "switch_pattern" => {
+                    let pattern = writer.expr(&item)?;
+                    out.push_str(&format!("{:indent$}case {}:\n", "", pattern, indent = indent + 4));
+                }
+                "default_keyword" => {
+                    out.push_str(&format!("{:indent$}default:\n", "", indent = indent + 4));
+                }
+                "statements" => statements = Some(item),
+                _ => {}
+            }
+        }
+
+        // a block keeps the bound names in the case
+        let body_indent = indent + 8;
+        if destructure.is_some() {
+            out.truncate(out.trim_end().len());
+            out.push_str(" {\n");
+        }
+        if let Some(destructure) = &destructure {
+            out.push_str(&format!("{:indent$}{}\n", "", destructure, indent = body_indent));
+        }
+
+        let mut returned = false;
+        if let Some(statements) = statements {
+            returned = named_children(&statements)
+                .last()
+                .map(|x| x.kind() == "control_transfer_statement")
+                .unwrap_or(false);
+            out.push_str(&writer.statements(&statements, body_indent)?);
+        }
+        // Swift doesn't fall through
+        if !returned {
+            out.push_str(&format!("{:indent$}break;\n", "", indent = body_indent));
+        }
+        if destructure.is_some() {
+            out.push_str(&format!("{:indent$}}}\n", "", indent = indent + 4));
+        }
+    }
+
+    out.push_str(&format!("{:indent$}}}\n", "", indent = indent));
+    Ok(out)
+}
+
+/// `if case let .failed(message) = state { ... }`, `None` for other `if` statements
+pub fn if_case2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<Option<String>> {
+    let source = writer.source();
+    let children = all_children(node);
+
+    if children.get(1).map(|x| x.kind()) != Some("case") {
+        return Ok(None);
+    }
+    let Some(eq) = children.iter().position(|x| x.kind() == "=") else {
+        return Ok(None);
+    };
+    // `if case .a = x, condition` has more conditions
+    let (Some(value), Some(body)) = (children.get(eq + 1), children.get(eq + 2)) else {
+        return Ok(None);
+    };
+    if body.kind() != "statements" && body.kind() != "{" {
+        return Ok(None);
+    }
+    let Some(pattern) = CasePattern::parse(&children[2..eq], source)? else {
+        return Ok(None);
+    };
+
+    let value = writer.expr(value)?;
+    let mut out = format!(
+        "{:indent$}if (SwiftEnum.matches({}, \"{}\")) {{\n",
+        "",
+        value,
+        pattern.case,
+        indent = indent
+    );
+    if let Some(destructure) = pattern.destructure(&value) {
+        out.push_str(&format!("{:indent$}{}\n", "", destructure, indent = indent + 4));
+    }
+
+    let mut rest = children[eq + 2..].iter();
+    if let Some(statements) = rest.find(|x| x.kind() == "statements") {
+        out.push_str(&writer.statements(statements, indent + 4)?);
+    }
+    out.push_str(&format!("{:indent$}}}", "", indent = indent));
+
+    if rest.any(|x| x.kind() == "else") {
+        match rest.find(|x| x.kind() == "statements" || x.kind() == "if_statement") {
+            Some(statements) if statements.kind() == "statements" => {
+                out.push_str(" else {\n");
+                out.push_str(&writer.statements(statements, indent + 4)?);
+                out.push_str(&format!("{:indent$}}}", "", indent = indent));
+            }
+            Some(if_statement) => {
+                out.push_str(" else ");
+                out.push_str(writer.statement(if_statement, indent)?.trim_start());
+            }
+            // `else {}`
+            None => {}
+        }
+    }
+
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(Some(out))
+}
+
+/// `Type.case(a, b)` of `Type.case(x: a, y: b)`, or of `.case(a, b)` where the type `ty` is known to be an enum.
+/// `expr` converts the associated values. `None` if `node` doesn't construct one of the `enums`
+pub fn construct2js(node: &Node, source: &str, ty: Option<&str>, enums: &[String], expr: ExprFn) -> Result<Option<String>> {
+    let ty = ty.filter(|x| enums.iter().any(|e| e == x));
+
+    // `Type.case` or `.case`
+    let case_of = |callee: &Node| -> Result<Option<(String, String)>> {
+        let children = all_children(callee);
+        match (callee.kind(), children.as_slice()) {
+            ("prefix_expression", [dot, name]) if dot.kind() == "." => {
+                Ok(ty.map(|ty| (ty.to_string(), String::new())).and_then(|(ty, _)| {
+                    name.text(source).ok().map(|name| (ty, name.to_string()))
+                }))
+            }
+            ("navigation_expression", [target, suffix]) if target.kind() == "simple_identifier" => {
+                let target = target.text(source)?;
+                if !enums.iter().any(|x| x == target) {
+                    return Ok(None);
+                }
+                let Some(name) = suffix.named_child(0) else {
+                    return Ok(None);
+                };
+                Ok(Some((target.to_string(), name.text(source)?.to_string())))
+            }
+            _ => Ok(None),
+        }
+    };
+
+    match node.kind() {
+        "prefix_expression" => Ok(case_of(node)?.map(|(ty, case)| format!("{}.{}", ty, case))),
+        "call_expression" => {
+            let Some((ty, case)) = node.named_child(0).map(|x| case_of(&x)).transpose()?.flatten() else {
+                return Ok(None);
+            };
+
+            let mut args = Vec::new();
+            if let Some(arguments) = find_first_node(*node, "value_arguments") {
+                for argument in named_children(&arguments) {
+                    // the labels are dropped, the values are passed in the declared order
+                    let Some(value) = named_children(&argument).into_iter().last() else {
+                        continue;
+                    };
+                    args.push(expr(&value)?);
+                }
+            }
+
+            Ok(Some(format!("{}.{}({})", ty, case, args.join(", "))))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug)]
+struct Case<'a> {
+    name: String,
+    /// JavaScript literal of the raw value
+    raw_value: Option<String>,
+    /// Labels and types of the associated values
+    values: Option<Vec<(Option<String>, Node<'a>)>>,
+}
+
+impl Case<'_> {
+    /// Names of the parameters of its static method, the labels or `arg0`, `arg1`...
+    fn params(&self) -> Vec<String> {
+        self.values
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, (label, _))| match label.as_deref() {
+                Some(label) if label != "_" => label.to_string(),
+                _ => format!("arg{}", i),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Computed<'a> {
+    name: String,
+    /// Name of the declared type, `.case` in the body refers to it if it's an enum
+    ty: Option<String>,
+    statements: Node<'a>,
+    /// Source of the file it's declared in, an extension may be in another file
+    source: Rc<str>,
+}
+
+#[derive(Default, Debug)]
+struct State<'a> {
+    /// Source of the declaration being collected
+    source: Rc<str>,
+    name: String,
+    /// TypeScript type of `rawValue`
+    raw_type: Option<&'static str>,
+    case_iterable: bool,
+    /// Conforms to `Error`, the cases are thrown as exceptions
+    error: bool,
+    cases: Vec<Case<'a>>,
+    computed: Vec<Computed<'a>>,
+    /// Enums `.case` may refer to
+    enums: Vec<String>,
+    /// Types the TypeScript output may refer to
+    known_types: Vec<String>,
+    symbols: SymbolTable,
+}
+
+impl<'a> State<'a> {
+    fn collect(&mut self, node: &Node<'a>) -> Result<()> {
+        let source = self.source.clone();
+        let source = &*source;
+
+        for i in 0..node.child_count() {
+            let child = node.child_at(i, source)?;
+
+            match child.kind() {
+                "type_identifier" if self.name.is_empty() => {
+                    self.name = child.text(source)?.to_string();
+                }
+                "inheritance_specifier" => {
+                    let name = child.text(source)?;
+                    if name == "CaseIterable" {
+                        self.case_iterable = true;
+                    }
+                    if name == "Error" || name == "LocalizedError" {
+                        self.error = true;
+                    }
+                    // only the first one can be the raw type
+                    if self.cases.is_empty() && self.raw_type.is_none() {
+                        self.raw_type = RAW_TYPES.iter().find(|(x, _)| *x == name).map(|(_, ts)| *ts);
+                    }
+                }
+                // the body of an extension is a `class_body`
+                "enum_class_body" | "class_body" => self.collect_body(&child)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn collect_body(&mut self, node: &Node<'a>) -> Result<()> {
+        let source = self.source.clone();
+        let source = &*source;
+
+        for i in 0..node.child_count() {
+            let child = node.child_at(i, source)?;
+
+            match child.kind() {
+                "enum_entry" => {
+                    for j in 0..child.child_count() {
+                        let item = child.child_at(j, source)?;
+                        match item.kind() {
+                            "simple_identifier" => {
+                                let name = item.text(source)?.to_string();
+                                let raw_value = self.default_raw_value(&name);
+                                self.cases.push(Case {
+                                    name,
+                                    raw_value,
+                                    values: None,
+                                });
+                            }
+                            "enum_type_parameters" => {
+                                let mut values = Vec::new();
+                                let mut label = None;
+                                for param in named_children(&item) {
+                                    match param.kind() {
+                                        "simple_identifier" => label = Some(param.text(source)?.to_string()),
+                                        // a default value of the associated value
+                                        kind if kind.ends_with("_literal") || kind.ends_with("_expression") => {}
+                                        _ => values.push((label.take(), param)),
+                                    }
+                                }
+                                if let Some(case) = self.cases.last_mut() {
+                                    case.values = Some(values);
+                                }
+                            }
+                            kind if kind.ends_with("_literal") => {
+                                if let Some(case) = self.cases.last_mut() {
+                                    case.raw_value = Some(item.text(source)?.to_string());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                "property_declaration" => {
+                    let children = all_children(&child);
+
+                    let name = children.iter().find(|x| x.kind() == "pattern");
+                    let statements = children
+                        .iter()
+                        .find(|x| x.kind() == "computed_property")
+                        .and_then(|x| named_children(x).into_iter().find(|x| x.kind() == "statements"));
+                    let ty = children
+                        .iter()
+                        .find(|x| x.kind() == "type_annotation")
+                        .map(|x| x.text(source).map(|x| x.trim_start_matches(':').trim().to_string()))
+                        .transpose()?;
+
+                    match (name, statements) {
+                        (Some(name), Some(statements)) => self.computed.push(Computed {
+                            name: name.text(source)?.to_string(),
+                            ty,
+                            statements,
+                            source: self.source.clone(),
+                        }),
+                        _ => {
+                            return Err(CompileError::unsupported(
+                                "枚举中只支持计算属性",
+                                &child,
+                                source,
+                            ))
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A `String` case is its own name, an `Int` case is one more than the previous one
+    fn default_raw_value(&self, name: &str) -> Option<String> {
+        match self.raw_type? {
+            "string" => Some(format!("\"{}\"", name)),
+            _ => {
+                let previous = match self.cases.last() {
+                    Some(case) => case.raw_value.as_deref()?.parse::<i64>().ok()?,
+                    None => -1,
+                };
+                Some((previous + 1).to_string())
+            }
+        }
+    }
+
+    fn generate(&self, typescript: bool, export: bool) -> Result<String> {
+        let name = &self.name;
+        let export = if export { "export " } else { "" };
+        let typed = |ty: &str| if typescript { format!(": {}", ty) } else { String::new() };
+        let modifier = if typescript { "static readonly" } else { "static" };
+        let has_values = self.cases.iter().any(|x| x.values.is_some());
+        let raw_type = self.raw_type.unwrap_or("string");
+
+        let extends = if self.error { " extends SwiftError" } else { "" };
+        let mut out = format!("{}class {}{} {{\n", export, name, extends);
+
+        for case in &self.cases {
+            if case.values.is_some() {
+                continue;
+            }
+            let args = match &case.raw_value {
+                Some(raw_value) if self.raw_type.is_some() => format!("\"{}\", {}", case.name, raw_value),
+                _ => format!("\"{}\"", case.name),
+            };
+            out.push_str(&format!("    {} {} = new {}({});\n", modifier, case.name, name, args));
+        }
+        if self.case_iterable {
+            let cases = self
+                .cases
+                .iter()
+                .map(|x| format!("{}.{}", name, x.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("    {} allCases = [{}];\n", modifier, cases));
+        }
+
+        for case in self.cases.iter().filter(|x| x.values.is_some()) {
+            let params = case.params();
+            let typed_params = params
+                .iter()
+                .zip(case.values.iter().flatten())
+                .map(|(param, (_, ty))| {
+                    let ty = if typescript {
+                        swift_type2ts(ty, &self.source, &self.known_types)?
+                    } else {
+                        String::new()
+                    };
+                    Ok(format!("{}{}", param, if typescript { format!(": {}", ty) } else { ty }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            out.push_str(&format!(
+                "\n    static {}({}){} {{\n        return new {}(\"{}\", [{}]);\n    }}\n",
+                case.name,
+                typed_params.join(", "),
+                typed(name),
+                name,
+                case.name,
+                params.join(", ")
+            ));
+        }
+        out.push('\n');
+
+        if typescript {
+            out.push_str("    readonly case: string;\n");
+            if self.raw_type.is_some() {
+                out.push_str(&format!("    readonly rawValue: {};\n", raw_type));
+            }
+            if has_values {
+                out.push_str("    readonly associatedValues: any[];\n");
+            }
+            out.push('\n');
+        }
+
+        let (field, param) = if has_values {
+            ("associatedValues", format!("associatedValues{} = []", typed("any[]")))
+        } else {
+            ("rawValue", format!("rawValue{}", typed(raw_type)))
+        };
+        if has_values || self.raw_type.is_some() {
+            out.push_str(&format!("    constructor(caseName{}, {}) {{\n", typed("string"), param));
+        } else {
+            out.push_str(&format!("    constructor(caseName{}) {{\n", typed("string")));
+        }
+        if self.error {
+            out.push_str("        super(caseName);\n");
+        }
+        out.push_str("        this.case = caseName;\n");
+        if has_values || self.raw_type.is_some() {
+            out.push_str(&format!("        this.{} = {};\n", field, field));
+        }
+        out.push_str("        Object.freeze(this);\n");
+        out.push_str("    }\n");
+
+        for computed in &self.computed {
+            let writer = BodyWriter { state: self, computed };
+            out.push_str(&format!("\n    get {}() {{\n", computed.name));
+
+            // a single expression is returned, like Swift does for a computed property
+            let statements = named_children(&computed.statements);
+            match statements.as_slice() {
+                [expression] if !STATEMENT_KINDS.contains(&expression.kind()) => {
+                    out.push_str(&format!("        return {};\n", writer.expr(expression)?));
+                }
+                _ => out.push_str(&writer.statements(&computed.statements, 8)?),
+            }
+            out.push_str("    }\n");
+        }
+
+        out.push_str("\n    toString() {\n        return this.case;\n    }\n");
+        out.push_str("}\n");
+        Ok(out)
+    }
+}
+
+/// Writes the body of a computed property
+struct BodyWriter<'s, 'a> {
+    state: &'s State<'a>,
+    computed: &'s Computed<'a>,
+}
+
+impl StatementWriter for BodyWriter<'_, '_> {
+    fn source(&self) -> &str {
+        &self.computed.source
+    }
+
+    /// The Swift expression as is, except `self`, members without `self.`, `.case` and `==`
+    fn expr(&self, node: &Node) -> Result<String> {
+        let state = self.state;
+        let source = &*self.computed.source;
+        // `.nuts` of `var suggestedTopping: Topping`, or a case of this enum
+        let ty = self
+            .computed
+            .ty
+            .as_ref()
+            .filter(|x| state.enums.contains(x))
+            .unwrap_or(&state.name);
+
+        rewrite_expr(node, source, &mut |node| {
+            if let Some(code) = super::expr::swift2js(node, source, &state.symbols, &|x| self.expr(x), &|_| Ok(None))? {
+                return Ok(Some(code));
+            }
+            let parent_kind = node.parent().map(|x| x.kind());
+
+            Ok(match node.kind() {
+                "self_expression" => Some("this".to_string()),
+                "prefix_expression" | "call_expression" => construct2js(node, source, Some(ty), &state.enums, &|x| self.expr(x))?,
+                "lambda_literal" => Some(super::statement::closure2js(self, node, None)?),
+                "simple_identifier" if parent_kind != Some("navigation_suffix") => {
+                    let name = node.text(source)?;
+                    let member = name == "rawValue" && state.raw_type.is_some()
+                        || state.computed.iter().any(|x| x.name == name);
+                    member.then(|| format!("this.{}", name))
+                }
+                _ => None,
+            })
+        })
+    }
+
+    fn statement(&self, node: &Node, indent: usize) -> Result<String> {
+        super::statement::statement2js(self, node, indent)
+    }
+}
+
+/// A class with a frozen instance or a static method per case, with the computed properties of the `extensions`. A `.case` may refer to the enums of `symbols`, the TypeScript output to all of its types
+pub fn enum2js<'a>(
+    node: &Node<'a>,
+    source: &str,
+    extensions: &[Extension<'a>],
+    symbols: &SymbolTable,
+    typescript: bool,
+    export: bool,
+) -> Result<String> {
+    node.expect_kind("class_declaration", source)?;
+    let mut state = State {
+        source: source.into(),
+        enums: symbols.enums.iter().cloned().collect(),
+        known_types: symbols.type_names(),
+        symbols: symbols.clone(),
+        ..Default::default()
+    };
+    state.collect(node)?;
+    for ext in extensions {
+        state.source = ext.source.clone();
+        state.collect(&ext.node)?;
+    }
+    // the cases are declared by `node`
+    state.source = source.into();
+    state.generate(typescript, export)
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::project::SymbolTable;
+
+    const SOURCE: &str = r#"
+enum Flavor: String, CaseIterable, Identifiable {
+    case chocolate
+    case vanilla = "v"
+
+    var id: String { self.rawValue }
+
+    var suggestedTopping: Topping {
+        switch self {
+        case .chocolate: return .nuts
+        default: return .cookies
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn test_enum2js() {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(SOURCE, None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        let symbols = SymbolTable {
+            enums: ["Flavor".to_string(), "Topping".to_string()].into(),
+            ..Default::default()
+        };
+        let result = super::enum2js(&node, SOURCE, &[], &symbols, false, true).unwrap();
+        assert_eq!(
+            result,
+            r#"export class Flavor {
+    static chocolate = new Flavor("chocolate", "chocolate");
+    static vanilla = new Flavor("vanilla", "v");
+    static allCases = [Flavor.chocolate, Flavor.vanilla];
+
+    constructor(caseName, rawValue) {
+        this.case = caseName;
+        this.rawValue = rawValue;
+        Object.freeze(this);
+    }
+
+    get id() {
+        return this.rawValue;
+    }
+
+    get suggestedTopping() {
+        switch (SwiftEnum.caseOf(this)) {
+            case "chocolate":
+                return Topping.nuts;
+            default:
+                return Topping.cookies;
+        }
+    }
+
+    toString() {
+        return this.case;
+    }
+}
+"#
+        );
+    }
+
+    const ASSOCIATED_SOURCE: &str = r#"
+enum LoadState {
+    case idle
+    case loaded(items: [String], Int)
+    case failed(String)
+
+    var summary: String {
+        switch self {
+        case .idle:
+            return "idle"
+        case .loaded(let items, _):
+            return items.join(", ")
+        case let .failed(message):
+            return message
+        }
+    }
+
+    var isIdle: Bool {
+        if case .idle = self {
+            return true
+        } else {
+            return false
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn test_associated_values() {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(ASSOCIATED_SOURCE, None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        let symbols = SymbolTable {
+            enums: ["LoadState".to_string()].into(),
+            ..Default::default()
+        };
+        let result = super::enum2js(&node, ASSOCIATED_SOURCE, &[], &symbols, false, true).unwrap();
+        assert_eq!(
+            result,
+            r#"export class LoadState {
+    static idle = new LoadState("idle");
+
+    static loaded(items, arg1) {
+        return new LoadState("loaded", [items, arg1]);
+    }
+
+    static failed(arg0) {
+        return new LoadState("failed", [arg0]);
+    }
+
+    constructor(caseName, associatedValues = []) {
+        this.case = caseName;
+        this.associatedValues = associatedValues;
+        Object.freeze(this);
+    }
+
+    get summary() {
+        switch (SwiftEnum.caseOf(this)) {
+            case "idle":
+                return "idle";
+            case "loaded": {
+                const [items] = SwiftEnum.values(this);
+                return items.join(", ");
+            }
+            case "failed": {
+                const [message] = SwiftEnum.values(this);
+                return message;
+            }
+        }
+    }
+
+    get isIdle() {
+        if (SwiftEnum.matches(this, "idle")) {
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    toString() {
+        return this.case;
+    }
+}
+"#
+        );
+    }
+}
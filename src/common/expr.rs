@@ -0,0 +1,453 @@
+//! Swift 特有的表达式：`.count`/`.isEmpty`/`.description` 等属性、数组和字符串的常用方法、
+//! 强制解包、`try?`/`try!`、和 `nil` 比较、区间、key path 和字符串插值，转换为模板和脚本中都能用的 JavaScript。
+//! 其他表达式的语法和 JavaScript 相同，由各个 writer 原样输出
+
+use tree_sitter::Node;
+
+use crate::error::{NodeExt, Result};
+use crate::project::SymbolTable;
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+
+/// Converts a sub-expression with the mapping of the writer, e.g. `.value` of the refs
+pub type ExprFn<'e> = &'e dyn Fn(&Node) -> Result<String>;
+
+/// The Swift type of a sub-expression if the writer knows it, e.g. `Counter` of `@StateObject var counter = Counter()`
+pub type TypeFn<'t> = &'t dyn Fn(&Node) -> Result<Option<String>>;
+
+fn named_children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|x| !x.kind().ends_with("comment"))
+        .collect()
+}
+
+/// The values of the arguments of a call in order with the trailing closures after them, the labels are dropped.
+/// Whether the last one is a closure
+fn arguments(node: &Node, expr: ExprFn) -> Result<(Vec<String>, bool)> {
+    let mut args = Vec::new();
+    let mut closure = false;
+    let Some(suffix) = node.named_child(1).filter(|x| x.kind() == "call_suffix") else {
+        return Ok((args, closure));
+    };
+    for child in named_children(&suffix) {
+        match child.kind() {
+            "value_arguments" => {
+                for argument in named_children(&child) {
+                    if let Some(value) = named_children(&argument).last() {
+                        args.push(expr(value)?);
+                        closure = value.kind() == "lambda_literal";
+                    }
+                }
+            }
+            "lambda_literal" => {
+                args.push(expr(&child)?);
+                closure = true;
+            }
+            _ => {}
+        }
+    }
+    Ok((args, closure))
+}
+
+/// `f(a) { ... }` or `f { ... } label: { ... }`
+fn has_trailing_closure(node: &Node) -> bool {
+    let suffix = node.named_child(1).filter(|x| x.kind() == "call_suffix");
+    suffix.is_some_and(|x| named_children(&x).iter().any(|x| x.kind() == "lambda_literal"))
+}
+
+/// The label of the first argument, `contentsOf` of `append(contentsOf: items)`
+fn first_label(node: &Node, source: &str) -> Result<Option<String>> {
+    let suffix = node.named_child(1).filter(|x| x.kind() == "call_suffix");
+    let arguments = suffix.and_then(|x| x.named_child(0)).filter(|x| x.kind() == "value_arguments");
+    let label = arguments
+        .and_then(|x| x.named_child(0))
+        .and_then(|x| x.named_child(0))
+        .filter(|x| x.kind() == "value_argument_label");
+    label.map(|x| x.text(source).map(|x| x.to_string())).transpose()
+}
+
+/// `items.count` of the target and the name of a member, `?.` if the target is optional.
+/// The properties the type of the target declares, like `counter.count` of a store, are kept as is
+fn member(
+    target: &Node,
+    optional: bool,
+    name: &str,
+    symbols: &SymbolTable,
+    expr: ExprFn,
+    type_of: TypeFn,
+) -> Result<Option<String>> {
+    // `!items.isEmpty` is parsed as `(!items).isEmpty`
+    if target.kind() == "prefix_expression" && target.child_count() == 2 {
+        if let (Some(op), Some(operand)) = (target.child(0), target.child(1)) {
+            if ["bang", "-"].contains(&op.kind()) {
+                let op = if op.kind() == "bang" { "!" } else { "-" };
+                return Ok(member(&operand, optional, name, symbols, expr, type_of)?.map(|x| format!("{}{}", op, x)));
+            }
+        }
+    }
+
+    // `self.count` is a property declared in Swift, `counter.count` may be one of a model or a store
+    if target.kind() == "self_expression" {
+        return Ok(None);
+    }
+    if type_of(target)?.is_some_and(|ty| symbols.declares(&ty, name)) {
+        return Ok(None);
+    }
+
+    let dot = if optional { "?." } else { "." };
+    let code = || expr(target);
+    Ok(match name {
+        "count" => Some(format!("{}{}length", code()?, dot)),
+        "isEmpty" => Some(format!("({}{}length === 0)", code()?, dot)),
+        "description" => Some(format!("String({})", code()?)),
+        "first" => Some(format!("{}{}[0]", code()?, if optional { "?." } else { "" })),
+        "last" => Some(format!("{}{}at(-1)", code()?, dot)),
+        // the message of a JavaScript exception, the case of a `SwiftError`
+        "localizedDescription" => Some(format!("{}{}message", code()?, dot)),
+        _ => None,
+    })
+}
+
+/// A method of `Array` or `String`, with the JavaScript receiver `target` and the arguments `args`,
+/// `closure` if the last argument is a closure
+fn method(name: &str, label: Option<&str>, target: &str, args: &[String], closure: bool) -> Option<String> {
+    let all = args.join(", ");
+    if closure {
+        return Some(match (name, args) {
+            ("reduce", [initial, f]) => format!("{}.reduce({}, {})", target, f, initial),
+            // `areInIncreasingOrder` is false both ways for equal elements
+            ("sorted", [f]) => format!("[...{}].sort((a, b) => ({f})(a, b) ? -1 : ({f})(b, a) ? 1 : 0)", target, f = f),
+            ("first", [_]) => format!("{}.find({})", target, all),
+            ("firstIndex", [_]) => format!("{}.findIndex({})", target, all),
+            ("contains", [_]) => format!("{}.some({})", target, all),
+            ("allSatisfy", [_]) => format!("{}.every({})", target, all),
+            ("compactMap", [_]) => format!("{}.map({}).filter((x) => x != null)", target, all),
+            ("removeAll", [f]) => format!("{0}.splice(0, {0}.length, ...{0}.filter((x) => !({1})(x)))", target, f),
+            _ => return None,
+        });
+    }
+    Some(match (name, label, args) {
+        ("append", Some("contentsOf"), [items]) => format!("{}.push(...{})", target, items),
+        ("append", _, _) => format!("{}.push({})", target, all),
+        ("insert", _, [value, index]) => format!("{}.splice({}, 0, {})", target, index, value),
+        ("remove", Some("at"), [index]) => format!("{}.splice({}, 1)[0]", target, index),
+        ("removeAll", None, []) => format!("{}.splice(0)", target),
+        ("removeFirst", None, []) => format!("{}.shift()", target),
+        ("removeLast", None, []) => format!("{}.pop()", target),
+        // `undefined` of an empty array is nil
+        ("popLast", None, []) => format!("{}.pop()", target),
+        ("contains", None, [_]) => format!("{}.includes({})", target, all),
+        ("joined", _, []) => format!("{}.join(\"\")", target),
+        ("joined", _, [_]) => format!("{}.join({})", target, all),
+        ("sorted", None, []) => format!("[...{}].sort()", target),
+        ("reversed", None, []) => format!("[...{}].reverse()", target),
+        ("uppercased", None, []) => format!("{}.toUpperCase()", target),
+        ("lowercased", None, []) => format!("{}.toLowerCase()", target),
+        ("hasPrefix", None, [_]) => format!("{}.startsWith({})", target, all),
+        ("hasSuffix", None, [_]) => format!("{}.endsWith({})", target, all),
+        _ => return None,
+    })
+}
+
+/// A function of the standard library
+fn function(name: &str, args: &[String]) -> Option<String> {
+    let all = args.join(", ");
+    Some(match (name, args) {
+        ("print", _) => format!("console.log({})", all),
+        ("min", [_, _, ..]) => format!("Math.min({})", all),
+        ("max", [_, _, ..]) => format!("Math.max({})", all),
+        ("abs", [_]) => format!("Math.abs({})", all),
+        // `Int("12abc")` is nil, `parseInt` would be 12
+        ("Int", [_]) => format!("SwiftOptional.int({})", all),
+        ("Double", [_]) | ("Float", [_]) => format!("SwiftOptional.double({})", all),
+        _ => return None,
+    })
+}
+
+/// `"\(count) items"` --> `"" + (count) + " items"`, it's valid in a template string and in an attribute
+fn interpolation(node: &Node, source: &str, expr: ExprFn) -> Result<String> {
+    let mut parts = Vec::new();
+    for child in named_children(node) {
+        match child.kind() {
+            "line_str_text" | "str_escaped_char" => parts.push(format!("\"{}\"", child.text(source)?)),
+            "interpolated_expression" => {
+                // `+` concatenates strings only if one of the first two is a string
+                if parts.is_empty() {
+                    parts.push("\"\"".to_string());
+                }
+                let value = child.named_child(0).map(|x| expr(&x)).transpose()?.unwrap_or_default();
+                parts.push(format!("({})", value));
+            }
+            _ => {}
+        }
+    }
+    Ok(parts.join(" + "))
+}
+
+/// A literal or a case without associated values like `.idle`, `===` compares it like Swift's `==`
+fn identity(node: &Node) -> bool {
+    const LITERAL_KINDS: [&str; 6] = [
+        "integer_literal",
+        "real_literal",
+        "boolean_literal",
+        "line_string_literal",
+        "multi_line_string_literal",
+        "hex_literal",
+    ];
+    let case = node.kind() == "prefix_expression" && node.child(0).is_some_and(|x| x.kind() == ".");
+    case || LITERAL_KINDS.contains(&node.kind())
+}
+
+/// `LoadState.idle` of `.idle` compared with `other`, if `other` is known to be a `LoadState`
+fn case(node: &Node, other: &Node, source: &str, symbols: &SymbolTable, type_of: TypeFn) -> Result<Option<String>> {
+    if node.kind() != "prefix_expression" || node.child(0).map(|x| x.kind()) != Some(".") {
+        return Ok(None);
+    }
+    let Some(ty) = type_of(other)?.filter(|x| symbols.enums.contains(x)) else {
+        return Ok(None);
+    };
+    Ok(Some(format!("{}.{}", ty, node.child_at(1, source)?.text(source)?)))
+}
+
+/// `value!`, `SwiftOptional.unwrap` throws on nil like Swift. The grammar parses `a + b!` as `(a + b)!` and `a + b.c!` as `(a + b).c!`,
+/// only the last operand is unwrapped
+fn unwrap<'a>(value: &Node<'a>, source: &str, symbols: &SymbolTable, expr: ExprFn, type_of: TypeFn) -> Result<String> {
+    const BINARY_KINDS: [&str; 6] = [
+        "additive_expression",
+        "multiplicative_expression",
+        "comparison_expression",
+        "equality_expression",
+        "infix_expression",
+        "nil_coalescing_expression",
+    ];
+    let binary = |node: Option<Node<'a>>| node.filter(|x| BINARY_KINDS.contains(&x.kind()) && x.child_count() == 3);
+
+    if let Some(binary) = binary(Some(*value)) {
+        let (lhs, op, rhs) = (binary.child_at(0, source)?, binary.child_at(1, source)?, binary.child_at(2, source)?);
+        return Ok(format!("{} {} {}", expr(&lhs)?, expr(&op)?, unwrap(&rhs, source, symbols, expr, type_of)?));
+    }
+    let suffix = value.child(value.child_count().saturating_sub(1)).filter(|x| x.kind() == "navigation_suffix");
+    if let (Some(binary), Some(suffix)) = (binary(value.child(0)), suffix) {
+        let (lhs, op, rhs) = (binary.child_at(0, source)?, binary.child_at(1, source)?, binary.child_at(2, source)?);
+        let name = suffix.child_at(1, source)?.text(source)?;
+        let optional = (0..value.child_count()).any(|i| value.child(i).map(|x| x.kind()) == Some("?"));
+        let operand = match member(&rhs, optional, name, symbols, expr, type_of)? {
+            Some(code) => code,
+            None => format!("{}{}{}", expr(&rhs)?, if optional { "?." } else { "." }, name),
+        };
+        return Ok(format!("{} {} SwiftOptional.unwrap({})", expr(&lhs)?, expr(&op)?, operand));
+    }
+    Ok(format!("SwiftOptional.unwrap({})", expr(value)?))
+}
+
+/// The JavaScript of a Swift-specific expression, `None` if `node` is the same in JavaScript.
+/// `expr` converts the sub-expressions, it's never called with `node` itself
+pub fn swift2js(
+    node: &Node,
+    source: &str,
+    symbols: &SymbolTable,
+    expr: ExprFn,
+    type_of: TypeFn,
+) -> Result<Option<String>> {
+    let children = named_children(node);
+
+    Ok(match node.kind() {
+        "navigation_expression" => {
+            let (Some(target), Some(suffix)) = (children.first(), children.last()) else {
+                return Ok(None);
+            };
+            let Some(name) = suffix.named_child(0).filter(|_| suffix.kind() == "navigation_suffix") else {
+                return Ok(None);
+            };
+            let name = name.text(source)?;
+            // `\.name` is a function reading the property
+            if target.kind() == "key_path_expression" {
+                return Ok(Some(match name {
+                    "self" => "(x) => x".to_string(),
+                    _ => format!("(x) => x.{}", name),
+                }));
+            }
+            let optional = (0..node.child_count()).any(|i| node.child(i).map(|x| x.kind()) == Some("?"));
+            member(target, optional, name, symbols, expr, type_of)?
+        }
+        "call_expression" => {
+            let Some(mut callee) = children.first().copied() else {
+                return Ok(None);
+            };
+            let mut call = *node;
+            let (mut args, mut closure) = arguments(node, expr)?;
+            // `f(a) { ... }` may be parsed as a call of `f(a)` with the trailing closure
+            let suffix = node.named_child(1).map(|x| named_children(&x)).unwrap_or_default();
+            if callee.kind() == "call_expression" && suffix.iter().all(|x| x.kind() == "lambda_literal") {
+                let (mut inner, _) = arguments(&callee, expr)?;
+                inner.append(&mut args);
+                (args, closure) = (inner, true);
+                call = callee;
+                callee = callee.named_child(0).unwrap_or(callee);
+            }
+            let label = first_label(&call, source)?;
+            let code = match callee.kind() {
+                "simple_identifier" => function(callee.text(source)?, &args),
+                "navigation_expression" => match (callee.named_child(0), callee.named_child(1).and_then(|x| x.named_child(0))) {
+                    // `Type.method()` and `self.method()` are declared in Swift
+                    (Some(target), _)
+                        if target.kind() == "self_expression"
+                            || target.kind() == "simple_identifier"
+                                && target.text(source)?.starts_with(|c: char| c.is_ascii_uppercase()) =>
+                    {
+                        None
+                    }
+                    (Some(target), Some(name)) => {
+                        method(name.text(source)?, label.as_deref(), &expr(&target)?, &args, closure)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            match code {
+                Some(code) => Some(code),
+                // the trailing closures are the last arguments
+                None if has_trailing_closure(node) => Some(format!("{}({})", expr(&callee)?, args.join(", "))),
+                None => None,
+            }
+        }
+        // `x!` fails like Swift when `x` is nil
+        "postfix_expression" if node.child(node.child_count().saturating_sub(1)).map(|x| x.kind()) == Some("bang") => {
+            children.first().map(|x| unwrap(x, source, symbols, expr, type_of)).transpose()?
+        }
+        // `try x` throws like the call it's written before, `try?` is nil and `try!` is fatal if it throws
+        "try_expression" => {
+            let operator = node.child(0).map(|x| x.text(source)).transpose()?.unwrap_or_default();
+            let value = children.last().map(expr).transpose()?;
+            match operator {
+                "try?" => value.map(|x| format!("SwiftError.attempt(() => {})", x)),
+                "try!" => value.map(|x| format!("SwiftError.force(() => {})", x)),
+                _ => value,
+            }
+        }
+        // `x == nil` is true for `undefined` and `null`. Structs and the cases with associated values are new
+        // objects every time, they are compared by their contents unless the other side is a literal or a case
+        "equality_expression" | "infix_expression" => {
+            let op = node.child(1).map(|x| x.text(source)).transpose()?.unwrap_or_default();
+            let (Some(a), Some(b)) = (node.child(0), node.child(2)) else {
+                return Ok(None);
+            };
+            if !["==", "!="].contains(&op) || node.child_count() != 3 {
+                return Ok(None);
+            }
+            if b.kind() == "nil" {
+                Some(format!("{} {} null", expr(&a)?, op))
+            } else if let Some(case) = case(&b, &a, source, symbols, type_of)? {
+                Some(format!("{} {}= {}", expr(&a)?, op, case))
+            } else if let Some(case) = case(&a, &b, source, symbols, type_of)? {
+                Some(format!("{} {}= {}", case, op, expr(&b)?))
+            } else if identity(&a) || identity(&b) {
+                None
+            } else {
+                let not = if op == "!=" { "!" } else { "" };
+                Some(format!("{}SwiftEquatable.equals({}, {})", not, expr(&a)?, expr(&b)?))
+            }
+        }
+        "==" => Some("===".to_string()),
+        "!=" => Some("!==".to_string()),
+        // `a != b` is parsed as an infix expression of a custom operator
+        "custom_operator" => match node.text(source)? {
+            "==" => Some("===".to_string()),
+            "!=" => Some("!==".to_string()),
+            _ => None,
+        },
+        // like a missing property of a model
+        "nil" => Some("undefined".to_string()),
+        // `0..<n` is the array of the numbers
+        "range_expression" => {
+            let op = node.child(1).map(|x| x.kind()).unwrap_or_default();
+            let [start, end] = children.as_slice() else {
+                return Ok(None);
+            };
+            let (start, end) = (expr(start)?, expr(end)?);
+            // `1...n` has `n` numbers
+            let offset = start.parse::<i64>().ok().map(|x| if op == "..<" { x } else { x - 1 });
+            let length = match offset {
+                Some(0) => end.clone(),
+                Some(x) if x > 0 => format!("{} - {}", end, x),
+                Some(x) => format!("{} + {}", end, -x),
+                None if op == "..<" => format!("{} - {}", end, start),
+                None => format!("{} - {} + 1", end, start),
+            };
+            if start == "0" {
+                Some(format!("Array.from({{ length: {} }}, (_, i) => i)", length))
+            } else {
+                Some(format!("Array.from({{ length: {} }}, (_, i) => {} + i)", length, start))
+            }
+        }
+        "line_string_literal" if children.iter().any(|x| x.kind() == "interpolated_expression") => {
+            Some(interpolation(node, source, expr)?)
+        }
+        _ => None,
+    })
+}
+
+/// The JavaScript in a double quoted attribute
+pub fn attribute(code: String) -> String {
+    if code.contains('\'') {
+        code.replace('"', "&quot;")
+    } else {
+        code.replace('"', "'")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::common::statement::rewrite_expr;
+    use crate::project::SymbolTable;
+
+    /// The expression of `let x = ...`, the sub-expressions are left as they are
+    fn translate(expr: &str) -> String {
+        let source = format!("let x = {}", expr);
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(&source, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+        let value = node.named_child(node.named_child_count() - 1).unwrap();
+
+        // `state` is a `LoadState`
+        fn js(node: &tree_sitter::Node, source: &str) -> crate::error::Result<String> {
+            let symbols = SymbolTable {
+                enums: ["LoadState".to_string()].into(),
+                ..Default::default()
+            };
+            let type_of = |x: &tree_sitter::Node| Ok((x.utf8_text(source.as_bytes()).ok() == Some("state")).then(|| "LoadState".to_string()));
+            rewrite_expr(node, source, &mut |node| super::swift2js(node, source, &symbols, &|x| js(x, source), &type_of))
+        }
+        js(&value, &source).unwrap()
+    }
+
+    #[test]
+    fn test_swift2js() {
+        assert_eq!(translate("colors[value].description"), "String(colors[value])");
+        assert_eq!(translate("selection.count > 0"), "selection.length > 0");
+        assert_eq!(translate("!items.isEmpty"), "!(items.length === 0)");
+        assert_eq!(translate("item?.tags.count ?? 0"), "item?.tags.length ?? 0");
+        assert_eq!(translate("item!.name == nil"), "SwiftOptional.unwrap(item).name == null");
+        assert_eq!(translate("a != b"), "!SwiftEquatable.equals(a, b)");
+        assert_eq!(translate("item.count == 0"), "item.length === 0");
+        assert_eq!(translate("state == .idle"), "state === LoadState.idle");
+        assert_eq!(translate("state != .idle"), "state !== LoadState.idle");
+        assert_eq!(translate(".idle == state"), "LoadState.idle === state");
+        assert_eq!(translate("count + item.price!"), "count + SwiftOptional.unwrap(item.price)");
+        assert_eq!(translate("count + items?.count!"), "count + SwiftOptional.unwrap(items?.length)");
+        assert_eq!(translate("0..<n"), "Array.from({ length: n }, (_, i) => i)");
+        assert_eq!(translate("1...n"), "Array.from({ length: n }, (_, i) => 1 + i)");
+        assert_eq!(translate("a...b"), "Array.from({ length: b - a + 1 }, (_, i) => a + i)");
+        assert_eq!(translate(r#""\(count) items""#), r#""" + (count) + " items""#);
+        assert_eq!(translate(r#"names.joined(separator: ", ")"#), r#"names.join(", ")"#);
+        assert_eq!(translate("items.append(contentsOf: [1])"), "items.push(...[1])");
+        assert_eq!(translate("items.remove(at: 0)"), "items.splice(0, 1)[0]");
+        assert_eq!(translate(r"\.name"), "(x) => x.name");
+        assert_eq!(translate("try? load(name)"), "SwiftError.attempt(() => load(name))");
+        assert_eq!(translate("try! load(name)"), "SwiftError.force(() => load(name))");
+    }
+}
@@ -0,0 +1,403 @@
+//! 文件顶层的声明：`let` 转换为导出的常量，`var` 转换为导出的变量，函数转换为导出的 function，
+//! 它们和模型、枚举一起放在 `shared.js` 中，每个组件都会导入
+
+use tree_sitter::Node;
+
+use crate::error::{CompileError, NodeExt, Result};
+use crate::project::SymbolTable;
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+
+use super::enums::construct2js;
+use super::statement::{rewrite_expr, StatementWriter};
+use super::object::callexp2object_with_context;
+use super::expr::swift2js;
+use super::statement::{closure2js, statement2js};
+use super::types::{infer_ts_type, swift_type2ts};
+
+pub(super) struct GlobalWriter<'s> {
+    pub(super) source: &'s str,
+    pub(super) symbols: &'s SymbolTable,
+    /// Properties of the store the code belongs to, they are read and written through `self`
+    pub(super) members: Vec<String>,
+    /// Methods of the store the code belongs to, called like the functions at file scope
+    pub(super) methods: Vec<String>,
+    /// What `self` is in JavaScript, `this` in the getters of a model
+    pub(super) receiver: &'static str,
+}
+
+impl<'s> GlobalWriter<'s> {
+    pub(super) fn new(source: &'s str, symbols: &'s SymbolTable) -> Self {
+        Self {
+            source,
+            symbols,
+            members: Vec::new(),
+            methods: Vec::new(),
+            receiver: "self",
+        }
+    }
+
+    fn models(&self) -> Vec<String> {
+        self.symbols.models.iter().cloned().collect()
+    }
+
+    fn enums(&self) -> Vec<String> {
+        self.symbols.enums.iter().cloned().collect()
+    }
+
+    /// The expression, `.case` refers to the enum `ty` if there is one
+    pub(super) fn typed_expr(&self, node: &Node, ty: Option<&str>) -> Result<String> {
+        let source = self.source;
+        let models = self.models();
+        let enums = self.enums();
+
+        rewrite_expr(node, source, &mut |node| {
+            if let Some(code) = swift2js(node, source, self.symbols, &|x| self.expr(x), &|_| Ok(None))? {
+                return Ok(Some(code));
+            }
+
+            Ok(match node.kind() {
+                "call_expression" => {
+                    let callee = node.named_child(0).map(|x| x.text(source)).transpose()?.unwrap_or_default();
+                    let method = callee.strip_prefix("self.").unwrap_or(callee);
+                    if models.iter().any(|x| x == callee) {
+                        Some(callexp2object_with_context(node, source, models.clone())?)
+                    } else if self.symbols.functions.contains(callee) {
+                        Some(self.call(node, callee)?)
+                    } else if self.methods.iter().any(|x| x == method) {
+                        Some(self.call(node, method)?)
+                    } else {
+                        construct2js(node, source, ty, &enums, &|x| self.expr(x))?
+                    }
+                }
+                "prefix_expression" => construct2js(node, source, ty, &enums, &|x| self.expr(x))?,
+                "lambda_literal" => Some(closure2js(self, node, None)?),
+                "simple_identifier" => {
+                    let parent = node.parent().map(|x| x.kind());
+                    let name = node.text(source)?;
+                    let member = !matches!(parent, Some("navigation_suffix") | Some("value_argument_label"));
+                    (member && self.members.iter().any(|x| x == name)).then(|| format!("{}.{}", self.receiver, name))
+                }
+                "self_expression" if self.receiver != "self" => Some(self.receiver.to_string()),
+                _ => None,
+            })
+        })
+    }
+
+    /// A call of a function declared at file scope, the labels are dropped
+    fn call(&self, node: &Node, callee: &str) -> Result<String> {
+        let mut args = Vec::new();
+        if let Some(arguments) = crate::utils::find_first_node(*node, "value_arguments") {
+            let mut cursor = arguments.walk();
+            for argument in arguments.named_children(&mut cursor) {
+                let mut cursor = argument.walk();
+                if let Some(value) = argument.named_children(&mut cursor).last() {
+                    args.push(self.expr(&value)?);
+                }
+            }
+        }
+
+        Ok(format!("{}({})", callee, args.join(", ")))
+    }
+}
+
+impl StatementWriter for GlobalWriter<'_> {
+    fn source(&self) -> &str {
+        self.source
+    }
+
+    fn expr(&self, node: &Node) -> Result<String> {
+        self.typed_expr(node, None)
+    }
+
+    fn statement(&self, node: &Node, indent: usize) -> Result<String> {
+        statement2js(self, node, indent)
+    }
+}
+
+/// `const` or `let`, the name, the type annotation and the value of a `property_declaration`
+pub(super) fn property_parts<'a>(node: &Node<'a>, source: &str) -> Result<(&'static str, String, Option<Node<'a>>, Option<Node<'a>>)> {
+    let mut keyword = "let";
+    let mut name = None;
+    let mut ty = None;
+    let mut value = None;
+
+    for i in 0..node.child_count() {
+        let child = node.child_at(i, source)?;
+        match child.kind() {
+            "value_binding_pattern" if child.text(source)? == "let" => keyword = "const",
+            "pattern" => name = Some(child.text(source)?.to_string()),
+            "type_annotation" => ty = Some(child),
+            "computed_property" => {
+                return Err(CompileError::unsupported("暂不支持顶层的计算属性", node, source));
+            }
+            _ if child.prev_sibling().map(|x| x.kind() == "=").unwrap_or(false) => value = Some(child),
+            _ => {}
+        }
+    }
+
+    let name = name.ok_or_else(|| CompileError::unexpected("expect a property name", node, source))?;
+    Ok((keyword, name, ty, value))
+}
+
+fn property2js(writer: &GlobalWriter, node: &Node, typescript: bool) -> Result<String> {
+    let source = writer.source;
+    let known = writer.symbols.type_names();
+    let (keyword, name, ty, value) = property_parts(node, source)?;
+
+    let annotation = match (&ty, &value) {
+        (Some(ty), _) if typescript => format!(": {}", swift_type2ts(ty, source, &known)?),
+        (None, Some(value)) if typescript => format!(": {}", infer_ts_type(value, source, &known)?),
+        _ => String::new(),
+    };
+    // `.case` of `let flavor: Flavor = .chocolate`
+    let ty = ty
+        .map(|x| x.text(source).map(|x| x.trim_start_matches(':').trim().to_string()))
+        .transpose()?;
+
+    Ok(match value {
+        Some(value) => format!(
+            "export {} {}{} = {};\n",
+            keyword,
+            name,
+            annotation,
+            writer.typed_expr(&value, ty.as_deref())?
+        ),
+        None => format!("export {} {}{};\n", keyword, name, annotation),
+    })
+}
+
+/// A function or a method, the parameters are declared with their internal names
+pub(super) struct Function<'a> {
+    pub(super) name: String,
+    pub(super) params: Vec<String>,
+    /// `item: Item = x` of the parameter `item`, typed in TypeScript
+    pub(super) declarations: Vec<String>,
+    pub(super) return_type: String,
+    pub(super) body: Option<Node<'a>>,
+}
+
+pub(super) fn function_parts<'a>(writer: &GlobalWriter, node: &Node<'a>, typescript: bool) -> Result<Function<'a>> {
+    let source = writer.source;
+    let known = writer.symbols.type_names();
+    let typed = |ty: Option<Node>| -> Result<String> {
+        match ty {
+            Some(ty) if typescript => Ok(format!(": {}", swift_type2ts(&ty, source, &known)?)),
+            _ => Ok(String::new()),
+        }
+    };
+
+    let mut name = None;
+    let mut names = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let mut return_type = String::new();
+    let mut body = None;
+
+    for i in 0..node.child_count() {
+        let child = node.child_at(i, source)?;
+        match child.kind() {
+            "simple_identifier" if name.is_none() => name = Some(child.text(source)?.to_string()),
+            "parameter" => {
+                let mut cursor = child.walk();
+                let children = child.named_children(&mut cursor).collect::<Vec<_>>();
+                // `_ name: String` or `label name: String`, the label is only used at the call site
+                let Some(param) = children.iter().rfind(|x| x.kind() == "simple_identifier") else {
+                    return Err(CompileError::unexpected("expect a parameter name", &child, source));
+                };
+                let ty = children.iter().find(|x| x.kind() != "simple_identifier").copied();
+                names.push(param.text(source)?.to_string());
+                params.push(format!("{}{}", param.text(source)?, typed(ty)?));
+            }
+            // the default value of the last parameter
+            _ if child.prev_sibling().map(|x| x.kind() == "=").unwrap_or(false) => {
+                if let Some(param) = params.last_mut() {
+                    param.push_str(&format!(" = {}", writer.expr(&child)?));
+                }
+            }
+            _ if child.prev_sibling().map(|x| x.kind() == "->").unwrap_or(false) => {
+                return_type = typed(Some(child))?;
+            }
+            "function_body" => body = find_statements(&child),
+            _ => {}
+        }
+    }
+
+    let name = name.ok_or_else(|| CompileError::unexpected("expect a function name", node, source))?;
+    Ok(Function {
+        name,
+        params: names,
+        declarations: params,
+        return_type,
+        body,
+    })
+}
+
+fn function2js(writer: &GlobalWriter, node: &Node, typescript: bool) -> Result<String> {
+    let function = function_parts(writer, node, typescript)?;
+    let mut out = format!(
+        "export function {}({}){} {{\n",
+        function.name,
+        function.declarations.join(", "),
+        function.return_type
+    );
+    if let Some(body) = function.body {
+        out.push_str(&writer.statements(&body, 4)?);
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// The getter and the setter of a `computed_property`
+pub struct Accessors<'a> {
+    /// The `statements` of the getter
+    pub getter: Option<Node<'a>>,
+    /// The name of the new value, `newValue` unless the setter names it, and the `statements`
+    pub setter: Option<(String, Option<Node<'a>>)>,
+}
+
+pub fn accessors<'a>(node: &Node<'a>, source: &str) -> Result<Accessors<'a>> {
+    let mut accessors = Accessors { getter: None, setter: None };
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "statements" => accessors.getter = Some(child),
+            "computed_getter" => accessors.getter = find_statements(&child),
+            "computed_setter" => {
+                let mut cursor = child.walk();
+                let name = child.named_children(&mut cursor).find(|x| x.kind() == "simple_identifier");
+                let name = name.map(|x| x.text(source)).transpose()?.unwrap_or("newValue");
+                accessors.setter = Some((name.to_string(), find_statements(&child)));
+            }
+            _ => {}
+        }
+    }
+    Ok(accessors)
+}
+
+/// The expression of a getter with a single expression, it's returned implicitly
+pub fn implicit_return<'a>(statements: &Node<'a>) -> Option<Node<'a>> {
+    let mut cursor = statements.walk();
+    let children = statements
+        .named_children(&mut cursor)
+        .filter(|x| !x.kind().ends_with("comment"))
+        .collect::<Vec<_>>();
+
+    match children.as_slice() {
+        [expr] if !expr.kind().ends_with("_statement") && !["property_declaration", "assignment"].contains(&expr.kind()) => {
+            Some(*expr)
+        }
+        _ => None,
+    }
+}
+
+/// `get name() { ... }` of a computed property in an object literal, with `set name(newValue) { ... }`
+/// if it has a setter. The members are read through the receiver of `writer`
+pub(super) fn accessors2js(writer: &GlobalWriter, name: &str, node: &Node, ts_type: Option<&str>, indent: usize) -> Result<String> {
+    let accessors = accessors(node, writer.source)?;
+    let return_type = ts_type.map(|x| format!(": {}", x)).unwrap_or_default();
+
+    let mut out = format!("{:indent$}get {}(){} {{\n", "", name, return_type, indent = indent);
+    if let Some(getter) = accessors.getter {
+        match implicit_return(&getter) {
+            Some(expr) => out.push_str(&format!("{:indent$}return {};\n", "", writer.expr(&expr)?, indent = indent + 4)),
+            None => out.push_str(&writer.statements(&getter, indent + 4)?),
+        }
+    }
+    out.push_str(&format!("{:indent$}}},\n", "", indent = indent));
+
+    if let Some((value, setter)) = accessors.setter {
+        out.push_str(&format!("{:indent$}set {}({}{}) {{\n", "", name, value, return_type, indent = indent));
+        if let Some(setter) = setter {
+            out.push_str(&writer.statements(&setter, indent + 4)?);
+        }
+        out.push_str(&format!("{:indent$}}},\n", "", indent = indent));
+    }
+
+    Ok(out)
+}
+
+pub(super) fn find_statements<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    let statements = node.named_children(&mut cursor).find(|x| x.kind() == "statements");
+    statements
+}
+
+/// An exported JavaScript declaration of a `let`/`var` or a function declared at file scope
+pub fn global2js(node: &Node, source: &str, symbols: &SymbolTable, typescript: bool) -> Result<String> {
+    let writer = GlobalWriter::new(source, symbols);
+
+    match node.kind() {
+        "property_declaration" => property2js(&writer, node, typescript),
+        "function_declaration" => function2js(&writer, node, typescript),
+        _ => Err(CompileError::unexpected(
+            "expect a property or function declaration",
+            node,
+            source,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::project::SymbolTable;
+
+    const SOURCE: &str = r#"let movies = [Movie(title: "Pulp Fiction"),
+              Movie(title: "Kill Bill")]
+var flavor: Flavor = .chocolate
+
+func describe(_ movie: Movie, prefix label: String = "Movie: ") -> String {
+    let title = label + movie.title
+    return title
+}
+"#;
+
+    fn symbols() -> SymbolTable {
+        let mut symbols = SymbolTable::default();
+        symbols.models.insert("Movie".to_string());
+        symbols.enums.insert("Flavor".to_string());
+        symbols.globals.insert("movies".to_string());
+        symbols.functions.insert("describe".to_string());
+        symbols
+    }
+
+    fn convert(typescript: bool) -> Vec<String> {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(SOURCE, None).unwrap();
+        let root = tree.root_node();
+        let symbols = symbols();
+
+        (0..root.named_child_count())
+            .map(|i| super::global2js(&root.named_child(i).unwrap(), SOURCE, &symbols, typescript).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_global2js() {
+        let result = convert(false);
+        assert_eq!(
+            result[0],
+            "export const movies = [Movie({title: \"Pulp Fiction\"}),\n              Movie({title: \"Kill Bill\"})];\n"
+        );
+        assert_eq!(result[1], "export let flavor = Flavor.chocolate;\n");
+        assert_eq!(
+            result[2],
+            r#"export function describe(movie, label = "Movie: ") {
+    const title = label + movie.title;
+    return title;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_global2ts() {
+        let result = convert(true);
+        // the type of an array of models is not inferred
+        assert!(result[0].starts_with("export const movies: any = ["));
+        assert_eq!(result[1], "export let flavor: Flavor = Flavor.chocolate;\n");
+        assert!(result[2].starts_with("export function describe(movie: Movie, label: string = \"Movie: \"): string {\n"));
+    }
+}
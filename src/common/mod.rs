@@ -1,3 +1,9 @@
 pub mod object;
 pub mod array;
-pub mod model;
\ No newline at end of file
+pub mod enums;
+pub mod expr;
+pub mod global;
+pub mod model;
+pub mod statement;
+pub mod store;
+pub mod types;
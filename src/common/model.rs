@@ -1,92 +1,231 @@
+use std::rc::Rc;
+
+use crate::error::{CompileError, NodeExt, Result};
+use crate::paser::Extension;
+use crate::project::SymbolTable;
 #[allow(unused_imports)]
 use crate::utils::log_node_tree;
-use crate::utils::{find_first_node, log_node};
+use crate::utils::find_first_node;
 
-use super::object::callexp2object;
+use super::global::{accessors2js, GlobalWriter};
+use super::types::{infer_ts_type, swift_type2ts};
 
 #[derive(Debug)]
-struct Var {
+struct Var<'a> {
     name: String,
     default: Option<String>,
+    /// TypeScript type, only collected for [`date_model2ts_fn`]
+    ty: Option<String>,
+    /// The `computed_property` of `var description: String { ... }`, there is nothing to pass in for it,
+    /// it's a getter of the object
+    computed: Option<tree_sitter::Node<'a>>,
+    /// Source of the file it's declared in, an extension may be in another file
+    source: Rc<str>,
 }
 
 #[derive(Default, Debug)]
-struct State {
+struct State<'a> {
     name: String,
-    source: String,
-    vars: Vec<Var>,
+    /// Source of the declaration being collected
+    source: Rc<str>,
+    vars: Vec<Var<'a>>,
+    /// Types the TypeScript output may refer to, `None` when generating JavaScript
+    known_types: Option<Vec<String>>,
 }
 
-impl State {
-    fn collect(&mut self, node: &tree_sitter::Node) {
-        let node_code = node.utf8_text(self.source.as_bytes()).unwrap();
+impl<'a> State<'a> {
+    fn collect(&mut self, node: &tree_sitter::Node<'a>) -> Result<()> {
+        let node_code = node.text(&self.source)?;
 
         match node.kind() {
-            "type_identifier" => {
-              if self.name.is_empty() {
+            "type_identifier" if self.name.is_empty() => {
                 self.name = node_code.to_string();
-              }
             }
             "property_declaration" => {
-                let name_node = find_first_node(*node, "pattern", &self.source).unwrap();
-                let name_code = name_node.utf8_text(self.source.as_bytes()).unwrap();
+                let name_node = find_first_node(*node, "pattern").ok_or_else(|| {
+                    CompileError::unexpected("expect a property name", node, &self.source)
+                })?;
+                let name_code = name_node.text(&self.source)?;
+
+                let computed = find_first_node(*node, "computed_property");
+                let mut ty = None;
+                if let Some(known) = &self.known_types {
+                    let mut cursor = node.walk();
+                    let annotation = node.children(&mut cursor).find(|x| x.kind() == "type_annotation");
+                    let value = find_first_node(*node, "=").map(|x| x.next_node(&self.source)).transpose()?;
+                    ty = Some(match (annotation, value) {
+                        (Some(annotation), _) => swift_type2ts(&annotation, &self.source, known)?,
+                        (None, Some(value)) => infer_ts_type(&value, &self.source, known)?,
+                        (None, None) => "any".to_string(),
+                    });
+                }
 
-                if let Some(eq_node) = find_first_node(*node, "=", &self.source) {
-                    let default_node = eq_node.next_sibling().unwrap();
-                    let default_code = default_node.utf8_text(self.source.as_bytes()).unwrap();
+                // `=` of the getter is not the default value
+                if let Some(eq_node) = find_first_node(*node, "=").filter(|_| computed.is_none()) {
+                    let default_node = eq_node.next_node(&self.source)?;
+                    let default_code = default_node.text(&self.source)?;
 
                     self.vars.push(Var {
                         name: name_code.to_string(),
                         default: Some(default_code.to_string()),
+                        ty,
+                        computed,
+                        source: self.source.clone(),
                     });
                 } else {
                     self.vars.push(Var {
                         name: name_code.to_string(),
                         default: None,
+                        ty,
+                        computed,
+                        source: self.source.clone(),
                     });
                 }
-                return;
+                return Ok(());
             }
             _ => {}
         }
 
         for i in 0..node.child_count() {
-            let child = node.child(i).unwrap();
-            self.collect(&child);
+            let child = node.child_at(i, &self.source)?;
+            self.collect(&child)?;
         }
+
+        Ok(())
+    }
+
+    /// `get description() { ... }` of a computed property, the other properties are read through `this`
+    fn accessors(&self, var: &Var, computed: &tree_sitter::Node, symbols: &SymbolTable) -> Result<String> {
+        let mut writer = GlobalWriter::new(&var.source, symbols);
+        writer.members = self.vars.iter().map(|x| x.name.clone()).collect();
+        writer.receiver = "this";
+        accessors2js(&writer, &var.name, computed, var.ty.as_deref(), 8)
     }
 
-    fn generate(self) -> String {
+    fn generate(self, symbols: &SymbolTable) -> Result<String> {
         let mut out = format!("function {}(arg) {{\n", self.name);
         out.push_str("    const { ");
-        for var in &self.vars {
+        for var in self.vars.iter().filter(|x| x.computed.is_none()) {
             out.push_str(&format!("{}, ", var.name));
         }
         out.push_str("} = arg ?? {};\n");
 
         out.push_str(format!("{:indent$}return {{\n", "", indent = 4).as_str());
         for var in &self.vars {
-            out.push_str(&format!("{:indent$}{}: {} ?? {},\n", "", var.name, var.name, var.default.as_ref().unwrap_or(&"false".to_string()), indent = 8));
+            match &var.computed {
+                Some(computed) => out.push_str(&self.accessors(var, computed, symbols)?),
+                None => {
+                    let default = var.default.as_deref().unwrap_or("false");
+                    out.push_str(&format!("{:indent$}{}: {} ?? {},\n", "", var.name, var.name, default, indent = 8))
+                }
+            }
+        }
+        out.push_str(format!("{:indent$}}};\n", "", indent = 4).as_str());
+        out.push_str("}\n");
+        Ok(out)
+    }
+
+    /// An interface and a factory function of the same name,
+    /// properties without a default value are required unless they are optional
+    fn generate_ts(self, export: bool, symbols: &SymbolTable) -> Result<String> {
+        let export = if export { "export " } else { "" };
+        let ty = |var: &Var| var.ty.clone().unwrap_or_else(|| "any".to_string());
+        let required = |var: &Var| var.default.is_none() && !ty(var).ends_with("| undefined");
+
+        let mut out = format!("{}interface {} {{\n", export, self.name);
+        for var in &self.vars {
+            out.push_str(&format!("{:indent$}{}: {};\n", "", var.name, ty(var), indent = 4));
+        }
+        out.push_str("}\n\n");
+
+        let fields = self
+            .vars
+            .iter()
+            .filter(|x| x.computed.is_none())
+            .map(|var| {
+                let optional = if required(var) { "" } else { "?" };
+                format!("{}{}: {}", var.name, optional, ty(var))
+            })
+            .collect::<Vec<_>>();
+        let fields = if fields.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", fields.join("; "))
+        };
+        let stored = self.vars.iter().filter(|x| x.computed.is_none());
+        let arg_default = if stored.clone().any(required) { "" } else { " = {}" };
+        out.push_str(&format!(
+            "{}function {}(arg: {}{}): {} {{\n",
+            export, self.name, fields, arg_default, self.name
+        ));
+
+        out.push_str("    const { ");
+        for var in self.vars.iter().filter(|x| x.computed.is_none()) {
+            out.push_str(&format!("{}, ", var.name));
+        }
+        out.push_str("} = arg;\n");
+
+        out.push_str(format!("{:indent$}return {{\n", "", indent = 4).as_str());
+        for var in &self.vars {
+            match (&var.computed, &var.default) {
+                (Some(computed), _) => out.push_str(&self.accessors(var, computed, symbols)?),
+                (None, Some(default)) => {
+                    out.push_str(&format!("{:indent$}{}: {} ?? {},\n", "", var.name, var.name, default, indent = 8))
+                }
+                (None, None) => out.push_str(&format!("{:indent$}{},\n", "", var.name, indent = 8)),
+            }
         }
         out.push_str(format!("{:indent$}}};\n", "", indent = 4).as_str());
         out.push_str("}\n");
-        out
+        Ok(out)
     }
 }
 
+/// The properties of the `extensions` are collected as well
 #[allow(dead_code)]
-pub fn date_model2js_fn(node: &tree_sitter::Node, source: &String) -> String {
-    assert_eq!(node.kind(), "class_declaration");
-    let mut state = State::default();
-    state.source = source.clone();
-    state.collect(node);
-    state.generate()
+pub fn date_model2js_fn(node: &tree_sitter::Node, source: &str, extensions: &[Extension], symbols: &SymbolTable) -> Result<String> {
+    node.expect_kind("class_declaration", source)?;
+    let mut state = State {
+        source: source.into(),
+        ..Default::default()
+    };
+    state.collect(node)?;
+    for ext in extensions {
+        state.source = ext.source.clone();
+        state.collect(&ext.node)?;
+    }
+    state.generate(symbols)
+}
+
+/// TypeScript version of [`date_model2js_fn`], `known_types` are the models the properties may refer to
+pub fn date_model2ts_fn(
+    node: &tree_sitter::Node,
+    source: &str,
+    extensions: &[Extension],
+    known_types: &[String],
+    symbols: &SymbolTable,
+    export: bool,
+) -> Result<String> {
+    node.expect_kind("class_declaration", source)?;
+    let mut state = State {
+        source: source.into(),
+        known_types: Some(known_types.to_vec()),
+        ..Default::default()
+    };
+    state.collect(node)?;
+    for ext in extensions {
+        state.source = ext.source.clone();
+        state.collect(&ext.node)?;
+    }
+    state.generate_ts(export, symbols)
 }
 
 #[cfg(test)]
 mod test {
     use tree_sitter::Parser;
 
+    use crate::project::SymbolTable;
+
     #[allow(unused_imports)]
     use crate::utils::log_node_tree;
 
@@ -122,8 +261,79 @@ mod test {
 
         // log_node_tree(&node, 0, &SOURCE3.to_string());
 
-        let result = super::date_model2js_fn(&node, &SOURCE3.to_string());
+        let result = super::date_model2js_fn(&node, SOURCE3, &[], &SymbolTable::default()).unwrap();
         println!("result: {}", result);
         // assert_eq!(result, "[DatePicker.red, DatePicker.blue, SwiftColor({red: 22})]".to_string());
     }
+
+    #[test]
+    fn test_model_computed() {
+        let source = r#"
+struct Cart {
+    var price: Double = 0
+    var count: Double = 1
+    var total: Double {
+        price * count
+    }
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        let result = super::date_model2js_fn(&node, source, &[], &SymbolTable::default()).unwrap();
+        assert_eq!(
+            result,
+            r#"function Cart(arg) {
+    const { price, count, } = arg ?? {};
+    return {
+        price: price ?? 0,
+        count: count ?? 1,
+        get total() {
+            return this.price * this.count;
+        },
+    };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_model2ts() {
+        let source = r#"
+struct Movie {
+    var id = UUID()
+    var title: String
+    var rating: Double?
+    var cast: [Actor] = []
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        let result = super::date_model2ts_fn(&node, source, &[], &["Actor".to_string()], &SymbolTable::default(), true).unwrap();
+        assert_eq!(
+            result,
+            r#"export interface Movie {
+    id: string;
+    title: string;
+    rating: number | undefined;
+    cast: Actor[];
+}
+
+export function Movie(arg: { id?: string; title: string; rating?: number | undefined; cast?: Actor[] }): Movie {
+    const { id, title, rating, cast, } = arg;
+    return {
+        id: id ?? UUID(),
+        title,
+        rating,
+        cast: cast ?? [],
+    };
+}
+"#
+        );
+    }
 }
@@ -1,3 +1,7 @@
+use tree_sitter::Point;
+
+use crate::error::{NodeExt, Result};
+use crate::mapping::MappedCode;
 #[allow(unused_imports)]
 use crate::utils::log_node_tree;
 
@@ -7,50 +11,46 @@ enum ArgType {
     Value { value: String },
     PrefixValue { value: String },
 
-    Sub { code: String },
-    LabelSub { label: String, code: String },
+    Sub { code: MappedCode },
+    LabelSub { label: String, code: MappedCode },
 }
 
 impl ArgType {
     fn is_label(&self) -> bool {
-        match self {
-            ArgType::Label { .. } => true,
-            ArgType::LabelSub { .. } => true,
-            _ => false,
-        }
+        matches!(self, ArgType::Label { .. } | ArgType::LabelSub { .. })
     }
 }
 
-fn handle_value_arg(node: &tree_sitter::Node, source: &String) -> ArgType {
-    let first_child = node.child(0).unwrap();
-    let node_code = first_child.utf8_text(source.as_bytes()).unwrap();
+fn handle_value_arg(node: &tree_sitter::Node, source: &str) -> Result<ArgType> {
+    let first_child = node.child_at(0, source)?;
+    let node_code = first_child.text(source)?;
 
-    match first_child.kind() {
+    let arg = match first_child.kind() {
         "prefix_expression" => {
-            let name = first_child.child(1).unwrap();
-            let name_code = name.utf8_text(source.as_bytes()).unwrap();
+            let name = first_child.child_at(1, source)?;
+            let name_code = name.text(source)?;
 
             ArgType::PrefixValue {
                 value: name_code.to_string(),
             }
         }
         "call_expression" => {
-            let code = callexp2object(&first_child, source);
+            let code = callexp2mapped(&first_child, source, vec![])?;
             ArgType::Sub { code }
         }
         "value_argument_label" => {
             let label = node_code;
-            let value = node.child(2).unwrap();
+            let value = node.child_at(2, source)?;
 
             if value.kind() == "call_expression" {
-                let code = callexp2object(&value, source);
-                return ArgType::LabelSub {
+                let code = callexp2mapped(&value, source, vec![])?;
+                return Ok(ArgType::LabelSub {
                     label: label.to_string(),
                     code,
-                };
+                });
             }
 
-            let value_code = value.utf8_text(source.as_bytes()).unwrap();
+            let value_code = value.text(source)?;
 
             ArgType::Label {
                 label: label.to_string(),
@@ -60,52 +60,57 @@ fn handle_value_arg(node: &tree_sitter::Node, source: &String) -> ArgType {
         _ => ArgType::Value {
             value: node_code.to_string(),
         },
-    }
+    };
+
+    Ok(arg)
 }
 
 #[derive(Default, Debug)]
 struct State {
     source: String,
-    args: Vec<ArgType>,
+    /// With the position of the argument
+    args: Vec<(ArgType, Point)>,
     name: String,
     context: Vec<String>,
 }
 
 impl State {
-    fn collect(&mut self, node: &tree_sitter::Node) {
-        let node_code = node.utf8_text(self.source.as_bytes()).unwrap();
+    fn collect(&mut self, node: &tree_sitter::Node) -> Result<()> {
+        let node_code = node.text(&self.source)?;
 
         match node.kind() {
             "simple_identifier" => {
-                let parent_is_call = node.parent().unwrap().kind() == "call_expression";
+                let parent_is_call = node.parent_node(&self.source)?.kind() == "call_expression";
                 if parent_is_call {
                     self.name = node_code.to_string();
                 }
             }
             "value_argument" => {
-                let arg = handle_value_arg(node, &self.source);
-                self.args.push(arg);
-                return;
+                let arg = handle_value_arg(node, &self.source)?;
+                self.args.push((arg, node.start_position()));
+                return Ok(());
             }
             _ => {}
         }
 
         for i in 0..node.child_count() {
-            let child = node.child(i).unwrap();
-            self.collect(&child);
+            let child = node.child_at(i, &self.source)?;
+            self.collect(&child)?;
         }
+
+        Ok(())
     }
 
-    fn generate(mut self) -> String {
+    fn generate(mut self) -> MappedCode {
         let name = if !self.context.contains(&self.name) {
             format!("Swift{}", self.name)
         } else {
             self.name.clone()
         };
-        let mut out = format!("{}(", name);
+        let mut out = MappedCode::from(format!("{}(", name));
 
         // sort args, all value args first, then label args
-        self.args.sort_by(|a, b| {
+        self.args.sort_by(|(a, _), (b, _)| {
             if a.is_label() && !b.is_label() {
                 std::cmp::Ordering::Greater
             } else if !a.is_label() && b.is_label() {
@@ -117,7 +122,13 @@ impl State {
 
         let mut into_labels = false;
 
-        for arg in self.args {
+        for (arg, point) in self.args {
+            if arg.is_label() && !into_labels {
+                out.push_str("{");
+                into_labels = true;
+            }
+            out.mark(point);
+
             match arg {
                 ArgType::Value { value } => {
                     out.push_str(&format!("{}, ", value));
@@ -126,30 +137,22 @@ impl State {
                     out.push_str(&format!("{name}.{}, ", value));
                 }
                 ArgType::Label { label, value } => {
-                    if !into_labels {
-                        out.push_str("{");
-                        into_labels = true;
-                    }
-
                     out.push_str(&format!("{}: {}, ", label, value));
                 }
                 ArgType::Sub { code } => {
-                    out.push_str(&format!("{}, ", code));
+                    out.push_mapped(code);
+                    out.push_str(", ");
                 }
                 ArgType::LabelSub { label, code } => {
-                    if !into_labels {
-                        out.push_str("{");
-                        into_labels = true;
-                    }
-
-                    out.push_str(&format!("{}: {}, ", label, code));
+                    out.push_str(&format!("{}: ", label));
+                    out.push_mapped(code);
+                    out.push_str(", ");
                 }
             }
         }
 
         if into_labels {
-            out.pop();
-            out.pop();
+            out.code.truncate(out.code.len() - 2);
             out.push_str("}");
         }
 
@@ -162,22 +165,33 @@ impl State {
 /// Color(.sRGB, red: 0.98, green: 0.9, blue: 0.2) -->
 /// SwiftColor("sRGB", {red: 0.98, green: 0.9, blue: 0.2})
 #[allow(dead_code)]
-pub fn callexp2object(node: &tree_sitter::Node, source: &String) -> String {
-    assert_eq!(node.kind(), "call_expression");
-    let mut state = State::default();
-    state.source = source.clone();
-    state.collect(node);
-    state.generate()
+pub fn callexp2object(node: &tree_sitter::Node, source: &str) -> Result<String> {
+    callexp2object_with_context(node, source, vec![])
 }
 
 #[allow(dead_code)]
-pub fn callexp2object_with_context(node: &tree_sitter::Node, source: &String, context: Vec<String>) -> String {
-    assert_eq!(node.kind(), "call_expression");
-    let mut state = State::default();
-    state.source = source.clone();
-    state.collect(node);
-    state.context = context;
-    state.generate()
+pub fn callexp2object_with_context(
+    node: &tree_sitter::Node,
+    source: &str,
+    context: Vec<String>,
+) -> Result<String> {
+    Ok(callexp2mapped(node, source, context)?.code)
+}
+
+/// Same as [`callexp2object_with_context`], each argument is mapped to its position in `source`
+pub fn callexp2mapped(node: &tree_sitter::Node, source: &str, context: Vec<String>) -> Result<MappedCode> {
+    node.expect_kind("call_expression", source)?;
+    let mut state = State {
+        source: source.to_string(),
+        context,
+        ..Default::default()
+    };
+    state.collect(node)?;
+
+    let mut out = MappedCode::new();
+    out.mark(node.start_position());
+    out.push_mapped(state.generate());
+    Ok(out)
 }
 
 #[cfg(test)]
@@ -203,7 +217,25 @@ mod test {
         let tree = parser.parse(SOURCE3, None).unwrap();
         let node = tree.root_node().child(0).unwrap();
 
-        let result = super::callexp2object(&node, &SOURCE3.to_string());
+        let result = super::callexp2object(&node, SOURCE3).unwrap();
         assert_eq!(result, "SwiftColor(SwiftColor.sRGB, SwiftTest(2, {a: 123}), {red: 0.98, sub: SwiftTest(2, {a: 123})})".to_string());
     }
+
+    #[test]
+    fn test_callexp2object_unexpected_node() {
+        let lang = tree_sitter_swift::language();
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(&lang)
+            .expect("Error loading Rust grammar");
+
+        let source = "[1, 2]";
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().child(0).unwrap();
+
+        let err = super::callexp2object(&node, source).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::UnexpectedNode);
+        assert_eq!(err.span.unwrap().code, source);
+    }
 }
@@ -0,0 +1,919 @@
+//! 函数体中的语句：`if`/`guard`/`for ... in`/`while`/`repeat`/`do ... catch` 等控制流、`return`/`throw`/`break`/`continue`
+//! 和局部的 `let`/`var` 声明，表达式交给 [`StatementWriter::expr`]，所以视图中 `@State` 的 `.value`
+//! 在每一处读写都会加上
+
+use tree_sitter::Node;
+
+use crate::error::{CompileError, NodeExt, Result};
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+
+use super::enums::{if_case2js, switch2js, CasePattern, STATEMENT_KINDS};
+use super::global::property_parts;
+
+fn all_children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).collect()
+}
+
+/// Writes the statements of a body, the `switch` and `if case` over enums are written by [`switch2js`] and [`if_case2js`]
+pub trait StatementWriter {
+    fn source(&self) -> &str;
+
+    /// JavaScript of an expression
+    fn expr(&self, node: &Node) -> Result<String>;
+
+    /// JavaScript of a statement, every line indented by `indent`
+    fn statement(&self, node: &Node, indent: usize) -> Result<String>;
+
+    /// JavaScript of a `statements` node, one statement after another
+    fn statements(&self, node: &Node, indent: usize) -> Result<String> {
+        let mut out = String::new();
+        let mut cursor = node.walk();
+        for statement in node.named_children(&mut cursor) {
+            if statement.kind().ends_with("comment") {
+                continue;
+            }
+            out.push_str(&self.statement(&statement, indent)?);
+        }
+        Ok(out)
+    }
+
+    /// [`Self::statements`] of a block declaring `names`, e.g. `if let` and `catch`, a writer mapping the
+    /// members to other names hides the ones declared
+    fn scoped_statements(&self, node: &Node, indent: usize, names: &[String]) -> Result<String> {
+        let _ = names;
+        self.statements(node, indent)
+    }
+
+    /// The names of a local `let` or `var`, they hide the members until the end of the block
+    /// like the ones of [`Self::scoped_statements`]
+    fn declare(&self, names: &[String]) {
+        let _ = names;
+    }
+}
+
+/// The source of `node` with the nodes `replace` returns some code for replaced, their children aren't visited
+pub fn rewrite_expr(
+    node: &Node,
+    source: &str,
+    replace: &mut dyn FnMut(&Node) -> Result<Option<String>>,
+) -> Result<String> {
+    fn visit(
+        node: &Node,
+        source: &str,
+        replace: &mut dyn FnMut(&Node) -> Result<Option<String>>,
+        out: &mut String,
+        last: &mut usize,
+    ) -> Result<()> {
+        if let Some(replacement) = replace(node)? {
+            out.push_str(&source[*last..node.start_byte()]);
+            out.push_str(&replacement);
+            *last = node.end_byte();
+            return Ok(());
+        }
+
+        for i in 0..node.child_count() {
+            let child = node.child_at(i, source)?;
+            visit(&child, source, replace, out, last)?;
+        }
+        Ok(())
+    }
+
+    let mut out = String::new();
+    let mut last = node.start_byte();
+    visit(node, source, replace, &mut out, &mut last)?;
+    out.push_str(&source[last..node.end_byte()]);
+    Ok(out)
+}
+
+/// An optional binding `let name = value` of a condition, `if let name` has no value
+pub struct Binding<'a> {
+    pub keyword: &'a str,
+    pub name: Node<'a>,
+    pub value: Option<Node<'a>>,
+}
+
+impl<'a> Binding<'a> {
+    /// `let user = user` and `let user = self.user` only check the value, the name already refers to it
+    pub fn checks_only(&self, source: &str) -> Result<bool> {
+        let Some(value) = self.value else {
+            return Ok(true);
+        };
+        let (name, value) = (self.name.text(source)?, value.text(source)?);
+        Ok(value == name || value.strip_prefix("self.") == Some(name))
+    }
+}
+
+/// A condition of `if a, let b = c`
+pub enum Condition<'a> {
+    Binding(Binding<'a>),
+    Expr(Node<'a>),
+}
+
+/// The conditions of `if a, let b = c`, split at the `,`
+pub fn condition_parts<'a>(nodes: &[Node<'a>], source: &'a str) -> Result<Vec<Condition<'a>>> {
+    let mut parts = Vec::new();
+    for part in nodes.split(|x| x.kind() == ",").filter(|x| !x.is_empty()) {
+        match part {
+            [pattern, name, rest @ ..] if pattern.kind() == "value_binding_pattern" => parts.push(Condition::Binding(Binding {
+                keyword: pattern.text(source)?,
+                name: *name,
+                value: rest.iter().find(|x| x.kind() != "=").copied(),
+            })),
+            _ => parts.extend(part.iter().filter(|x| !x.kind().ends_with("comment")).map(|x| Condition::Expr(*x))),
+        }
+    }
+    Ok(parts)
+}
+
+/// The names declared by the optional bindings of an `if`, `guard` or `while`, the ones that only check
+/// a value of the same name are not declared again
+pub fn optional_bindings(node: &Node, source: &str) -> Result<Vec<String>> {
+    let children = all_children(node);
+    let end = children.iter().position(|x| ["{", "else"].contains(&x.kind())).unwrap_or(children.len());
+    let mut names = Vec::new();
+    for part in condition_parts(&children[1..end], source)? {
+        match part {
+            Condition::Binding(binding) if !binding.checks_only(source)? => names.push(binding.name.text(source)?.to_string()),
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+/// The conditions of `if a, b`, `guard a, b` or `while a, b` joined with `&&`
+struct Conditions {
+    code: String,
+    /// `let a, b;` or `const a = value;` of the optional bindings, written before the statement
+    declaration: Option<String>,
+    /// The conditions are a single optional binding, `x != null`
+    single_binding: bool,
+}
+
+/// An optional binding is checked with `!= null`. A leading binding that's the only one declared is
+/// `const x = value;` when `hoist` is set, the others are assigned in the condition, so the later
+/// values are only evaluated when the previous ones are not nil
+fn conditions(writer: &impl StatementWriter, node: &Node, nodes: &[Node], hoist: bool) -> Result<Conditions> {
+    let source = writer.source();
+    let parts = condition_parts(nodes, source)?;
+
+    let mut declared = Vec::new();
+    for part in &parts {
+        match part {
+            Condition::Binding(binding) if !binding.checks_only(source)? => declared.push(binding),
+            _ => {}
+        }
+    }
+    let hoisted = hoist
+        && match (parts.first(), declared.as_slice()) {
+            (Some(Condition::Binding(first)), [binding]) => first.name.id() == binding.name.id(),
+            _ => false,
+        };
+
+    let mut codes = Vec::new();
+    let mut declaration = None;
+    for part in &parts {
+        let code = match part {
+            Condition::Binding(binding) => {
+                let name = binding.name.text(source)?;
+                let value = binding.value.as_ref().unwrap_or(&binding.name);
+                if binding.checks_only(source)? {
+                    format!("{} != null", writer.expr(value)?)
+                } else {
+                    // `let node = node.next` would read the name before it's assigned in JavaScript
+                    if uses_name(value, name, source)? {
+                        return Err(CompileError::unsupported("暂不支持在可选绑定的值中使用同名的变量", value, source));
+                    }
+                    if hoisted {
+                        let keyword = if binding.keyword == "var" { "let" } else { "const" };
+                        declaration = Some(format!("{} {} = {};", keyword, name, writer.expr(value)?));
+                        format!("{} != null", name)
+                    } else {
+                        format!("({} = {}) != null", name, writer.expr(value)?)
+                    }
+                }
+            }
+            Condition::Expr(condition) => {
+                let code = writer.expr(condition)?;
+                // `a || b, c` is `(a || b) && c`
+                if parts.len() > 1 && ["disjunction_expression", "ternary_expression"].contains(&condition.kind()) {
+                    format!("({})", code)
+                } else {
+                    code
+                }
+            }
+        };
+        codes.push(code);
+    }
+
+    if codes.is_empty() {
+        return Err(CompileError::unexpected("expect a condition", node, source));
+    }
+    if declaration.is_none() && !declared.is_empty() {
+        let names = declared.iter().map(|x| x.name.text(source)).collect::<Result<Vec<_>>>()?;
+        declaration = Some(format!("let {};", names.join(", ")));
+    }
+    Ok(Conditions {
+        code: codes.join(" && "),
+        declaration,
+        single_binding: matches!(parts.as_slice(), [Condition::Binding(_)]),
+    })
+}
+
+/// Whether `name` is read in `node`, the members after `.` and the labels are other names
+fn uses_name(node: &Node, name: &str, source: &str) -> Result<bool> {
+    if node.kind() == "simple_identifier" {
+        return Ok(node.text(source)? == name);
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if !["navigation_suffix", "value_argument_label"].contains(&child.kind()) && uses_name(&child, name, source)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `{ declaration statement }`, the names of the optional bindings are only visible in the statement
+fn scoped(declaration: &str, statement: &str, indent: usize) -> String {
+    format!(
+        "{{\n{:inner$}{}\n{:inner$}{}\n{:indent$}}}",
+        "",
+        declaration,
+        "",
+        statement,
+        "",
+        inner = indent + 4,
+        indent = indent
+    )
+}
+
+/// `{ ... }` of the `statements` after the `{` at `open`, with the index after its `}`
+fn block(writer: &impl StatementWriter, children: &[Node], open: usize, indent: usize) -> Result<(String, usize)> {
+    scoped_block(writer, children, open, indent, &[])
+}
+
+/// [`block`] declaring `names`, see [`StatementWriter::scoped_statements`]
+fn scoped_block(
+    writer: &impl StatementWriter,
+    children: &[Node],
+    open: usize,
+    indent: usize,
+    names: &[String],
+) -> Result<(String, usize)> {
+    let mut out = "{\n".to_string();
+    let mut next = open + 1;
+    while let Some(child) = children.get(next) {
+        next += 1;
+        match child.kind() {
+            "statements" => out.push_str(&writer.scoped_statements(child, indent + 4, names)?),
+            "}" => break,
+            _ => {}
+        }
+    }
+    out.push_str(&format!("{:indent$}}}", "", indent = indent));
+    Ok((out, next))
+}
+
+/// The position of the first child of the `kind`
+fn position(node: &Node, children: &[Node], kind: &str, source: &str) -> Result<usize> {
+    children
+        .iter()
+        .position(|x| x.kind() == kind)
+        .ok_or_else(|| CompileError::unexpected(format!("expect `{}`", kind), node, source))
+}
+
+/// `if let x = value { ... }` --> `{ const x = value; if (x != null) { ... } }`
+fn if2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let open = position(node, &children, "{", source)?;
+    // the statement is put in a block with the declarations of the optional bindings
+    let names = optional_bindings(node, source)?;
+    let scoped_indent = if names.is_empty() { indent } else { indent + 4 };
+
+    let (body, next) = scoped_block(writer, &children, open, scoped_indent, &names)?;
+    let conditions = conditions(writer, node, &children[1..open], true)?;
+    let mut out = format!("if ({}) {}", conditions.code, body);
+
+    if children.get(next).map(|x| x.kind()) == Some("else") {
+        match children.get(next + 1) {
+            Some(else_if) if else_if.kind() == "if_statement" => {
+                out.push_str(" else ");
+                out.push_str(writer.statement(else_if, scoped_indent)?.trim());
+            }
+            Some(_) => {
+                let (body, _) = block(writer, &children, next + 1, scoped_indent)?;
+                out.push_str(&format!(" else {}", body));
+            }
+            None => {}
+        }
+    }
+    Ok(match conditions.declaration {
+        Some(declaration) => scoped(&declaration, &out, indent),
+        None => out,
+    })
+}
+
+/// `guard a else { return }` --> `if (!(a)) { return }`, the names of the optional bindings are declared
+/// before it, they are used after the `guard`
+fn guard2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let else_index = position(node, &children, "else", source)?;
+    let (body, _) = block(writer, &children, else_index + 1, indent)?;
+    let conditions = conditions(writer, node, &children[1..else_index], true)?;
+
+    let test = match conditions.code.strip_suffix(" != null") {
+        Some(value) if conditions.single_binding => format!("{} == null", value),
+        _ => format!("!({})", conditions.code),
+    };
+    Ok(match conditions.declaration {
+        Some(declaration) => format!("{}\n{:indent$}if ({}) {}", declaration, "", test, body, indent = indent),
+        None => format!("if ({}) {}", test, body),
+    })
+}
+
+/// `i` or `[index, item]` of `(index, item)`
+fn pattern2js(node: &Node, source: &str) -> Result<String> {
+    let code = node.text(source)?;
+    Ok(match code.strip_prefix('(').and_then(|x| x.strip_suffix(')')) {
+        Some(names) => format!("[{}]", names),
+        None => code.to_string(),
+    })
+}
+
+/// `for i in 0..<n` counts, `for (i, x) in xs.enumerated()` iterates over `entries()`, the others use `for ... of`
+fn for2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let pattern = children
+        .iter()
+        .find(|x| x.kind() == "pattern")
+        .ok_or_else(|| CompileError::unexpected("expect a loop variable", node, source))?;
+    let sequence = children
+        .get(position(node, &children, "in", source)? + 1)
+        .ok_or_else(|| CompileError::unexpected("expect a sequence", node, source))?;
+    let open = position(node, &children, "{", source)?;
+    let name = pattern2js(pattern, source)?;
+
+    let range = all_children(sequence);
+    let head = match (sequence.kind(), range.as_slice()) {
+        ("range_expression", [start, op, end]) if ["..<", "..."].contains(&op.kind()) => {
+            let compare = if op.kind() == "..<" { "<" } else { "<=" };
+            format!(
+                "for (let {name} = {}; {name} {compare} {}; {name}++)",
+                writer.expr(start)?,
+                writer.expr(end)?
+            )
+        }
+        _ => {
+            let code = writer.expr(sequence)?;
+            match code.strip_suffix(".enumerated()") {
+                Some(items) => format!("for (const {} of {}.entries())", name, items),
+                None => format!("for (const {} of {})", name, code),
+            }
+        }
+    };
+
+    let (mut body, _) = block(writer, &children, open, indent)?;
+    // `where` skips the elements that don't match
+    if let Some(clause) = children.iter().find(|x| x.kind() == "where_clause") {
+        if let Some(condition) = clause.named_children(&mut clause.walk()).find(|x| x.kind() != "where_keyword") {
+            let skip = format!("{:indent$}if (!({})) continue;\n", "", writer.expr(&condition)?, indent = indent + 4);
+            body.insert_str(2, &skip);
+        }
+    }
+    Ok(format!("{} {}", head, body))
+}
+
+/// `while let x = stack.popLast()` assigns `x` in the condition of every iteration
+fn while2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let open = position(node, &children, "{", source)?;
+    let names = optional_bindings(node, source)?;
+    let scoped_indent = if names.is_empty() { indent } else { indent + 4 };
+    let (body, _) = scoped_block(writer, &children, open, scoped_indent, &names)?;
+    let conditions = conditions(writer, node, &children[1..open], false)?;
+
+    let out = format!("while ({}) {}", conditions.code, body);
+    Ok(match conditions.declaration {
+        Some(declaration) => scoped(&declaration, &out, indent),
+        None => out,
+    })
+}
+
+/// `repeat { ... } while a` --> `do { ... } while (a);`
+fn repeat2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let open = position(node, &children, "{", source)?;
+    let (body, next) = block(writer, &children, open, indent)?;
+    let condition = children.get(next + 1..).unwrap_or_default();
+    Ok(format!("do {} while ({});", body, conditions(writer, node, condition, false)?.code))
+}
+
+/// `const x = 1;` of `let x = 1`, `let [a, b] = t;` of `var (a, b) = t`
+fn declaration2js(writer: &impl StatementWriter, node: &Node) -> Result<String> {
+    let source = writer.source();
+    let (keyword, _, _, value) = property_parts(node, source)?;
+    let pattern = node
+        .named_children(&mut node.walk())
+        .find(|x| x.kind() == "pattern")
+        .ok_or_else(|| CompileError::unexpected("expect a property name", node, source))?;
+    let name = pattern2js(&pattern, source)?;
+
+    let code = match value {
+        Some(value) => format!("{} {} = {};", keyword, name, writer.expr(&value)?),
+        None => format!("let {};", name),
+    };
+    // `let count = count` still reads the member
+    writer.declare(&pattern_names(&pattern, source)?);
+    Ok(code)
+}
+
+/// `a` and `b` of the pattern `(a, b)`
+fn pattern_names(node: &Node, source: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for child in all_children(node) {
+        match child.kind() {
+            "simple_identifier" => names.push(child.text(source)?.to_string()),
+            "pattern" => names.extend(pattern_names(&child, source)?),
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+/// `return x`, `throw x`, `break` and `continue`
+fn control_transfer2js(writer: &impl StatementWriter, node: &Node) -> Result<String> {
+    let source = writer.source();
+    let keyword = node.child_at(0, source)?;
+    let mut cursor = node.walk();
+    let value = node.named_children(&mut cursor).find(|x| x.kind() != "throw_keyword");
+    if let Some(value) = value.filter(|x| x.kind() == "range_expression") {
+        return Err(CompileError::unsupported("暂不支持返回区间", &value, source));
+    }
+    Ok(match value {
+        Some(value) => format!("{} {};", keyword.text(source)?, writer.expr(&value)?),
+        None => format!("{};", keyword.text(source)?),
+    })
+}
+
+/// The test of a `catch` pattern on the caught `error` and the name it binds the error to.
+/// `None` for a `catch` of every error
+fn catch_pattern(pattern: &Node, source: &str) -> Result<(Option<String>, Option<String>, Option<CasePattern>)> {
+    let children = all_children(pattern);
+    let type_test = |ty: &Node| -> Result<String> { Ok(format!("SwiftError.is(error, \"{}\")", ty.text(source)?)) };
+
+    Ok(match children.as_slice() {
+        // `let e`
+        [binding, name] if binding.kind() == "value_binding_pattern" => (None, Some(name.text(source)?.to_string()), None),
+        // `let e as LoadError`
+        [binding, as_, ty] if binding.kind() == "pattern" && as_.kind() == "as" => {
+            let name = binding.named_children(&mut binding.walk()).find(|x| x.kind() == "simple_identifier");
+            (Some(type_test(ty)?), name.map(|x| x.text(source).map(str::to_string)).transpose()?, None)
+        }
+        // `is LoadError`
+        [is, ty] if is.kind() == "is" => (Some(type_test(ty)?), None, None),
+        // `LoadError.notFound` or `.invalid(let message)`
+        _ => {
+            let (ty, rest) = match children.split_first() {
+                Some((ty, rest)) if ty.kind() == "user_type" => (Some(ty), rest),
+                _ => (None, children.as_slice()),
+            };
+            let Some(case) = CasePattern::parse(rest, source)? else {
+                return Err(CompileError::unsupported("暂不支持这种 catch 模式", pattern, source));
+            };
+            let matches = format!("SwiftEnum.matches(error, \"{}\")", case.case);
+            let test = match ty {
+                Some(ty) => format!("{} && {}", type_test(ty)?, matches),
+                None => matches,
+            };
+            (Some(test), None, Some(case))
+        }
+    })
+}
+
+/// `do { ... } catch let e as LoadError { ... } catch { ... }` --> `try { ... } catch (error) { if (...) { ... } else { ... } }`,
+/// the error is thrown again if no `catch` matches it
+fn do2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let source = writer.source();
+    let children = all_children(node);
+    let body = children.iter().find(|x| x.kind() == "statements");
+    let body = match body {
+        Some(body) => format!("{{\n{}{:indent$}}}", writer.statements(body, indent + 4)?, "", indent = indent),
+        None => "{}".to_string(),
+    };
+    let catches = children.iter().filter(|x| x.kind() == "catch_block").collect::<Vec<_>>();
+    // `do { ... }` alone is a scope
+    if catches.is_empty() {
+        return Ok(body);
+    }
+
+    let mut branches = Vec::new();
+    for catch in &catches {
+        let mut cursor = catch.walk();
+        let catch_children = catch.named_children(&mut cursor).collect::<Vec<_>>();
+        if catch_children.iter().any(|x| x.kind() == "where_clause") {
+            return Err(CompileError::unsupported("暂不支持 catch 的 where 子句", catch, source));
+        }
+        let (test, name, case) = match catch_children.iter().find(|x| x.kind() == "pattern") {
+            Some(pattern) => catch_pattern(pattern, source)?,
+            // the error is `error` in the `catch`
+            None => (None, None, None),
+        };
+        let statements = catch_children.iter().find(|x| x.kind() == "statements");
+        branches.push((test, name, case, statements.copied()));
+    }
+
+    // the `catch` after the one of every error never run
+    if let Some(all) = branches.iter().position(|x| x.0.is_none()) {
+        branches.truncate(all + 1);
+    }
+    // a single `catch` of every error names the error itself
+    if let [(None, name, None, statements)] = branches.as_slice() {
+        let name = name.as_deref().unwrap_or("error");
+        let statements = statements
+            .map(|x| writer.scoped_statements(&x, indent + 4, &[name.to_string()]))
+            .transpose()?
+            .unwrap_or_default();
+        return Ok(format!("try {} catch ({}) {{\n{}{:indent$}}}", body, name, statements, "", indent = indent));
+    }
+
+    let mut out = format!("try {} catch (error) {{\n{:inner$}", body, "", inner = indent + 4);
+    for (i, (test, name, case, statements)) in branches.iter().enumerate() {
+        match test {
+            Some(test) if i == 0 => out.push_str(&format!("if ({}) {{\n", test)),
+            Some(test) => out.push_str(&format!(" else if ({}) {{\n", test)),
+            None => out.push_str(" else {\n"),
+        }
+        if let Some(name) = name.as_deref().filter(|x| *x != "error") {
+            out.push_str(&format!("{:indent$}const {} = error;\n", "", name, indent = indent + 8));
+        }
+        if let Some(destructure) = case.as_ref().and_then(|x| x.destructure("error")) {
+            out.push_str(&format!("{:indent$}{}\n", "", destructure, indent = indent + 8));
+        }
+        if let Some(statements) = statements {
+            let mut names = vec!["error".to_string()];
+            names.extend(name.clone());
+            names.extend(case.iter().flat_map(|x| x.names().cloned()));
+            out.push_str(&writer.scoped_statements(statements, indent + 8, &names)?);
+        }
+        out.push_str(&format!("{:indent$}}}", "", indent = indent + 4));
+    }
+    if branches.last().is_some_and(|x| x.0.is_some()) {
+        out.push_str(&format!(" else {{\n{:indent$}throw error;\n{:inner$}}}", "", "", indent = indent + 8, inner = indent + 4));
+    }
+    out.push_str(&format!("\n{:indent$}}}", "", indent = indent));
+    Ok(out)
+}
+
+/// The parameters of a closure, `_` are numbered so they don't clash. Without a parameter list they are
+/// the shorthand arguments `$0`, `$1` the body refers to, they are valid names in JavaScript
+pub fn closure_params(node: &Node, source: &str) -> Result<Vec<String>> {
+    fn shorthand(node: &Node, source: &str, count: &mut usize) -> Result<()> {
+        if node.kind() == "simple_identifier" {
+            if let Some(Ok(index)) = node.text(source)?.strip_prefix('$').map(str::parse::<usize>) {
+                *count = (*count).max(index + 1);
+            }
+        }
+        let mut cursor = node.walk();
+        // a nested closure has its own arguments
+        for child in node.children(&mut cursor).filter(|x| x.kind() != "lambda_literal") {
+            shorthand(&child, source, count)?;
+        }
+        Ok(())
+    }
+
+    let mut cursor = node.walk();
+    let parameters = node
+        .children(&mut cursor)
+        .find(|x| x.kind() == "lambda_function_type")
+        .and_then(|x| crate::utils::find_first_node(x, "lambda_function_type_parameters"));
+    let Some(parameters) = parameters else {
+        let mut count = 0;
+        shorthand(node, source, &mut count)?;
+        return Ok((0..count).map(|i| format!("${}", i)).collect());
+    };
+
+    let mut params = Vec::new();
+    let mut cursor = parameters.walk();
+    for (i, parameter) in parameters.named_children(&mut cursor).enumerate() {
+        let name = parameter.child_at(0, source)?;
+        match name.text(source)? {
+            "_" => params.push(format!("_{}", i)),
+            name => params.push(name.to_string()),
+        }
+    }
+    Ok(params)
+}
+
+/// `{ x in x + 1 }` --> `(x) => x + 1`. A closure of a single expression returns it, the others are a block,
+/// written on one line unless there is an `indent` for its `}`
+pub fn closure2js(writer: &impl StatementWriter, node: &Node, indent: Option<usize>) -> Result<String> {
+    closure2js_with_params(writer, node, &closure_params(node, writer.source())?, indent)
+}
+
+/// [`closure2js`] with the parameters named `params`, e.g. renamed not to hide a member
+pub fn closure2js_with_params(
+    writer: &impl StatementWriter,
+    node: &Node,
+    params: &[String],
+    indent: Option<usize>,
+) -> Result<String> {
+    let code = closure_body(writer, node, &params.join(", "), indent)?;
+
+    // `{ ... }()` is called right away
+    let called = node.parent().filter(|x| x.kind() == "call_expression").and_then(|x| x.child(0));
+    if called.map(|x| x.id()) == Some(node.id()) {
+        return Ok(format!("({})", code));
+    }
+    Ok(code)
+}
+
+fn closure_body(writer: &impl StatementWriter, node: &Node, params: &str, indent: Option<usize>) -> Result<String> {
+    let mut cursor = node.walk();
+    let Some(statements) = node.children(&mut cursor).find(|x| x.kind() == "statements") else {
+        return Ok(format!("({}) => {{}}", params));
+    };
+    let mut cursor = statements.walk();
+    let body = statements
+        .named_children(&mut cursor)
+        .filter(|x| !x.kind().ends_with("comment"))
+        .collect::<Vec<_>>();
+    if let [value] = body.as_slice() {
+        if !STATEMENT_KINDS.contains(&value.kind()) {
+            let code = writer.expr(value)?;
+            // `{}` of the body would be a block
+            let code = if code.starts_with('{') { format!("({})", code) } else { code };
+            return Ok(format!("({}) => {}", params, code));
+        }
+    }
+
+    Ok(match indent {
+        Some(indent) => format!(
+            "({}) => {{\n{}{:indent$}}}",
+            params,
+            writer.statements(&statements, indent + 4)?,
+            "",
+            indent = indent
+        ),
+        None => {
+            let code = writer.statements(&statements, 0)?;
+            let lines = code.lines().map(str::trim).filter(|x| !x.is_empty()).collect::<Vec<_>>();
+            format!("({}) => {{ {} }}", params, lines.join(" "))
+        }
+    })
+}
+
+/// A Swift statement in a function body, every line indented by `indent`.
+/// The writers call it for the statements they don't treat specially
+pub fn statement2js(writer: &impl StatementWriter, node: &Node, indent: usize) -> Result<String> {
+    let code = match node.kind() {
+        "switch_statement" => return switch2js(writer, node, indent),
+        "if_statement" => match if_case2js(writer, node, indent)? {
+            Some(code) => return Ok(code),
+            None => if2js(writer, node, indent)?,
+        },
+        "guard_statement" => guard2js(writer, node, indent)?,
+        "for_statement" => for2js(writer, node, indent)?,
+        "while_statement" => while2js(writer, node, indent)?,
+        "repeat_while_statement" => repeat2js(writer, node, indent)?,
+        "do_statement" => do2js(writer, node, indent)?,
+        "property_declaration" => declaration2js(writer, node)?,
+        "control_transfer_statement" => control_transfer2js(writer, node)?,
+        // the rest of `return a\n...\nb`, the range is split at the line break
+        "fully_open_range" => return Err(CompileError::unsupported("暂不支持返回区间", node, writer.source())),
+        _ => format!("{};", writer.expr(node)?),
+    };
+
+    Ok(format!("{:indent$}{}\n", "", code, indent = indent))
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::project::SymbolTable;
+
+    const SOURCE: &str = r#"func total(_ prices: [Int], limit: Int, bounds: (Int, Int)) -> Int {
+    var sum = 0
+    guard limit > 0 else {
+        return 0
+    }
+    for i in 0..<prices.count where i % 2 == 0 {
+        if prices[i] > limit {
+            continue
+        } else if prices[i] == 0 {
+            break
+        } else {
+            sum += prices[i]
+        }
+    }
+    for (index, price) in prices.enumerated() {
+        let (low, high) = bounds
+    }
+    while sum > limit, limit > 1 {
+        sum -= 1
+    }
+    repeat {
+        sum += 1
+    } while sum < 0
+    return sum
+}
+"#;
+
+    #[test]
+    fn test_statement2js() {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(SOURCE, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+
+        let code = crate::common::global::global2js(&node, SOURCE, &SymbolTable::default(), false).unwrap();
+        assert_eq!(
+            code,
+            r#"export function total(prices, limit, bounds) {
+    let sum = 0;
+    if (!(limit > 0)) {
+        return 0;
+    }
+    for (let i = 0; i < prices.length; i++) {
+        if (!(i % 2 === 0)) continue;
+        if (prices[i] > limit) {
+            continue;
+        } else if (prices[i] === 0) {
+            break;
+        } else {
+            sum += prices[i];
+        }
+    }
+    for (const [index, price] of prices.entries()) {
+        const [low, high] = bounds;
+    }
+    while (sum > limit && limit > 1) {
+        sum -= 1;
+    }
+    do {
+        sum += 1;
+    } while (sum < 0);
+    return sum;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_closure2js() {
+        let source = r#"func report(_ prices: [Int]) {
+    let doubled = prices.map { $0 * 2 }
+    let total = prices.reduce(0) { sum, price in sum + price }
+    let sorted = prices.sorted { a, _ in a > 0 }
+    prices.forEach { price in
+        if price > 0 {
+            print(price)
+        }
+    }
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+
+        let code = crate::common::global::global2js(&node, source, &SymbolTable::default(), false).unwrap();
+        assert_eq!(
+            code,
+            r#"export function report(prices) {
+    const doubled = prices.map(($0) => $0 * 2);
+    const total = prices.reduce((sum, price) => sum + price, 0);
+    const sorted = [...prices].sort((a, b) => ((a, _1) => a > 0)(a, b) ? -1 : ((a, _1) => a > 0)(b, a) ? 1 : 0);
+    prices.forEach((price) => { if (price > 0) { console.log(price); } });
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_optional2js() {
+        let source = r#"func greet(_ user: User?, _ names: [String], _ stack: [Int]) -> String {
+    guard let user = user else {
+        return ""
+    }
+    guard let first = names.first, !first.isEmpty else {
+        return user.name
+    }
+    if let nickname = user.nickname, let initial = nickname.first {
+        print(initial)
+    } else if let age = user.age {
+        print(age)
+    }
+    while let top = stack.popLast() {
+        print(top)
+    }
+    return first + user.email!
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+
+        let code = crate::common::global::global2js(&node, source, &SymbolTable::default(), false).unwrap();
+        assert_eq!(
+            code,
+            r#"export function greet(user, names, stack) {
+    if (user == null) {
+        return "";
+    }
+    const first = names[0];
+    if (!(first != null && !(first.length === 0))) {
+        return user.name;
+    }
+    {
+        let nickname, initial;
+        if ((nickname = user.nickname) != null && (initial = nickname[0]) != null) {
+            console.log(initial);
+        } else {
+            const age = user.age;
+            if (age != null) {
+                console.log(age);
+            }
+        }
+    }
+    {
+        let top;
+        while ((top = stack.pop()) != null) {
+            console.log(top);
+        }
+    }
+    return first + SwiftOptional.unwrap(user.email);
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_do2js() {
+        let source = r#"func load(_ name: String) throws -> Int {
+    if name.isEmpty {
+        throw LoadError.notFound
+    }
+    do {
+        let value = try decode(name)
+        return value
+    } catch let e as LoadError {
+        print(e)
+    } catch LoadError.invalid(let message) {
+        print(message)
+    }
+    do {
+        try save(name)
+    } catch {
+        print(error)
+    }
+    let cached = try? cache(name)
+    return try! parse(name) + (cached ?? 0)
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+
+        let code = crate::common::global::global2js(&node, source, &SymbolTable::default(), false).unwrap();
+        assert_eq!(
+            code,
+            r#"export function load(name) {
+    if ((name.length === 0)) {
+        throw LoadError.notFound;
+    }
+    try {
+        const value = decode(name);
+        return value;
+    } catch (error) {
+        if (SwiftError.is(error, "LoadError")) {
+            const e = error;
+            console.log(e);
+        } else if (SwiftError.is(error, "LoadError") && SwiftEnum.matches(error, "invalid")) {
+            const [message] = SwiftEnum.values(error);
+            console.log(message);
+        } else {
+            throw error;
+        }
+    }
+    try {
+        save(name);
+    } catch (error) {
+        console.log(error);
+    }
+    const cached = SwiftError.attempt(() => cache(name));
+    return SwiftError.force(() => parse(name)) + (cached ?? 0);
+}
+"#
+        );
+    }
+}
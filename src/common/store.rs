@@ -0,0 +1,241 @@
+//! `ObservableObject` 类转换为 reactive store：存储属性（包括 `@Published`）放在 `reactive()` 对象中，
+//! 计算属性是这个对象的 getter/setter，方法转换为 function，通过 `self` 读写属性，
+//! 视图中的 `@StateObject`/`@ObservedObject` 就是这个对象
+
+use tree_sitter::Node;
+
+use crate::error::{CompileError, NodeExt, Result};
+use crate::paser::Extension;
+use crate::project::SymbolTable;
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+use crate::utils::find_first_node;
+
+use super::statement::StatementWriter;
+use super::global::{accessors2js, find_statements, function_parts, property_parts, GlobalWriter};
+use super::types::swift_type2ts;
+
+/// The declarations in the body of the class and of its extensions, with the source of the file they are in
+fn class_members<'a, 's>(node: &Node<'a>, source: &'s str, extensions: &'s [Extension<'a>]) -> Vec<(Node<'a>, &'s str)> {
+    let mut members = Vec::new();
+    let declarations = std::iter::once((*node, source)).chain(extensions.iter().map(|x| (x.node, &*x.source)));
+    for (decl, source) in declarations {
+        let mut cursor = decl.walk();
+        for body in decl.named_children(&mut cursor).filter(|x| x.kind() == "class_body") {
+            let mut cursor = body.walk();
+            members.extend(body.named_children(&mut cursor).map(|x| (x, source)));
+        }
+    }
+    members
+}
+
+/// A factory function of the store, every `ViewModel()` creates a new reactive object.
+/// In TypeScript, a type of the same name is the object it returns
+pub fn store2js(node: &Node, source: &str, extensions: &[Extension], symbols: &SymbolTable, typescript: bool) -> Result<String> {
+    let known = symbols.type_names();
+    let name = node
+        .named_children(&mut node.walk())
+        .find(|x| x.kind() == "type_identifier")
+        .map(|x| x.text(source).map(|x| x.to_string()))
+        .transpose()?
+        .ok_or_else(|| CompileError::unexpected("expect a class name", node, source))?;
+
+    let mut properties = Vec::new();
+    let mut functions = Vec::new();
+    let mut init = None;
+    for (member, source) in class_members(node, source, extensions) {
+        match member.kind() {
+            "property_declaration" => properties.push((member, source)),
+            "function_declaration" => functions.push((member, source)),
+            "init_declaration" => {
+                let mut cursor = member.walk();
+                if member.children(&mut cursor).any(|x| x.kind() == "parameter") {
+                    return Err(CompileError::unsupported("暂不支持带参数的 init", &member, source));
+                }
+                init = find_first_body(&member).map(|x| (x, source));
+            }
+            _ => {}
+        }
+    }
+
+    // the initial values can't refer to the other properties, like in Swift.
+    // The writers are switched to the source of the member they write
+    let mut values = GlobalWriter::new(source, symbols);
+    let mut writer = GlobalWriter::new(source, symbols);
+
+    let mut parts = Vec::new();
+    for (function, source) in &functions {
+        values.source = source;
+        parts.push((function_parts(&values, function, typescript)?, *source));
+    }
+    writer.methods = parts.iter().map(|(x, _)| x.name.clone()).collect();
+    for (property, source) in &properties {
+        let name = find_first_node(*property, "pattern")
+            .ok_or_else(|| CompileError::unexpected("expect a property name", property, source))?;
+        writer.members.push(name.text(source)?.to_string());
+    }
+
+    let mut out = format!("export function {}() {{\n    const self = reactive({{\n", name);
+    for (property, source) in properties {
+        values.source = source;
+        writer.source = source;
+        let mut cursor = property.walk();
+        let ty = property.children(&mut cursor).find(|x| x.kind() == "type_annotation");
+        let ts_type = match &ty {
+            Some(ty) if typescript => Some(swift_type2ts(ty, source, &known)?),
+            _ => None,
+        };
+
+        // a getter of the reactive object, it reads the properties through `self`
+        if let Some(computed) = find_first_node(property, "computed_property") {
+            let name = find_first_node(property, "pattern").map(|x| x.text(source)).transpose()?.unwrap_or_default();
+            out.push_str(&accessors2js(&writer, name, &computed, ts_type.as_deref(), 8)?);
+            continue;
+        }
+
+        let (_, name, _, value) = property_parts(&property, source)?;
+        let enum_type = ty
+            .map(|x| x.text(source).map(|x| x.trim_start_matches(':').trim().to_string()))
+            .transpose()?;
+
+        let value = match value {
+            Some(value) => {
+                let value = values.typed_expr(&value, enum_type.as_deref())?;
+                match ts_type {
+                    Some(ts_type) => format!("{} as {}", value, ts_type),
+                    None => value,
+                }
+            }
+            // it's set later, e.g. by a method
+            None => match ts_type {
+                Some(ts_type) if ts_type.ends_with("| undefined") => format!("undefined as {}", ts_type),
+                Some(ts_type) => format!("undefined as {} | undefined", ts_type),
+                None => "undefined".to_string(),
+            },
+        };
+        out.push_str(&format!("        {}: {},\n", name, value));
+    }
+    out.push_str("    });\n");
+
+    for (function, source) in &parts {
+        writer.source = source;
+        out.push_str(&format!(
+            "\n    function {}({}){} {{\n",
+            function.name,
+            function.declarations.join(", "),
+            function.return_type
+        ));
+        if let Some(body) = function.body {
+            // a parameter hides the property of the same name
+            let members = writer.members.clone();
+            writer.members.retain(|x| !function.params.contains(x));
+            out.push_str(&writer.statements(&body, 8)?);
+            writer.members = members;
+        }
+        out.push_str("    }\n");
+    }
+
+    if let Some((init, source)) = init {
+        writer.source = source;
+        out.push('\n');
+        out.push_str(&writer.statements(&init, 4)?);
+    }
+
+    if writer.methods.is_empty() {
+        out.push_str("\n    return self;\n}\n");
+    } else {
+        out.push_str(&format!("\n    return Object.assign(self, {{ {} }});\n}}\n", writer.methods.join(", ")));
+    }
+    if typescript {
+        out.push_str(&format!("export type {} = ReturnType<typeof {}>;\n", name, name));
+    }
+
+    Ok(out)
+}
+
+/// The statements of an `init`
+fn find_first_body<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    let body = node.named_children(&mut cursor).find(|x| x.kind() == "function_body");
+    body.and_then(|x| find_statements(&x))
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::project::SymbolTable;
+
+    const SOURCE: &str = r#"class ViewModel: ObservableObject {
+    @Published var movies: [Movie] = []
+    @Published var selected: Movie?
+    var count = 0
+    var isEmpty: Bool { count == 0 }
+
+    init() {
+        reload()
+    }
+
+    func add(_ movie: Movie) {
+        movies = movies + [movie]
+        self.count += 1
+    }
+
+    func reload() {
+        add(Movie(title: "Up"))
+    }
+}
+"#;
+
+    fn convert(typescript: bool) -> String {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(SOURCE, None).unwrap();
+        let node = tree.root_node().named_child(0).unwrap();
+
+        let mut symbols = SymbolTable::default();
+        symbols.models.insert("Movie".to_string());
+        symbols.stores.insert("ViewModel".to_string());
+        super::store2js(&node, SOURCE, &[], &symbols, typescript).unwrap()
+    }
+
+    #[test]
+    fn test_store2js() {
+        assert_eq!(
+            convert(false),
+            r#"export function ViewModel() {
+    const self = reactive({
+        movies: [],
+        selected: undefined,
+        count: 0,
+        get isEmpty() {
+            return self.count === 0;
+        },
+    });
+
+    function add(movie) {
+        self.movies = self.movies + [movie];
+        self.count += 1;
+    }
+
+    function reload() {
+        add(Movie({title: "Up"}));
+    }
+
+    reload();
+
+    return Object.assign(self, { add, reload });
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_store2ts() {
+        let code = convert(true);
+        assert!(code.contains("        movies: [] as Movie[],\n        selected: undefined as Movie | undefined,\n"));
+        assert!(code.contains("    function add(movie: Movie) {\n"));
+        assert!(code.contains("        get isEmpty(): boolean {\n"));
+        assert!(code.ends_with("export type ViewModel = ReturnType<typeof ViewModel>;\n"));
+    }
+}
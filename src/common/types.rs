@@ -0,0 +1,207 @@
+use crate::error::{NodeExt, Result};
+#[allow(unused_imports)]
+use crate::utils::log_node_tree;
+
+/// TypeScript type of a Swift type node, `type_annotation` nodes are accepted as well.
+/// Types that aren't builtin and not in `known` become `any`
+pub fn swift_type2ts(node: &tree_sitter::Node, source: &str, known: &[String]) -> Result<String> {
+    let convert = |node: &tree_sitter::Node| swift_type2ts(node, source, known);
+
+    let ty = match node.kind() {
+        "type_annotation" => match named(node).last() {
+            Some(child) => convert(child)?,
+            None => "any".to_string(),
+        },
+        "user_type" => {
+            let children = named(node);
+            let names = children
+                .iter()
+                .filter(|x| x.kind() == "type_identifier")
+                .collect::<Vec<_>>();
+            let args = match children.iter().find(|x| x.kind() == "type_arguments") {
+                Some(args) => named(args).iter().map(convert).collect::<Result<Vec<_>>>()?,
+                None => vec![],
+            };
+
+            // nested types like `Foo.Bar` aren't declared in TypeScript
+            if names.len() != 1 {
+                return Ok("any".to_string());
+            }
+
+            user_type2ts(names[0].text(source)?, args, known)
+        }
+        "array_type" => match named(node).first() {
+            Some(element) => array_of(&convert(element)?),
+            None => "any[]".to_string(),
+        },
+        "dictionary_type" => {
+            let children = named(node);
+            match (children.first(), children.last()) {
+                (Some(key), Some(value)) if children.len() == 2 => {
+                    format!("Record<{}, {}>", convert(key)?, convert(value)?)
+                }
+                _ => "Record<string, any>".to_string(),
+            }
+        }
+        "optional_type" | "implicitly_unwrapped_type" => match named(node).first() {
+            Some(wrapped) => optional_of(&convert(wrapped)?),
+            None => "any".to_string(),
+        },
+        "tuple_type" => {
+            let items = named(node)
+                .iter()
+                .map(|item| match named(item).last() {
+                    Some(ty) => convert(ty),
+                    None => Ok("any".to_string()),
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            match items.len() {
+                0 => "void".to_string(),
+                // `(Int)` is only parenthesized
+                1 => items[0].clone(),
+                _ => format!("[{}]", items.join(", ")),
+            }
+        }
+        "function_type" => {
+            let children = named(node);
+            let params = match children.first() {
+                Some(params) if params.kind() == "tuple_type" => named(params)
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let ty = match named(item).last() {
+                            Some(ty) => convert(ty)?,
+                            None => "any".to_string(),
+                        };
+                        Ok(format!("arg{}: {}", i, ty))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                _ => vec![],
+            };
+            let ret = match children.last() {
+                Some(ret) if children.len() > 1 => convert(ret)?,
+                _ => "void".to_string(),
+            };
+
+            format!("({}) => {}", params.join(", "), ret)
+        }
+        _ => "any".to_string(),
+    };
+
+    Ok(ty)
+}
+
+/// TypeScript type of a property's default value, for properties declared without a type
+pub fn infer_ts_type(node: &tree_sitter::Node, source: &str, known: &[String]) -> Result<String> {
+    let ty = match node.kind() {
+        "integer_literal" | "real_literal" => "number".to_string(),
+        "line_string_literal" | "multi_line_string_literal" => "string".to_string(),
+        "boolean_literal" => "boolean".to_string(),
+        "call_expression" => {
+            let callee = node.child_at(0, source)?;
+            match callee.text(source)? {
+                "UUID" => "string".to_string(),
+                name if known.iter().any(|x| x == name) => name.to_string(),
+                _ => "any".to_string(),
+            }
+        }
+        _ => "any".to_string(),
+    };
+
+    Ok(ty)
+}
+
+fn named<'a>(node: &tree_sitter::Node<'a>) -> Vec<tree_sitter::Node<'a>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor).collect()
+}
+
+fn user_type2ts(name: &str, args: Vec<String>, known: &[String]) -> String {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or_else(|| "any".to_string());
+
+    match name {
+        "Int" | "Int8" | "Int16" | "Int32" | "Int64" | "UInt" | "UInt8" | "UInt16" | "UInt32" | "UInt64"
+        | "Double" | "Float" | "CGFloat" | "Decimal" => "number".to_string(),
+        "String" | "Character" | "Substring" | "UUID" => "string".to_string(),
+        "Bool" => "boolean".to_string(),
+        "Void" => "void".to_string(),
+        "Array" => array_of(&arg(0)),
+        "Set" => format!("Set<{}>", arg(0)),
+        "Dictionary" => format!("Record<{}, {}>", arg(0), arg(1)),
+        "Optional" => optional_of(&arg(0)),
+        _ if known.iter().any(|x| x == name) => name.to_string(),
+        _ => "any".to_string(),
+    }
+}
+
+fn needs_parens(ty: &str) -> bool {
+    ty.contains('|') || ty.contains("=>")
+}
+
+fn array_of(element: &str) -> String {
+    if needs_parens(element) {
+        format!("({})[]", element)
+    } else {
+        format!("{}[]", element)
+    }
+}
+
+fn optional_of(wrapped: &str) -> String {
+    if wrapped.ends_with("| undefined") {
+        wrapped.to_string()
+    } else if wrapped.contains("=>") {
+        format!("({}) | undefined", wrapped)
+    } else {
+        format!("{} | undefined", wrapped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Parser;
+
+    use crate::utils::find_first_node;
+
+    fn ts_type(swift_type: &str) -> String {
+        let source = format!("struct A {{\n    var a: {}\n}}", swift_type);
+
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(&source, None).unwrap();
+
+        let node = find_first_node(tree.root_node(), "type_annotation").unwrap();
+        super::swift_type2ts(&node, &source, &["Movie".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_swift_type2ts() {
+        assert_eq!(ts_type("Int"), "number");
+        assert_eq!(ts_type("String?"), "string | undefined");
+        assert_eq!(ts_type("[Movie]"), "Movie[]");
+        assert_eq!(ts_type("[String: [Int]]"), "Record<string, number[]>");
+        assert_eq!(ts_type("Set<Movie>"), "Set<Movie>");
+        assert_eq!(ts_type("[Int?]"), "(number | undefined)[]");
+        assert_eq!(ts_type("(Int, Bool)"), "[number, boolean]");
+        assert_eq!(ts_type("(String) -> Void"), "(arg0: string) => void");
+        assert_eq!(ts_type("Color"), "any");
+    }
+
+    #[test]
+    fn test_infer_ts_type() {
+        let source = "let a = 1\nlet b = \"b\"\nlet c = UUID()\nlet d = Movie(title: \"x\")\nlet e = .red";
+
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_swift::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let root = tree.root_node();
+        let types = (0..root.child_count())
+            .map(|i| {
+                let value = root.child(i).unwrap().child(3).unwrap();
+                super::infer_ts_type(&value, source, &["Movie".to_string()]).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["number", "string", "string", "Movie", "any"]);
+    }
+}
@@ -1,17 +1,20 @@
+//! Note that child is a special key, means child str content instead of modifier
+
 #![allow(unused_imports)]
 use crate::{
-    common,
+    common::{self, expr::ExprFn},
+    error::{CompileError, NodeExt, Result},
     utils::{find_first_simple_identifier, log_node_tree},
 };
 
-///! Note that child is a special key, means child str content instead of modifier
+type Modifier = Option<(String, String)>;
 
-fn compute_line_string_literal_for_str_child(node: &tree_sitter::Node, source: &String) -> String {
+fn compute_line_string_literal_for_str_child(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<String> {
     let mut out = String::new();
 
     for i in 0..node.child_count() {
-        let child = node.child(i).unwrap();
-        let content = child.utf8_text(source.as_bytes()).unwrap();
+        let child = node.child_at(i, source)?;
+        let content = child.text(source)?;
 
         match child.kind() {
             // "\"" => {
@@ -24,7 +27,8 @@ fn compute_line_string_literal_for_str_child(node: &tree_sitter::Node, source: &
                 out.push_str("}}");
             }
             "interpolated_expression" => {
-                out.push_str(content);
+                let value = child.child_at(0, source)?;
+                out.push_str(&expr(&value)?);
             }
             "line_str_text" => {
                 out.push_str(content);
@@ -33,130 +37,128 @@ fn compute_line_string_literal_for_str_child(node: &tree_sitter::Node, source: &
         }
     }
 
-    out
+    Ok(out)
 }
 
-fn compute_text(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_text(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("child".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("child".to_string(), content)));
     };
 
-    if arg_node.kind() == "navigation_expression" || arg_node.kind() == "simple_identifier" {
-        // TODO: 是否正确，这里是为了 for each 正常工作
-        let content = arg_node.utf8_text(source.as_bytes()).unwrap().to_string();
-        let code = format!("{{{{ {} }}}}", content);
-        return Some(("child".to_string(), code));
+    if arg_node.kind() != "value_argument_label" {
+        let code = format!("{{{{ {} }}}}", expr(&arg_node)?);
+        return Ok(Some(("child".to_string(), code)));
     }
 
     // log_node_tree(node, 0, source);
 
-    None
+    Ok(None)
 }
 
-fn compute_fields(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_fields(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("child".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("child".to_string(), content)));
     };
 
-    None
+    Ok(None)
 }
 
-fn compute_button(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_button(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("child".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("child".to_string(), content)));
     };
 
-    None
+    Ok(None)
 }
 
-fn compute_foreach(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
-    if arg_node.kind() == "simple_identifier" {
-        let arg_node_code = arg_node.utf8_text(source.as_bytes()).unwrap();
+fn compute_foreach(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
+    if arg_node.kind() != "value_argument_label" {
+        let arg_node_code = common::expr::attribute(expr(&arg_node)?);
 
         // TODO: 这里没有考虑不使用尾随闭包的情况
         // find foreach lambda
-        let lambda_node = node.parent().unwrap().next_sibling().unwrap();
-        assert_eq!(lambda_node.kind(), "lambda_literal");
+        let lambda_node = node.parent_node(source)?.next_node(source)?;
+        lambda_node.expect_kind("lambda_literal", source)?;
 
         // TODO: 没有考虑使用 $0.xxx 的情况
-        let item_name = lambda_node.child(1).unwrap();
-        assert_eq!(item_name.kind(), "lambda_function_type");
+        let item_name = lambda_node.child_at(1, source)?;
+        item_name.expect_kind("lambda_function_type", source)?;
 
-        let item_name =
-            find_first_simple_identifier(&item_name, source).expect("expect a item name");
+        let item_name = find_first_simple_identifier(&item_name, source)
+            .ok_or_else(|| CompileError::unexpected("expect a item name", &item_name, source))?;
         let v_for_code = format!("{} in {}", item_name, arg_node_code);
 
-        return Some(("v-for".to_string(), v_for_code));
+        return Ok(Some(("v-for".to_string(), v_for_code)));
     };
 
-    None
+    Ok(None)
 }
 
-fn compute_color_picker(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_color_picker(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("child".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("child".to_string(), content)));
     };
 
     // TODO: go to common
     if arg_node.kind() == "value_argument_label" {
-        let value_node = node.child(2).unwrap();
-        let arg_content = arg_node.utf8_text(source.as_bytes()).unwrap().to_string();
-        let value_content = value_node.utf8_text(source.as_bytes()).unwrap().to_string();
+        let value_node = node.child_at(2, source)?;
+        let arg_content = arg_node.text(source)?.to_string();
+        let value_content = value_node.text(source)?.to_string();
 
         if arg_content == "supportsOpacity" {
             let arg_content = format!("v-bind:{}", arg_content);
-            return Some((arg_content, value_content));
+            return Ok(Some((arg_content, value_content)));
         }
     }
 
     // log_node_tree(node, 0, source);
 
-    None
+    Ok(None)
 }
 
-fn compute_date_picker(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_date_picker(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("child".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("child".to_string(), content)));
     };
 
     // log_node_tree(node, 0, source);
 
-    None
+    Ok(None)
 }
 
-fn compute_disclosure_group(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_disclosure_group(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("title".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("title".to_string(), content)));
     };
 
     // log_node_tree(node, 0, source);
 
-    None
+    Ok(None)
 }
 
-fn compute_toggle(node: &tree_sitter::Node, source: &String) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn compute_toggle(node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "line_string_literal" {
-        let content = compute_line_string_literal_for_str_child(&arg_node, source);
-        return Some(("title".to_string(), content));
+        let content = compute_line_string_literal_for_str_child(&arg_node, source, expr)?;
+        return Ok(Some(("title".to_string(), content)));
     };
 
     // log_node_tree(node, 0, source);
 
-    None
+    Ok(None)
 }
 
 lazy_static::lazy_static!(
@@ -168,15 +170,11 @@ lazy_static::lazy_static!(
 );
 
 
-fn common_compute(
-    node: &tree_sitter::Node,
-    source: &String,
-    tag: String,
-) -> Option<(String, String)> {
-    let arg_node = node.child(0).unwrap();
+fn common_compute(node: &tree_sitter::Node, source: &str, tag: String, expr: ExprFn) -> Result<Modifier> {
+    let arg_node = node.child_at(0, source)?;
     if arg_node.kind() == "value_argument_label" {
-        let value_node = node.child(2).unwrap();
-        let arg_content = arg_node.utf8_text(source.as_bytes()).unwrap().to_string();
+        let value_node = node.child_at(2, source)?;
+        let arg_content = arg_node.text(source)?.to_string();
         let mut fixed_ctx = tag.to_string();
 
         // find fixed arg name
@@ -186,64 +184,55 @@ fn common_compute(
             }
         }
 
-        // TODO: using v-bind, 只要不是 string
-        if value_node.kind() == "boolean_literal" {
-            let value_content = value_node.utf8_text(source.as_bytes()).unwrap().to_string();
-
+        let value_content = value_node.text(source)?.to_string();
+        let modifier = if value_node.kind() == "boolean_literal" {
             if value_content == "true" {
-                return Some((arg_content, "".to_string()));
+                Some((arg_content, "".to_string()))
             } else {
-                return None;
+                None
             }
+        } else if value_node.kind() == "lambda_literal" {
+            let arg = format!("v-bind:{}", arg_content);
+            Some((arg, common::expr::attribute(expr(&value_node)?)))
         } else if value_node.kind() == "array_literal" {
-            let code = common::array::array2js_call(&value_node, source, fixed_ctx);
+            let code = common::array::array2js_call(&value_node, source, fixed_ctx)?;
             let arg = format!("v-bind:{}", arg_content);
-            return Some((arg, code));
+            Some((arg, code))
         } else if value_node.kind().ends_with("_literal") {
-            let value_content = value_node.utf8_text(source.as_bytes()).unwrap().to_string();
-            return Some((arg_content, value_content));
-        } else if value_node.kind() == "simple_identifier" || value_node.kind() == "navigation_expression" {
-            let value_content = value_node.utf8_text(source.as_bytes()).unwrap().to_string();
-
-            if value_content.starts_with("$") {
-                let name_without_prefix = value_content.trim_start_matches("$");
-                let arg_content = format!("v-model:{}", arg_content);
-                return Some((arg_content, name_without_prefix.to_string()));
-            }
-
-            let arg_content = format!("v-bind:{}", arg_content);
-            return Some((arg_content, value_content));
+            Some((arg_content, value_content))
+        } else if value_content.starts_with('$') {
+            let name_without_prefix = value_content.trim_start_matches('$');
+            let arg_content = format!("v-model:{}", arg_content);
+            Some((arg_content, name_without_prefix.to_string()))
         } else {
-            let value_content = value_node.utf8_text(source.as_bytes()).unwrap().to_string();
             let arg_content = format!("v-bind:{}", arg_content);
-            return Some((arg_content, value_content));
-        }
+            Some((arg_content, common::expr::attribute(expr(&value_node)?)))
+        };
+
+        return Ok(modifier);
     };
 
-    None
+    Ok(None)
 }
 
-pub fn compute_modifier(
-    tag: String,
-    node: &tree_sitter::Node,
-    source: &String,
-) -> Option<(String, String)> {
+/// `expr` converts the Swift expressions to the JavaScript of the template
+pub fn compute_modifier(tag: String, node: &tree_sitter::Node, source: &str, expr: ExprFn) -> Result<Modifier> {
     let res = match tag.as_str() {
-        "Text" => compute_text(node, source),
-        "Button" => compute_button(node, source),
-        "ForEach" => compute_foreach(node, source),
-        "ColorPicker" => compute_color_picker(node, source),
-        "DatePicker" => compute_date_picker(node, source),
-        "DisclosureGroup" => compute_disclosure_group(node, source),
-        "Toggle" => compute_toggle(node, source),
-        "TextField" => compute_fields(node, source),
-        "SecureField" => compute_fields(node, source),
+        "Text" => compute_text(node, source, expr)?,
+        "Button" => compute_button(node, source, expr)?,
+        "ForEach" => compute_foreach(node, source, expr)?,
+        "ColorPicker" => compute_color_picker(node, source, expr)?,
+        "DatePicker" => compute_date_picker(node, source, expr)?,
+        "DisclosureGroup" => compute_disclosure_group(node, source, expr)?,
+        "Toggle" => compute_toggle(node, source, expr)?,
+        "TextField" => compute_fields(node, source, expr)?,
+        "SecureField" => compute_fields(node, source, expr)?,
         _ => None,
     };
 
     if res.is_none() {
-        common_compute(node, source, tag)
+        common_compute(node, source, tag, expr)
     } else {
-        res
+        Ok(res)
     }
 }
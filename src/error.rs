@@ -0,0 +1,240 @@
+use std::fmt;
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+/// Where in the Swift source an error happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub row: usize,
+    pub col: usize,
+    pub end_row: usize,
+    pub end_col: usize,
+    pub code: String,
+}
+
+impl SourceSpan {
+    pub fn from_node(node: &tree_sitter::Node, source: &str) -> Self {
+        let start = node.start_position();
+        let end = node.end_position();
+        let code = source
+            .get(node.byte_range())
+            .unwrap_or_default()
+            .to_string();
+
+        Self {
+            row: start.row,
+            col: start.column,
+            end_row: end.row,
+            end_col: end.column,
+            code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The output directory is not an absolute path
+    RelativeOutdir,
+    /// The output directory already contains files
+    OutdirNotEmpty,
+    Io,
+    /// The tree-sitter grammar can not be loaded or the source can not be parsed
+    Parse,
+    /// A `PreviewProvider` without `previews`
+    MissingPreviews,
+    /// The syntax tree is not shaped like we expect
+    UnexpectedNode,
+    /// A valid Swift construct we can't translate yet
+    Unsupported,
+    Bundle,
+    /// Rendering `app.js` or `index.html` failed
+    Template,
+    /// Two files declare a type with the same name
+    DuplicateDeclaration,
+    /// A library to put into the output is not vendored
+    Vendor,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::RelativeOutdir => "RELATIVE_OUTDIR",
+            ErrorCode::OutdirNotEmpty => "OUTDIR_NOT_EMPTY",
+            ErrorCode::Io => "IO",
+            ErrorCode::Parse => "PARSE",
+            ErrorCode::MissingPreviews => "MISSING_PREVIEWS",
+            ErrorCode::UnexpectedNode => "UNEXPECTED_NODE",
+            ErrorCode::Unsupported => "UNSUPPORTED",
+            ErrorCode::Bundle => "BUNDLE",
+            ErrorCode::Template => "TEMPLATE",
+            ErrorCode::DuplicateDeclaration => "DUPLICATE_DECLARATION",
+            ErrorCode::Vendor => "VENDOR",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Option<SourceSpan>,
+    /// The Swift file `span` is in
+    pub file: Option<String>,
+}
+
+pub type Result<T> = std::result::Result<T, CompileError>;
+
+impl CompileError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span: None,
+            file: None,
+        }
+    }
+
+    pub fn at(code: ErrorCode, message: impl Into<String>, node: &tree_sitter::Node, source: &str) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span: Some(SourceSpan::from_node(node, source)),
+            file: None,
+        }
+    }
+
+    pub fn in_file(mut self, path: &str) -> Self {
+        if self.file.is_none() {
+            self.file = Some(path.to_string());
+        }
+        self
+    }
+
+    pub fn unexpected(message: impl Into<String>, node: &tree_sitter::Node, source: &str) -> Self {
+        Self::at(ErrorCode::UnexpectedNode, message, node, source)
+    }
+
+    pub fn unsupported(message: impl Into<String>, node: &tree_sitter::Node, source: &str) -> Self {
+        Self::at(ErrorCode::Unsupported, message, node, source)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)?;
+        if let Some(file) = &self.file {
+            write!(f, " {}", file)?;
+        }
+        if let Some(span) = &self.span {
+            write!(f, " (位于 {} 行, {} 列)", span.row, span.col)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<handlebars::RenderError> for CompileError {
+    fn from(err: handlebars::RenderError) -> Self {
+        Self::new(ErrorCode::Template, err.to_string())
+    }
+}
+
+impl From<std::io::Error> for CompileError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(ErrorCode::Io, err.to_string())
+    }
+}
+
+/// A reported problem, as handed to JS
+#[cfg_attr(feature = "napi", napi(object))]
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: String,
+    pub message: String,
+    /// `true` when the build stopped because of it
+    pub fatal: bool,
+    /// The struct being converted, if any
+    pub view: Option<String>,
+    /// The Swift file the problem is in
+    pub file: Option<String>,
+    pub row: Option<u32>,
+    pub column: Option<u32>,
+    pub snippet: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn from_error(err: &CompileError, view: Option<String>, fatal: bool) -> Self {
+        Self {
+            code: err.code.to_string(),
+            message: err.message.clone(),
+            fatal,
+            view,
+            file: err.file.clone(),
+            row: err.span.as_ref().map(|x| x.row as u32),
+            column: err.span.as_ref().map(|x| x.col as u32),
+            snippet: err.span.as_ref().map(|x| x.code.clone()),
+        }
+    }
+}
+
+/// Checked accessors for tree-sitter nodes, so malformed input becomes an error instead of a panic
+pub trait NodeExt<'a> {
+    fn text<'s>(&self, source: &'s str) -> Result<&'s str>;
+    fn child_at(&self, i: usize, source: &str) -> Result<tree_sitter::Node<'a>>;
+    fn parent_node(&self, source: &str) -> Result<tree_sitter::Node<'a>>;
+    fn next_node(&self, source: &str) -> Result<tree_sitter::Node<'a>>;
+    fn expect_kind(&self, kind: &str, source: &str) -> Result<()>;
+}
+
+impl<'a> NodeExt<'a> for tree_sitter::Node<'a> {
+    fn text<'s>(&self, source: &'s str) -> Result<&'s str> {
+        self.utf8_text(source.as_bytes())
+            .map_err(|_| CompileError::unexpected("node text is not valid utf-8", self, source))
+    }
+
+    fn child_at(&self, i: usize, source: &str) -> Result<tree_sitter::Node<'a>> {
+        self.child(i).ok_or_else(|| {
+            CompileError::unexpected(
+                format!("expect child {} of `{}`", i, self.kind()),
+                self,
+                source,
+            )
+        })
+    }
+
+    fn parent_node(&self, source: &str) -> Result<tree_sitter::Node<'a>> {
+        self.parent().ok_or_else(|| {
+            CompileError::unexpected(format!("expect parent of `{}`", self.kind()), self, source)
+        })
+    }
+
+    fn next_node(&self, source: &str) -> Result<tree_sitter::Node<'a>> {
+        self.next_sibling().ok_or_else(|| {
+            CompileError::unexpected(
+                format!("expect next sibling of `{}`", self.kind()),
+                self,
+                source,
+            )
+        })
+    }
+
+    fn expect_kind(&self, kind: &str, source: &str) -> Result<()> {
+        if self.kind() == kind {
+            Ok(())
+        } else {
+            Err(CompileError::unexpected(
+                format!("expect `{}`, found `{}`", kind, self.kind()),
+                self,
+                source,
+            ))
+        }
+    }
+}
@@ -1,155 +1,745 @@
-use paser::State;
+use paser::{GlobalDecl, State, StructInfo};
 
 mod bundler;
 mod common;
 mod component;
+mod error;
+mod mapping;
 mod paser;
+mod project;
+mod sfc;
 mod template;
 mod utils;
+mod vendor;
 mod view;
+mod watch;
 
+#[cfg(feature = "napi")]
 use napi_derive::napi;
 
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
 use colored::Colorize;
 use include_dir::{include_dir, Dir};
+use mapping::MappedCode;
+
+pub use error::{CompileError, Diagnostic, ErrorCode, SourceSpan};
+pub use project::{find_sources, parse_swift, read_sources, SourceFile};
+pub use watch::{BuildReport, WatchSession};
 
 static BUILTIN_VIEWS_DIR: Dir = include_dir!("./builtin-views");
 
 static RUNTIME_DIR: Dir = include_dir!("./runtime");
 static STYLES_DIR: Dir = include_dir!("./styles");
 
-#[allow(unused)]
-#[napi]
-pub fn generate(source: String, outdir: String, verbose: bool) {
-    use tree_sitter::Parser;
-
-    let lang = tree_sitter_swift::language();
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct GenerateResult {
+    /// `false` if any diagnostic was reported
+    pub ok: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Absolute paths of the written files
+    pub files: Vec<String>,
+}
 
-    let mut parser = Parser::new();
-    parser
-        .set_language(&lang)
-        .expect("Error loading Rust grammar");
+impl GenerateResult {
+    fn new(result: error::Result<Vec<String>>, mut diagnostics: Vec<Diagnostic>) -> Self {
+        let files = match result {
+            Ok(files) => files,
+            Err(e) => {
+                diagnostics.push(Diagnostic::from_error(&e, None, true));
+                vec![]
+            }
+        };
 
-    let tree = parser.parse(source.clone(), None).unwrap();
-    let root_node = tree.root_node();
+        Self {
+            ok: diagnostics.is_empty(),
+            diagnostics,
+            files,
+        }
+    }
+}
 
-    // log_node(&root_node, 0);
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct CompileResult {
+    /// `false` if any diagnostic was reported
+    pub ok: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Generated files keyed by file name, see [`Artifacts`]
+    pub files: HashMap<String, String>,
+}
 
-    let mut cursor = root_node.walk();
+impl From<error::Result<Artifacts>> for CompileResult {
+    fn from(result: error::Result<Artifacts>) -> Self {
+        match result {
+            Ok(artifacts) => Self {
+                ok: artifacts.diagnostics.is_empty(),
+                diagnostics: artifacts.diagnostics,
+                files: artifacts.files.into_iter().collect(),
+            },
+            Err(e) => Self {
+                ok: false,
+                diagnostics: vec![Diagnostic::from_error(&e, None, true)],
+                files: HashMap::new(),
+            },
+        }
+    }
+}
 
-    let mut state = State::new(source.clone(), verbose);
-    state.handle_source(&mut cursor);
+/// What [`compile_project`] generates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A single `index.html` with everything bundled into it
+    #[default]
+    Bundle,
+    /// A `.vue` Single-File Component per view, with `App.vue` and `main.js` to mount the previews
+    Sfc,
+    /// The Single-File Components in a Vite project, with `package.json`, `vite.config.js` and `index.html`
+    Project,
+}
 
-    // using ./output
-    let out_dir = std::path::Path::new(outdir.as_str());
-    if out_dir.is_relative() {
-        panic!("Output directory must be an absolute path");
+impl OutputFormat {
+    /// The views are converted into Single-File Components
+    pub fn is_sfc(self) -> bool {
+        matches!(self, OutputFormat::Sfc | OutputFormat::Project)
     }
 
-    // if the dir is not empty, check is a empty dir or not
-    if out_dir.exists() {
-        let dir = std::fs::read_dir(out_dir).unwrap();
-        let mut is_empty = true;
-        for _ in dir {
-            is_empty = false;
-            break;
+    /// Name of the file the view `name` is written to
+    pub fn view_file(self, name: &str) -> String {
+        match self {
+            OutputFormat::Bundle => format!("{}.js", name),
+            OutputFormat::Sfc => format!("{}.vue", name),
+            OutputFormat::Project => format!("{}{}.vue", VITE_COMPONENTS_DIR, name),
         }
+    }
+}
+
+/// How `index.html` of [`OutputFormat::Bundle`] loads Vue, naive-ui and Coloris, always the versions pinned in `vendor.rs`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Libraries {
+    /// From unpkg and jsdelivr
+    #[default]
+    Cdn,
+    /// The vendored copies are put into `index.html`, the page works offline
+    Inline,
+    /// The vendored copies are written to `vendor/` next to `index.html`
+    Copy,
+    /// Vue and naive-ui are imported as ES modules through an import map, Coloris is loaded from jsdelivr
+    ImportMap,
+}
+
+/// Where [`OutputFormat::Project`] puts the components, runtime helpers and models
+const VITE_COMPONENTS_DIR: &str = "src/components/";
+/// Where [`OutputFormat::Project`] puts the stylesheets
+const VITE_STYLES_DIR: &str = "src/styles/";
 
-        if !is_empty {
-            panic!("Output directory is not empty");
+/// Options shared by the napi functions and the `swift2vue` binary
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Log the parsed structs and view trees
+    pub verbose: bool,
+    /// Minify `bundle.js`
+    pub minify: bool,
+    /// Write into an output directory that already contains files
+    pub overwrite: bool,
+    pub format: OutputFormat,
+    /// Emit `<script setup lang="ts">` and `shared.ts`, typed from the Swift declarations.
+    /// Only used by [`OutputFormat::Sfc`] and [`OutputFormat::Project`]
+    pub typescript: bool,
+    /// Only used by [`OutputFormat::Bundle`]
+    pub libraries: Libraries,
+    /// Write `<View>.js.map` next to each component, and map the script of `index.html` back to the Swift source.
+    /// Only used by [`OutputFormat::Bundle`]
+    pub source_maps: bool,
+}
+
+impl CompileOptions {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            ..Default::default()
         }
     }
+}
+
+/// Everything generated from the Swift source, nothing is written to disk
+#[derive(Debug, Default, Clone)]
+pub struct Artifacts {
+    pub format: OutputFormat,
+    /// `<View>.js` per view, `shared.js`, `app.js`, the bundled `bundle.js` and `index.html`,
+    /// with `<View>.js.map` per view if [`CompileOptions::source_maps`] is set.
+    /// For [`OutputFormat::Sfc`], `<View>.vue` per view, `shared.js`, `App.vue`, `main.js`,
+    /// the builtin views, runtime helpers and stylesheets.
+    /// [`OutputFormat::Project`] has the same files in `src`, with `package.json`, `vite.config.js` and `index.html`
+    pub files: BTreeMap<String, String>,
+    /// Problems that didn't stop the compilation, e.g. a view we fall back to an empty component for
+    pub diagnostics: Vec<Diagnostic>,
+}
 
-    // create the dir
-    std::fs::create_dir(out_dir).unwrap();
+#[cfg_attr(feature = "napi", napi)]
+pub fn generate(source: String, outdir: String, verbose: bool) -> GenerateResult {
+    let mut diagnostics = Vec::new();
+    let files = vec![SourceFile::new("main.swift", source)];
+    let options = CompileOptions::new(verbose);
+    let result = generate_to_dir(files, Path::new(&outdir), &options, &mut diagnostics);
+    GenerateResult::new(result, diagnostics)
+}
 
-    let mut view_imports = Vec::new();
-    let mut builtin_view_imports = Vec::new();
-    let mut runtime_imports = Vec::new();
-    let mut error_structs = Vec::new();
+/// Convert several Swift files, or directories of them, into one app
+#[cfg_attr(feature = "napi", napi)]
+pub fn generate_project(inputs: Vec<String>, outdir: String, verbose: bool) -> GenerateResult {
+    let mut diagnostics = Vec::new();
+    let inputs = inputs.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+    let options = CompileOptions::new(verbose);
+    let result = read_sources(&inputs)
+        .and_then(|files| generate_to_dir(files, Path::new(&outdir), &options, &mut diagnostics));
+    GenerateResult::new(result, diagnostics)
+}
+
+/// Same as [`generate`], but returns the generated files instead of writing them
+#[cfg_attr(feature = "napi", napi(js_name = "compile"))]
+pub fn compile_js(source: String, verbose: bool) -> CompileResult {
+    compile(source, verbose).into()
+}
+
+/// Same as [`generate_project`], but takes the sources keyed by path and returns the generated files
+#[cfg_attr(feature = "napi", napi(js_name = "compileProject"))]
+pub fn compile_project_js(sources: HashMap<String, String>, verbose: bool) -> CompileResult {
+    let mut files = sources
+        .into_iter()
+        .map(|(path, source)| SourceFile::new(path, source))
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
 
-    let temp_dir = out_dir.join("temp");
-    std::fs::create_dir(&temp_dir).unwrap();
+    compile_project(files, &CompileOptions::new(verbose)).into()
+}
+
+fn write_file(path: &std::path::Path, contents: impl AsRef<[u8]>, files: &mut Vec<String>) -> error::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+    files.push(path.display().to_string());
+    Ok(())
+}
+
+fn utf8_file_name(path: &std::path::Path) -> error::Result<(String, String)> {
+    let file_name = path.file_name().and_then(|x| x.to_str());
+    let base_name = path.file_stem().and_then(|x| x.to_str());
+
+    match (file_name, base_name) {
+        (Some(file_name), Some(base_name)) => Ok((file_name.to_string(), base_name.to_string())),
+        _ => Err(CompileError::new(
+            ErrorCode::Io,
+            format!("invalid file name: {}", path.display()),
+        )),
+    }
+}
 
-    // add runtime dir's content to view_imports and copy to output dir
-    for file in BUILTIN_VIEWS_DIR.files() {
-        let path = file.path();
-        let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let base_name = path.file_stem().unwrap().to_str().unwrap().to_string();
+/// Builtin views and runtime helpers as `(base name, file name, contents)`
+fn embedded_modules(dir: &Dir) -> error::Result<Vec<(String, String, String)>> {
+    let mut modules = Vec::new();
 
-        builtin_view_imports.push(base_name.clone());
+    for file in dir.files() {
+        let (file_name, base_name) = utf8_file_name(file.path())?;
+        let contents = file.contents_utf8().ok_or_else(|| {
+            CompileError::new(ErrorCode::Io, format!("{} is not valid utf-8", file_name))
+        })?;
 
-        let out_file = format!("{}/{}", temp_dir.display(), file_name);
-        std::fs::write(out_file, file.contents());
+        modules.push((base_name, file_name, contents.to_string()));
     }
 
-    for file in RUNTIME_DIR.files() {
-        let path = file.path();
-        let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let base_name = path.file_stem().unwrap().to_str().unwrap().to_string();
+    Ok(modules)
+}
+
+/// Convert the Swift source into a Vue app held in memory
+pub fn compile(source: String, verbose: bool) -> error::Result<Artifacts> {
+    let files = vec![SourceFile::new("main.swift", source)];
+    compile_project(files, &CompileOptions::new(verbose))
+}
+
+type StructList<'a> = Vec<Rc<RefCell<StructInfo<'a>>>>;
 
-        runtime_imports.push(base_name.clone());
+fn parse_files(files: &[SourceFile]) -> error::Result<Vec<tree_sitter::Tree>> {
+    files
+        .iter()
+        .map(|file| parse_swift(&file.source).map_err(|e| e.in_file(&file.path)))
+        .collect()
+}
 
-        let out_file = format!("{}/{}", temp_dir.display(), file_name);
-        std::fs::write(out_file, file.contents());
+/// Collect the declarations of all files, drop the duplicated ones and resolve the names they declare,
+/// the members of an extension are merged into the type it extends
+fn parse_project<'a>(
+    files: &[SourceFile],
+    trees: &'a [tree_sitter::Tree],
+    options: &CompileOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> error::Result<(StructList<'a>, Vec<GlobalDecl<'a>>, Rc<project::SymbolTable>)> {
+    let mut state = State::new(options.verbose);
+    for (file, tree) in files.iter().zip(trees.iter()) {
+        let mut cursor = tree.root_node().walk();
+        state
+            .handle_file(&file.path, &file.source, &mut cursor)
+            .map_err(|e| e.in_file(&file.path))?;
     }
 
+    let duplicated = |name: &str, node: &tree_sitter::Node, source: &str, path: &str| {
+        let e = CompileError::at(
+            ErrorCode::DuplicateDeclaration,
+            format!("{} is already declared", name),
+            node,
+            source,
+        )
+        .in_file(path);
+        Diagnostic::from_error(&e, Some(name.to_string()), false)
+    };
+
+    // the first declaration wins
+    let mut declared = HashSet::new();
+    let mut struct_list = Vec::new();
+    let mut extensions = Vec::new();
     for st in state.struct_list {
+        let name = st.borrow().name.clone();
+        // an extension doesn't declare a type of its own
+        if st.borrow().kind == paser::DeclKind::Extension {
+            extensions.push(st);
+            continue;
+        }
+        if declared.insert(name.clone()) {
+            struct_list.push(st);
+            continue;
+        }
+
+        let st = st.borrow();
+        diagnostics.push(duplicated(&name, &st.node, &st.source, &st.path));
+    }
+
+    for ext in extensions {
+        let ext = ext.borrow();
+        let target = struct_list.iter().find(|x| x.borrow().name == ext.name);
+
+        match target {
+            // the members refer to the source of the file they are declared in, it's kept with the extension
+            Some(target) => {
+                let mut target = target.borrow_mut();
+                let index = target.extensions.len();
+                for (name, member) in &ext.members {
+                    if !target.members.contains_key(name) {
+                        target.members.insert(name.clone(), member.clone());
+                        target.extension_members.insert(name.clone(), index);
+                    }
+                }
+                target.sub.extend(ext.sub.iter().cloned());
+                target.extensions.push(paser::Extension {
+                    node: ext.node,
+                    path: ext.path.clone(),
+                    source: ext.source.clone(),
+                });
+            }
+            None => {
+                let message = format!("{} 不是项目中声明的类型，忽略它的 extension", ext.name);
+                let e = CompileError::at(ErrorCode::Unsupported, message, &ext.node, &ext.source).in_file(&ext.path);
+                diagnostics.push(Diagnostic::from_error(&e, Some(ext.name.clone()), false));
+            }
+        }
+    }
+
+    let mut globals = Vec::new();
+    for global in state.globals {
+        if declared.insert(global.name.clone()) {
+            globals.push(global);
+        } else {
+            diagnostics.push(duplicated(&global.name, &global.node, &global.source, &global.path));
+        }
+    }
+
+    let symbols = Rc::new(project::SymbolTable::from_declarations(&struct_list, &globals));
+    Ok((struct_list, globals, symbols))
+}
+
+/// The view tree of every view and preview, in declaration order
+pub fn view_trees(files: Vec<SourceFile>, options: &CompileOptions) -> error::Result<Vec<(String, String)>> {
+    let trees = parse_files(&files)?;
+    let (struct_list, _, symbols) = parse_project(&files, &trees, options, &mut Vec::new())?;
+    let mut trees = Vec::new();
+
+    for st in struct_list {
+        let mut st = st.borrow().clone();
+        if st.is_preview() {
+            let previews = st.members.shift_remove("previews");
+            if let Some(previews) = previews {
+                st.members.insert("body".to_string(), previews);
+            }
+            st.inheritance = Some("View".to_string());
+        } else if !st.is_view() {
+            continue;
+        }
+
+        let name = st.name.clone();
+        let path = st.path.clone();
+        let mut view = view::ViewParser::from_struct(st, symbols.clone());
+        view.generate_template().map_err(|e| e.in_file(&path))?;
+        trees.push((name, view.view_tree_text()));
+    }
+
+    Ok(trees)
+}
+
+/// Convert all files into a single Vue app held in memory, views can use each other across files
+pub fn compile_project(files: Vec<SourceFile>, options: &CompileOptions) -> error::Result<Artifacts> {
+    let mut artifacts = Artifacts::default();
+    let trees = parse_files(&files)?;
+    let (struct_list, globals, symbols) = parse_project(&files, &trees, options, &mut artifacts.diagnostics)?;
+
+    generate_modules(struct_list, globals, symbols, options, &HashMap::new(), &mut artifacts)?;
+    if options.format == OutputFormat::Bundle {
+        bundle_artifacts(&mut artifacts, options)?;
+    }
+
+    Ok(artifacts)
+}
+
+/// Names of the builtin views and runtime helpers, the modules are read by [`bundle_artifacts`]
+fn embedded_names() -> error::Result<(Vec<String>, Vec<String>)> {
+    let builtin_views = embedded_modules(&BUILTIN_VIEWS_DIR)?
+        .into_iter()
+        .map(|(base_name, _, _)| base_name)
+        .collect();
+    let runtimes = embedded_modules(&RUNTIME_DIR)?
+        .into_iter()
+        .map(|(base_name, _, _)| base_name)
+        .collect();
+
+    Ok((builtin_views, runtimes))
+}
+
+/// Generate every module except `bundle.js` and `index.html`,
+/// the code of a view found in `reuse` is taken as is instead of converting the view again
+fn generate_modules(
+    struct_list: StructList,
+    globals: Vec<GlobalDecl>,
+    symbols: Rc<project::SymbolTable>,
+    options: &CompileOptions,
+    reuse: &HashMap<String, String>,
+    artifacts: &mut Artifacts,
+) -> error::Result<()> {
+    let verbose = options.verbose;
+    let sfc = options.format.is_sfc();
+    let typescript = sfc && options.typescript;
+    let extension = if sfc { "vue" } else { "js" };
+    let (builtin_view_imports, runtime_imports) = embedded_names()?;
+    artifacts.format = options.format;
+
+    let mut view_imports = Vec::new();
+    let mut error_structs = Vec::new();
+
+    let mut shared_code = runtime_imports
+        .iter()
+        .map(|name| format!("import {} from './{}.js'\n", name, name))
+        .collect::<String>();
+    let mut previews = Vec::new();
+
+    for st in struct_list {
         let st = st.borrow().clone();
-        println!("{:?}", st);
+        if verbose {
+            println!("{:?}", st);
+        }
 
         let st_name = st.name.clone();
+        let st_path = st.path.clone();
+        let st_source = st.source.clone();
+
+        if st.is_view() {
+            if let Some(cmp_code) = reuse.get(&st_name) {
+                artifacts.files.insert(format!("{}.{}", st_name, extension), cmp_code.clone());
+                view_imports.push(st_name);
+                continue;
+            }
 
-        if st.inheritance == Some("View".to_string()) {
-            let mut view = view::ViewParser::from_struct(st, source.clone());
-            let cmp_code =
-                view.generate_component_code(runtime_imports.clone(), builtin_view_imports.clone());
+            let mut view = view::ViewParser::from_struct(st, symbols.clone());
+            let cmp_code = if sfc {
+                view.generate_sfc_code(runtime_imports.clone(), builtin_view_imports.clone(), typescript)
+                    .map(MappedCode::from)
+            } else {
+                view.generate_component_code(runtime_imports.clone(), builtin_view_imports.clone())
+                    .and_then(|x| bundler::check_module(&format!("{}.js", st_name), &x.code).map(|_| x))
+            };
 
-            match cmp_code {
+            let cmp_code = match cmp_code {
                 Ok(cmp_code) => {
-                    let file_name = format!("{}/{}.js", temp_dir.display(), st_name);
-                    std::fs::write(file_name, cmp_code).unwrap();
-
-                    view_imports.push(st_name);
+                    if verbose {
+                        println!("{}", view.view_tree_text());
+                    }
+                    cmp_code
                 }
                 Err(e) => {
+                    let e = e.in_file(&st_path);
                     error_structs.push(st_name.clone());
 
-                    println!("{}", e.message.red());
-                    println!("{}", format!("位于 {} 行, {} 列:", e.row, e.col).red());
-                    println!("{}", e.node_code.red());
+                    if verbose {
+                        println!("{}", e.message.red());
+                        if let Some(span) = &e.span {
+                            println!("{}", format!("{} 位于 {} 行, {} 列:", st_path, span.row, span.col).red());
+                            println!("{}", span.code.red());
+                        }
+                    }
+                    artifacts
+                        .diagnostics
+                        .push(Diagnostic::from_error(&e, Some(st_name.clone()), false));
 
-                    let cmp_code = view.generate_empty_component();
-                    let file_name = format!("{}/{}.js", temp_dir.display(), st_name);
-                    std::fs::write(file_name, cmp_code).unwrap();
-
-                    view_imports.push(st_name);
+                    let code = if sfc {
+                        view.generate_empty_sfc(typescript)
+                    } else {
+                        view.generate_empty_component()
+                    };
+                    MappedCode::from(code)
                 }
+            };
+
+            let file_name = format!("{}.{}", st_name, extension);
+            let mut code = cmp_code.code.clone();
+            if options.source_maps && !sfc {
+                let map = cmp_code.to_source_map(&file_name, &st_path, &st_source);
+                code.push_str(&format!("\n//# sourceMappingURL={}.map", file_name));
+                artifacts
+                    .files
+                    .insert(format!("{}.map", file_name), mapping::source_map_json(&map));
+            }
+            artifacts.files.insert(file_name, code);
+
+            view_imports.push(st_name);
+        } else if st.is_preview() {
+            previews.push(st);
+        } else if symbols.models.contains(&st_name) {
+            let code = if typescript {
+                let known = symbols.type_names();
+                common::model::date_model2ts_fn(&st.node, &st.source, &st.extensions, &known, &symbols, true)
+            } else {
+                common::model::date_model2js_fn(&st.node, &st.source, &st.extensions, &symbols).map(|x| format!("export {}", x))
+            };
+            match code {
+                Ok(code) => shared_code.push_str(&format!("\n{}", code)),
+                Err(e) => artifacts.diagnostics.push(Diagnostic::from_error(
+                    &e.in_file(&st_path),
+                    Some(st_name.clone()),
+                    false,
+                )),
+            }
+        } else if symbols.stores.contains(&st_name) {
+            match common::store::store2js(&st.node, &st.source, &st.extensions, &symbols, typescript) {
+                Ok(code) => shared_code.push_str(&format!("\n{}", code)),
+                Err(e) => artifacts.diagnostics.push(Diagnostic::from_error(
+                    &e.in_file(&st_path),
+                    Some(st_name.clone()),
+                    false,
+                )),
+            }
+        } else if symbols.enums.contains(&st_name) {
+            match common::enums::enum2js(&st.node, &st.source, &st.extensions, &symbols, typescript, true) {
+                Ok(code) => shared_code.push_str(&format!("\n{}", code)),
+                // the other enums and models still work
+                Err(e) => artifacts.diagnostics.push(Diagnostic::from_error(
+                    &e.in_file(&st_path),
+                    Some(st_name.clone()),
+                    false,
+                )),
+            }
+        }
+    }
+
+    // after the classes of the enums they may refer to
+    for global in globals {
+        match common::global::global2js(&global.node, &global.source, &symbols, typescript) {
+            Ok(code) => shared_code.push_str(&format!("\n{}", code)),
+            Err(e) => artifacts.diagnostics.push(Diagnostic::from_error(
+                &e.in_file(&global.path),
+                Some(global.name.clone()),
+                false,
+            )),
+        }
+    }
+
+    if previews.is_empty() {
+        return Err(CompileError::new(
+            ErrorCode::MissingPreviews,
+            "No PreviewProvider found, there is nothing to render",
+        ));
+    }
+
+    // every preview of the project is rendered, one after another
+    let mut templates = Vec::new();
+    let mut preview_components = Vec::new();
+
+    for st in previews {
+        let mut transformed = st.clone();
+        transformed.inheritance = Some("View".to_string());
+
+        let previews = transformed.members.shift_remove("previews").ok_or_else(|| {
+            CompileError::at(
+                ErrorCode::MissingPreviews,
+                format!("No previews found in {}", st.name),
+                &st.node,
+                &st.source,
+            )
+            .in_file(&st.path)
+        })?;
+        transformed.members.insert("body".to_string(), previews);
+
+        let mut view = view::ViewParser::from_struct(transformed, symbols.clone());
+        let template = if sfc {
+            view.generate_indented_template(2)
+        } else {
+            view.generate_template()
+        };
+        templates.push(template.map_err(|e| e.in_file(&st.path))?);
+
+        for name in view.used_components(&builtin_view_imports) {
+            if !preview_components.contains(&name) {
+                preview_components.push(name);
             }
-        } else if st.inheritance == Some("PreviewProvider".to_string()) {
-            let mut transformed = st.clone();
-            transformed.inheritance = Some("View".to_string());
+        }
+    }
+
+    // e.g. the store of `.environmentObject(Store())`
+    let preview_shared = symbols
+        .shared_names()
+        .into_iter()
+        .filter(|name| templates.iter().any(|x| sfc::uses_identifier(x, name)))
+        .collect::<Vec<_>>();
+
+    if sfc {
+        preview_components.sort();
+        let shared_module = if typescript { "shared" } else { "shared.js" };
+        add_sfc_support_files(
+            artifacts,
+            preview_components,
+            preview_shared,
+            shared_module,
+            templates.join("\n"),
+            options.format,
+        )?;
+    } else {
+        let mut views_imports = view_imports.clone();
+        views_imports.extend(builtin_view_imports.clone());
+
+        let app_js =
+            template::generate_app_js(runtime_imports.clone(), views_imports, preview_shared, templates.join(""))?;
+        artifacts.files.insert("app.js".to_string(), app_js);
+    }
+
+    if !symbols.shared_names().is_empty() {
+        let shared = if typescript { "shared.ts" } else { "shared.js" };
+        // the stores call `reactive()`, it's a global of the bundle
+        if let Some(import) = sfc.then(|| sfc::vue_import(&shared_code)).flatten() {
+            shared_code = format!("{}\n{}", import, shared_code);
+        }
+        artifacts.files.insert(shared.to_string(), shared_code);
+    }
+
+    if options.format == OutputFormat::Project {
+        add_vite_project_files(artifacts, typescript)?;
+    }
+
+    if verbose && !error_structs.is_empty() {
+        println!("{}: {:?}", "Error structs".red(), error_structs);
+    }
+
+    Ok(())
+}
+
+/// `App.vue` rendering the previews, `main.js` mounting it, and the builtin views, runtime and styles they need
+fn add_sfc_support_files(
+    artifacts: &mut Artifacts,
+    views: Vec<String>,
+    shared: Vec<String>,
+    shared_module: &str,
+    index_template: String,
+    format: OutputFormat,
+) -> error::Result<()> {
+    let (builtin_views, _) = embedded_names()?;
+
+    for (base_name, _, contents) in embedded_modules(&BUILTIN_VIEWS_DIR)? {
+        let code = sfc::builtin_view_to_sfc(&base_name, &contents, &builtin_views)?;
+        artifacts.files.insert(format!("{}.vue", base_name), code);
+    }
+
+    // the Vue APIs are globals of the bundle
+    for (_, file_name, contents) in embedded_modules(&RUNTIME_DIR)? {
+        let contents = match sfc::vue_import(&contents) {
+            Some(import) => format!("{}\n{}", import, contents),
+            None => contents,
+        };
+        artifacts.files.insert(file_name, contents);
+    }
 
-            let previews = transformed
-                .members
-                .get("previews")
-                .expect("No previews found");
-            transformed
-                .members
-                .insert("body".to_string(), previews.clone());
-            transformed.members.remove("previews");
+    let mut styles = Vec::new();
+    for file in STYLES_DIR.files() {
+        if let (Some(code), Ok((file_name, _))) = (file.contents_utf8(), utf8_file_name(file.path())) {
+            artifacts.files.insert(file_name.clone(), code.to_string());
+            styles.push(file_name);
+        }
+    }
+
+    // App.vue is in `src`, next to the `components` and `styles` dirs
+    let project = format == OutputFormat::Project;
+    let (components_dir, styles_dir) = if project { ("components/", "styles/") } else { ("", "") };
+
+    let app_vue = template::generate_app_vue(views, shared, shared_module, index_template, styles, components_dir, styles_dir)?;
+    artifacts.files.insert("App.vue".to_string(), app_vue);
+    artifacts
+        .files
+        .insert("main.js".to_string(), template::generate_main_js(project)?);
+
+    Ok(())
+}
+
+/// Move the Single-File Component output into `src` of a Vite project and add the files to run it with npm
+fn add_vite_project_files(artifacts: &mut Artifacts, typescript: bool) -> error::Result<()> {
+    let files = std::mem::take(&mut artifacts.files);
 
-            let mut view = view::ViewParser::from_struct(transformed, source.clone());
-            let template = view.generate_template();
+    for (file_name, contents) in files {
+        let path = match file_name.as_str() {
+            "App.vue" | "main.js" => format!("src/{}", file_name),
+            _ if file_name.ends_with(".css") => format!("{}{}", VITE_STYLES_DIR, file_name),
+            _ => format!("{}{}", VITE_COMPONENTS_DIR, file_name),
+        };
+        artifacts.files.insert(path, contents);
+    }
+
+    let config = if typescript { "vite.config.ts" } else { "vite.config.js" };
+    artifacts.files.insert(config.to_string(), template::VITE_CONFIG.to_string());
+    artifacts
+        .files
+        .insert("package.json".to_string(), template::generate_package_json(typescript)?);
+    artifacts
+        .files
+        .insert("index.html".to_string(), template::VITE_INDEX_HTML.to_string());
+
+    if typescript {
+        artifacts
+            .files
+            .insert("tsconfig.json".to_string(), template::TSCONFIG_JSON.to_string());
+        artifacts.files.insert(
+            "src/env.d.ts".to_string(),
+            "/// <reference types=\"vite/client\" />\n".to_string(),
+        );
+    }
 
-            let mut views_imports = view_imports.clone();
-            views_imports.extend(builtin_view_imports.clone());
+    Ok(())
+}
 
-            let app_js =
-                template::generate_app_js(runtime_imports.clone(), views_imports, template);
+/// Bundle the generated modules with the builtin views and runtime into `bundle.js` and `index.html`
+fn bundle_artifacts(artifacts: &mut Artifacts, options: &CompileOptions) -> error::Result<()> {
+    // the bundler reads from here, it also contains the builtin views and runtime
+    let mut modules = bundler::VirtualFs::new();
 
-            let file_name = format!("{}/{}.js", temp_dir.display(), "app");
-            std::fs::write(file_name, app_js).unwrap();
+    for dir in [&BUILTIN_VIEWS_DIR, &RUNTIME_DIR] {
+        for (_, file_name, contents) in embedded_modules(dir)? {
+            modules.insert(file_name, contents);
         }
     }
 
@@ -157,25 +747,1495 @@ pub fn generate(source: String, outdir: String, verbose: bool) {
     let mut styles: Vec<String> = Vec::new();
 
     for file in STYLES_DIR.files() {
-        let code = file.contents_utf8().unwrap().to_string();
-        styles.push(code);
+        if let Some(code) = file.contents_utf8() {
+            styles.push(code.to_string());
+        }
     }
 
     // do bundle
-    let app_js_path = temp_dir.join("app.js");
-    let code = bundler::bundle(app_js_path.as_path(), true, false);
+    let mut input_maps = bundler::InputMaps::new();
+    for (name, code) in artifacts.files.iter().filter(|(name, _)| !is_bundle_output(name)) {
+        match name.strip_suffix(".map") {
+            Some(module) if options.source_maps => {
+                let map = sourcemap::SourceMap::from_slice(code.as_bytes()).map_err(|e| {
+                    CompileError::new(ErrorCode::Bundle, format!("invalid source map {}: {}", name, e))
+                })?;
+                input_maps.insert(module.to_string(), map);
+            }
+            Some(_) => {}
+            None => {
+                modules.insert(name.clone(), code.clone());
+            }
+        }
+    }
+    let bundled = bundler::bundle(
+        "app.js",
+        modules,
+        true,
+        options.minify,
+        options.source_maps.then_some(&input_maps),
+    )?;
+    let code = bundled.code;
+
+    let mut libraries = template::PageLibraries::default();
+    for library in vendor::LIBRARIES.iter() {
+        let script = library.kind == vendor::LibraryKind::Script;
+
+        match options.libraries {
+            Libraries::Cdn => {}
+            Libraries::ImportMap if library.module.is_some() => continue,
+            Libraries::ImportMap => {}
+            Libraries::Inline => {
+                let contents = library.contents()?.to_string();
+                if script {
+                    libraries.inline_scripts.push(contents);
+                } else {
+                    styles.push(contents);
+                }
+                continue;
+            }
+            Libraries::Copy => {
+                let file_name = format!("{}{}", VENDOR_OUTPUT_DIR, library.file_name);
+                artifacts
+                    .files
+                    .insert(file_name.clone(), library.contents()?.to_string());
+                if script {
+                    libraries.scripts.push(file_name);
+                } else {
+                    libraries.stylesheets.push(file_name);
+                }
+                continue;
+            }
+        }
 
-    // clear temp dir
-    // std::fs::remove_dir_all(temp_dir).unwrap();
+        if script {
+            libraries.scripts.push(library.url.to_string());
+        } else {
+            libraries.stylesheets.push(library.url.to_string());
+        }
+    }
+    if options.libraries == Libraries::ImportMap {
+        libraries.import_map = Some(vendor::import_map());
+    }
 
     // generate html
-    let index_html = template::generate_template_html(styles, code);
-    println!("index.html generated ({}kb)", index_html.len() / 1024);
+    let mut index_html = template::generate_template_html(styles, libraries, code.clone())?;
+    if let Some(map) = bundled.map {
+        index_html = inline_source_map(index_html, &code, map)?;
+    }
+    if options.verbose {
+        println!("bundle.js generated ({}kb)", code.len() / 1024);
+        println!("index.html generated ({}kb)", index_html.len() / 1024);
+    }
 
-    let file_name = format!("{}/index.html", out_dir.display());
-    std::fs::write(file_name, index_html).unwrap();
+    artifacts.files.insert("bundle.js".to_string(), code);
+    artifacts.files.insert("index.html".to_string(), index_html);
 
-    if !error_structs.is_empty() {
-        println!("{}: {:?}", "Error structs".red(), error_structs);
+    Ok(())
+}
+
+/// Point the module script of `index.html` to `map`, the source map of the `code` bundled into it.
+/// The map is shifted to where the code starts in the script
+fn inline_source_map(index_html: String, code: &str, map: sourcemap::SourceMap) -> error::Result<String> {
+    const SCRIPT_TAG: &str = "<script type=\"module\">";
+
+    let script_start = index_html.find(SCRIPT_TAG).map(|x| x + SCRIPT_TAG.len());
+    let code_start = script_start.and_then(|start| index_html[start..].find(code).map(|x| start + x));
+    let (Some(script_start), Some(code_start)) = (script_start, code_start) else {
+        return Err(CompileError::new(ErrorCode::Bundle, "bundled code not found in index.html"));
+    };
+    let (line, column) = mapping::utf16_position(&index_html[script_start..], code_start - script_start);
+
+    let mut builder = sourcemap::SourceMapBuilder::new(None);
+    for token in map.tokens() {
+        let dst_col = if token.get_dst_line() == 0 {
+            token.get_dst_col() + column
+        } else {
+            token.get_dst_col()
+        };
+        let added = builder.add(
+            token.get_dst_line() + line,
+            dst_col,
+            token.get_src_line(),
+            token.get_src_col(),
+            token.get_source(),
+            None,
+            false,
+        );
+        if builder.get_source_contents(added.src_id).is_none() {
+            builder.set_source_contents(added.src_id, map.get_source_contents(token.get_src_id()));
+        }
+    }
+
+    let url = builder
+        .into_sourcemap()
+        .to_data_url()
+        .map_err(|e| CompileError::new(ErrorCode::Bundle, format!("failed to write the source map: {}", e)))?;
+
+    let code_end = code_start + code.len();
+    Ok(format!(
+        "{}\n//# sourceMappingURL={}{}",
+        &index_html[..code_end],
+        url,
+        &index_html[code_end..]
+    ))
+}
+
+/// Where [`Libraries::Copy`] puts the vendored libraries, next to `index.html`
+const VENDOR_OUTPUT_DIR: &str = "vendor/";
+
+fn is_bundle_output(file_name: &str) -> bool {
+    matches!(file_name, "bundle.js" | "index.html") || file_name.starts_with(VENDOR_OUTPUT_DIR)
+}
+
+/// Compile the files and write the app to `out_dir`, the modules it was bundled from are kept in `temp`
+pub fn generate_to_dir(
+    files: Vec<SourceFile>,
+    out_dir: &Path,
+    options: &CompileOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> error::Result<Vec<String>> {
+    check_outdir(out_dir, options)?;
+
+    let artifacts = compile_project(files, options)?;
+    let files = write_artifacts(out_dir, &artifacts, None)?;
+    diagnostics.extend(artifacts.diagnostics);
+
+    Ok(files)
+}
+
+/// `out_dir` has to be absolute, and empty unless [`CompileOptions::overwrite`] is set
+pub fn check_outdir(out_dir: &Path, options: &CompileOptions) -> error::Result<()> {
+    if out_dir.is_relative() {
+        return Err(CompileError::new(
+            ErrorCode::RelativeOutdir,
+            format!("Output directory must be an absolute path: {}", out_dir.display()),
+        ));
+    }
+
+    // if the dir is not empty, check is a empty dir or not
+    if !options.overwrite && out_dir.exists() && std::fs::read_dir(out_dir)?.next().is_some() {
+        return Err(CompileError::new(
+            ErrorCode::OutdirNotEmpty,
+            format!("Output directory is not empty: {}", out_dir.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Write `artifacts` to `out_dir`, `index.html` at the root and the modules it was bundled from in `temp`.
+/// Single-File Components are all written to the root, the Vite project keeps its own layout.
+/// With the `previous` artifacts of the same directory, only the files that changed are written
+pub fn write_artifacts(out_dir: &Path, artifacts: &Artifacts, previous: Option<&Artifacts>) -> error::Result<Vec<String>> {
+    let mut files = Vec::new();
+    let sfc = artifacts.format.is_sfc();
+
+    // keep the modules we bundled from in temp dir, it's handy for debugging
+    let temp_dir = if sfc { out_dir.to_path_buf() } else { out_dir.join("temp") };
+    let path_of = |file_name: &str| match file_name {
+        "index.html" => out_dir.join(file_name),
+        _ if file_name.starts_with(VENDOR_OUTPUT_DIR) => out_dir.join(file_name),
+        _ => temp_dir.join(file_name),
+    };
+
+    match previous {
+        Some(previous) => {
+            for file_name in previous.files.keys() {
+                if artifacts.files.contains_key(file_name) || file_name == "bundle.js" {
+                    continue;
+                }
+                match std::fs::remove_file(path_of(file_name)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+        None => {
+            std::fs::create_dir_all(&temp_dir)?;
+
+            // the Single-File Component output has its own copies
+            let dirs = if sfc { vec![] } else { vec![&BUILTIN_VIEWS_DIR, &RUNTIME_DIR] };
+            for dir in dirs {
+                for (_, file_name, contents) in embedded_modules(dir)? {
+                    write_file(&temp_dir.join(file_name), contents, &mut files)?;
+                }
+            }
+        }
+    }
+
+    for (file_name, contents) in &artifacts.files {
+        let unchanged = previous
+            .and_then(|previous| previous.files.get(file_name))
+            .map(|x| x == contents)
+            .unwrap_or(false);
+        if file_name == "bundle.js" || unchanged {
+            continue;
+        }
+
+        write_file(&path_of(file_name), contents, &mut files)?;
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    const SOURCE: &str = r#"
+    struct ContentView: View {
+        var body: some View {
+            Text("Hello")
+        }
+    }
+    "#;
+
+    /// The previews of [`SOURCE`]
+    const PREVIEW: &str = "struct Previews: PreviewProvider { static var previews: some View { ContentView() } }";
+
+    /// The artifacts of a project with the single file `source`
+    fn compile_one(source: &str, options: &super::CompileOptions) -> super::Artifacts {
+        super::compile_project(vec![super::SourceFile::new("main.swift", source)], options).unwrap()
+    }
+
+    /// A Vue project in TypeScript
+    fn sfc_typescript() -> super::CompileOptions {
+        super::CompileOptions {
+            format: super::OutputFormat::Sfc,
+            typescript: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compile_in_memory() {
+        let source = format!("{}\n{}", SOURCE, PREVIEW);
+
+        let artifacts = super::compile(source, false).unwrap();
+        let names = artifacts.files.keys().map(|x| x.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["ContentView.js", "app.js", "bundle.js", "index.html"]);
+        assert!(artifacts.files["index.html"].contains("<Text>Hello</Text>"));
+        assert!(artifacts.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_compile_libraries() {
+        let source = format!("{}\n{}", SOURCE, PREVIEW);
+        let compile = |libraries| {
+            let options = super::CompileOptions {
+                libraries,
+                ..Default::default()
+            };
+            compile_one(&source, &options)
+        };
+
+        let html = &compile(super::Libraries::Cdn).files["index.html"];
+        assert!(html.contains("<script src=\"https://unpkg.com/vue@3.4.27/dist/vue.global.prod.js\"></script>"));
+        assert!(!html.contains("@latest"));
+
+        let html = &compile(super::Libraries::ImportMap).files["index.html"];
+        assert!(html.contains("<script type=\"importmap\">"));
+        assert!(html.contains("import * as naive from \"naive-ui\";"));
+        assert!(!html.contains("unpkg.com/vue"));
+        assert!(html.contains("coloris.min.js"));
+    }
+
+    #[test]
+    fn test_compile_source_maps() {
+        let source = format!("{}\n{}", SOURCE, PREVIEW);
+        let compile = |source_maps| {
+            let options = super::CompileOptions {
+                source_maps,
+                ..Default::default()
+            };
+            compile_one(&source, &options)
+        };
+
+        let artifacts = compile(false);
+        assert!(!artifacts.files.contains_key("ContentView.js.map"));
+        assert!(!artifacts.files["index.html"].contains("sourceMappingURL"));
+
+        let artifacts = compile(true);
+        assert!(artifacts.files["ContentView.js"].ends_with("\n//# sourceMappingURL=ContentView.js.map"));
+        let map = sourcemap::SourceMap::from_slice(artifacts.files["ContentView.js.map"].as_bytes()).unwrap();
+        assert_eq!(map.get_source(0), Some("main.swift"));
+
+        let html = &artifacts.files["index.html"];
+        let url = html.split("//# sourceMappingURL=").nth(1).unwrap().lines().next().unwrap();
+        // the decoder doesn't accept the charset the encoder writes
+        let url = url.replace(";charset=utf-8", "");
+        let sourcemap::DecodedMap::Regular(map) = sourcemap::decode_data_url(&url).unwrap() else {
+            panic!("not a regular source map");
+        };
+        // `Text("Hello")` is on the 4th line
+        assert!(map
+            .tokens()
+            .any(|x| x.get_source() == Some("main.swift") && x.get_src_line() == 3));
+    }
+
+    #[test]
+    fn test_generate_relative_outdir() {
+        let result = super::generate(SOURCE.to_string(), "output".to_string(), false);
+        assert!(!result.ok);
+        assert_eq!(result.diagnostics[0].code, "RELATIVE_OUTDIR");
+        assert!(result.diagnostics[0].fatal);
+    }
+
+    #[test]
+    fn test_generate_missing_previews() {
+        let source = format!("{}\nstruct Previews: PreviewProvider {{}}", SOURCE);
+        let outdir = std::env::temp_dir().join("swift2vue-test-missing-previews");
+        let _ = std::fs::remove_dir_all(&outdir);
+
+        let result = super::generate(source, outdir.display().to_string(), false);
+        assert!(!result.ok);
+        assert_eq!(result.diagnostics[0].code, "MISSING_PREVIEWS");
+        assert_eq!(
+            result.diagnostics[0].snippet.as_deref(),
+            Some("struct Previews: PreviewProvider {}")
+        );
+
+        let _ = std::fs::remove_dir_all(&outdir);
+    }
+
+    #[test]
+    fn test_compile_project_across_files() {
+        let content_view = r#"
+        struct ContentView: View {
+            @State var movies = [Movie(title: "A")]
+
+            var body: some View {
+                MovieRow()
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                ContentView()
+            }
+        }
+        "#;
+        let movie_row = r#"
+        struct Movie {
+            let title: String
+        }
+
+        struct MovieRow: View {
+            var body: some View {
+                Text("Row")
+            }
+        }
+        "#;
+
+        let files = vec![
+            super::SourceFile::new("ContentView.swift", content_view),
+            super::SourceFile::new("MovieRow.swift", movie_row),
+        ];
+        let artifacts = super::compile_project(files, &super::CompileOptions::default()).unwrap();
+        assert!(artifacts.diagnostics.is_empty());
+
+        let code = &artifacts.files["ContentView.js"];
+        assert!(code.contains("import MovieRow from './MovieRow.js'"));
+        assert!(code.contains("import { Movie } from './shared.js'"));
+        assert!(artifacts.files["shared.js"].contains("export function Movie(arg)"));
+    }
+
+    #[test]
+    fn test_compile_project_top_level_declarations() {
+        let source = r#"
+        let movies = [Movie(title: "A")]
+
+        func describe(_ movie: Movie) -> String {
+            return movie.title
+        }
+
+        struct Movie {
+            let title: String
+        }
+
+        struct MovieList: View {
+            @State var summary = ""
+
+            var body: some View {
+                ForEach(movies) { movie in
+                    Text(movie.title)
+                }
+            }
+        }
+
+        extension MovieList {
+            func update() {
+                summary = describe(movies[0])
+            }
+        }
+
+        extension Color {
+            static let brand = Color.red
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                MovieList()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        // `Color` isn't declared in the project
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].code, "UNSUPPORTED");
+
+        let shared = &artifacts.files["shared.js"];
+        assert!(shared.contains("export const movies = [Movie({title: \"A\"})];"));
+        assert!(shared.contains("export function describe(movie) {\n    return movie.title;\n}"));
+
+        let code = &artifacts.files["MovieList.js"];
+        assert!(code.contains("import { Movie, describe, movies } from './shared.js'"));
+        // the members of the extension belong to the view
+        assert!(code.contains("summary.value = describe(movies[0]);"));
+        assert!(code.contains("return { summary, update,"));
+    }
+
+    #[test]
+    fn test_compile_project_extensions_across_files() {
+        let extensions = r#"
+        extension Counter {
+            func reset() {
+                count = 0
+            }
+        }
+
+        extension Flavor {
+            var isSweet: Bool { self == .chocolate }
+        }
+
+        extension Movie {
+            var summary: String { title.uppercased() }
+        }
+
+        extension CounterView {
+            var header: some View {
+                Text("Count")
+            }
+
+            func tap() {
+                counter.reset()
+            }
+        }
+        "#;
+        let source = r#"
+        class Counter: ObservableObject {
+            @Published var count = 0
+        }
+
+        enum Flavor {
+            case chocolate
+            case vanilla
+        }
+
+        struct Movie {
+            let title: String
+        }
+
+        struct CounterView: View {
+            @StateObject var counter = Counter()
+
+            var body: some View {
+                VStack {
+                    header
+                    Text("\(counter.count)").onTapGesture(tap)
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                CounterView()
+            }
+        }
+        "#;
+
+        let files = vec![
+            super::SourceFile::new("Extensions.swift", extensions),
+            super::SourceFile::new("CounterView.swift", source),
+        ];
+        let artifacts = super::compile_project(files, &super::CompileOptions::default()).unwrap();
+        assert!(artifacts.diagnostics.is_empty());
+
+        let shared = &artifacts.files["shared.js"];
+        assert!(shared.contains("    function reset() {\n        self.count = 0;\n    }"));
+        assert!(shared.contains("    get isSweet() {\n        return this === Flavor.chocolate;\n    }"));
+        assert!(shared.contains("        get summary() {\n            return this.title.toUpperCase();\n        },"));
+
+        let code = &artifacts.files["CounterView.js"];
+        assert!(code.contains("<VStack><Text>Count</Text>"));
+        assert!(code.contains("const tap = () => {\n            counter.reset();\n        };"));
+    }
+
+    #[test]
+    fn test_compile_view_props() {
+        let source = r#"
+        struct Movie {
+            let title: String
+        }
+
+        struct MovieRow: View {
+            let movie: Movie
+            var rank = 1
+            private var hovered = false
+            static var step = 5
+
+            var body: some View {
+                Text(movie.title)
+            }
+        }
+
+        struct MovieList: View {
+            var body: some View {
+                MovieRow(movie: Movie(title: "A"), rank: 2)
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                MovieList()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let row = &artifacts.files["MovieRow.js"];
+        assert!(row.contains("    props: { movie: null, rank: { default: 1 } },\n    setup(props) {"));
+        assert!(row.contains("return { hovered,"));
+        // a static member is not set by the parent
+        assert!(!row.contains("step: "));
+        // the arguments are bound, they are not child views
+        let list = &artifacts.files["MovieList.js"];
+        assert!(list.contains(r#"<MovieRow v-bind:movie="Movie({title: 'A'})" v-bind:rank="2"></MovieRow>"#));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["MovieRow.vue"].contains(
+            "withDefaults(defineProps<{\n  movie: Movie\n  rank?: number\n}>(), {\n  rank: 1,\n})"
+        ));
+    }
+
+    #[test]
+    fn test_compile_view_state_without_value() {
+        let source = r#"
+        struct CountView: View {
+            @State var count: Int
+
+            var body: some View {
+                Text("\(count)")
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                CountView(count: 1)
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        // it's not a prop, the view owns its state
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].code, "UNSUPPORTED");
+        assert_eq!(artifacts.diagnostics[0].view.as_deref(), Some("CountView"));
+    }
+
+    #[test]
+    fn test_compile_view_bindings() {
+        let source = r#"
+        struct Switch: View {
+            @Binding var isOn: Bool
+            var title: String
+
+            func flip() {
+                isOn.toggle()
+            }
+
+            var body: some View {
+                VStack {
+                    Toggle(title, isOn: $isOn)
+                    Button("Flip") { isOn.toggle() }
+                }
+            }
+        }
+
+        struct Settings: View {
+            @State var wifi = false
+            var enabled: Bool {
+                get { wifi }
+                set { wifi = newValue }
+            }
+
+            func reset() {
+                wifi.toggle()
+                enabled.toggle()
+            }
+
+            var body: some View {
+                VStack {
+                    Button("Wi-Fi") { wifi.toggle() }
+                    Switch(isOn: $wifi, title: "Wi-Fi")
+                    Switch(isOn: .constant(true), title: "Bluetooth")
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Settings()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let switch = &artifacts.files["Switch.js"];
+        assert!(switch.contains("    emits: ['update:isOn'],\n    setup(props, { emit }) {"));
+        assert!(switch.contains("emit('update:isOn', !props.isOn);"));
+        // a binding can't be the target of `v-model`, the update is passed on
+        assert!(switch.contains(r#"v-bind:isOn="isOn" v-on:update:isOn="$emit('update:isOn', $event)""#));
+        let settings = &artifacts.files["Settings.js"];
+        assert!(settings.contains(r#"<Switch v-model:isOn="wifi" title="Wi-Fi"></Switch>"#));
+        assert!(settings.contains(r#"<Switch v-bind:isOn="true" title="Bluetooth"></Switch>"#));
+        assert!(switch.contains(r#"<Button v-bind:action="() => $emit('update:isOn', !isOn)">Flip</Button>"#));
+        assert!(settings.contains(r#"<Button v-bind:action="() => wifi = !wifi">Wi-Fi</Button>"#));
+        // the state and the computed setter are assigned
+        assert!(settings.contains("            wifi.value = !wifi.value;\n            enabled.value = !enabled.value;\n"));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["Switch.vue"]
+            .contains("const emit = defineEmits<{\n  'update:isOn': [value: boolean]\n}>()"));
+    }
+
+    #[test]
+    fn test_compile_observable_object() {
+        let source = r#"
+        class Counter: ObservableObject {
+            @Published var count = 0
+
+            func increment(by step: Int) {
+                count += step
+            }
+        }
+
+        struct CounterLabel: View {
+            @ObservedObject var counter: Counter
+
+            var body: some View {
+                Text("\(counter.count)")
+            }
+        }
+
+        struct CounterView: View {
+            @StateObject var counter = Counter()
+
+            func tap() {
+                counter.increment(by: 2)
+            }
+
+            var body: some View {
+                CounterLabel(counter: counter).onTapGesture(tap)
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                CounterView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let shared = &artifacts.files["shared.js"];
+        assert!(shared.contains("export function Counter() {\n    const self = reactive({\n        count: 0,\n    });"));
+        assert!(shared.contains("    function increment(step) {\n        self.count += step;\n    }"));
+        let view = &artifacts.files["CounterView.js"];
+        assert!(view.contains("const counter = Counter();"));
+        assert!(view.contains("counter.increment(2)"));
+        assert!(view.contains(r#"v-bind:counter="counter""#));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["shared.ts"].starts_with("import { reactive } from 'vue'\n"));
+        assert!(artifacts.files["CounterLabel.vue"].contains("defineProps<{\n  counter: Counter\n}>()"));
+    }
+
+    #[test]
+    fn test_compile_environment_object() {
+        let source = r#"
+        class Session: ObservableObject {
+            @Published var user = "guest"
+        }
+
+        struct Greeting: View {
+            @EnvironmentObject var session: Session
+
+            var body: some View {
+                Text(session.user)
+            }
+        }
+
+        struct ContentView: View {
+            @StateObject var session = Session()
+
+            var body: some View {
+                Greeting().environmentObject(session)
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Greeting().environmentObject(Session())
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        assert!(artifacts.files["Greeting.js"].contains("const session = inject('Session');"));
+        assert!(artifacts.files["ContentView.js"].contains(
+            r#"<SwiftCommon v-bind:environmentObjects="{ Session: session }"><template #child><Greeting></Greeting>"#
+        ));
+        // the previews create the objects they provide
+        let app = &artifacts.files["app.js"];
+        assert!(app.contains("import { Session } from './shared.js'"));
+        assert!(app.contains(r#"<SwiftCommon v-bind:environmentObjects="{ Session: Session() }">"#));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["Greeting.vue"].contains("const session = inject('Session') as Session;"));
+        assert!(artifacts.files["App.vue"].contains("import { Session } from './shared'"));
+    }
+
+    #[test]
+    fn test_compile_environment_values() {
+        let source = r#"
+        struct Badge: View {
+            @Environment(\.colorScheme) var colorScheme
+            @Environment(\.dismiss) var dismiss
+            @State var dark = false
+
+            func close() {
+                dark = colorScheme == .dark
+                dismiss()
+            }
+
+            var body: some View {
+                Text("Close").onTapGesture(close)
+            }
+        }
+
+        struct ContentView: View {
+            @State var mode: EditMode = .inactive
+
+            var body: some View {
+                Badge()
+                    .environment(\.locale, Locale(identifier: "zh_CN"))
+                    .environment(\.editMode, $mode)
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                ContentView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let badge = &artifacts.files["Badge.js"];
+        assert!(badge.contains("const colorScheme = SwiftEnvironment.read('colorScheme');"));
+        assert!(badge.contains("dark.value = colorScheme.value === 'dark';"));
+        assert!(badge.contains("dismiss.value();"));
+        let content = &artifacts.files["ContentView.js"];
+        assert!(content.contains("const mode = ref('inactive');"));
+        assert!(content.contains(
+            r#"<SwiftCommon v-bind:environmentValues="{ locale: 'zh-CN', editMode: mode }"><template #child><Badge></Badge>"#
+        ));
+
+        let options = super::CompileOptions {
+            format: super::OutputFormat::Sfc,
+            ..Default::default()
+        };
+        let artifacts = compile_one(source, &options);
+        // the runtime helpers of the Single-File Components import the Vue APIs
+        assert!(artifacts.files["SwiftEnvironment.js"].starts_with("import { ref, provide, inject } from 'vue'\n"));
+    }
+
+    #[test]
+    fn test_compile_computed_properties() {
+        let source = r#"
+        struct Cart: View {
+            @State var price = 2
+            @State var count = 1
+            var total: Int { price * count }
+            var label: String {
+                let unit = "items"
+                return unit
+            }
+            var doubled: Int {
+                get { count * 2 }
+                set { count = newValue / 2 }
+            }
+
+            func reset() {
+                doubled = 2
+            }
+
+            var body: some View {
+                Text("\(total)").onTapGesture(reset)
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Cart()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let cart = &artifacts.files["Cart.js"];
+        assert!(cart.contains("const total = computed(() => price.value * count.value);"));
+        assert!(cart.contains("        const label = computed(() => {\n"));
+        assert!(cart.contains("            return unit;\n        });"));
+        assert!(cart.contains(
+            "const doubled = computed({\n            get: () => count.value * 2,\n            set: (newValue) => {\n                count.value = newValue / 2;\n            },\n        });"
+        ));
+        assert!(cart.contains("doubled.value = 2;"));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["Cart.vue"].contains("const total = computed<number>(() => price.value * count.value);"));
+    }
+
+    #[test]
+    fn test_compile_view_builders() {
+        let source = r#"
+        struct Movies: View {
+            @State var movies = ["Up", "Coco"]
+
+            var header: some View {
+                Text("Movies")
+            }
+
+            @ViewBuilder
+            func row(_ movie: String, at index: Int) -> some View {
+                Text(movie)
+                Text("\(index)")
+            }
+
+            var body: some View {
+                VStack {
+                    header.padding()
+                    ForEach(movies) { movie in
+                        row(movie, at: 0)
+                    }
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Movies()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let movies = &artifacts.files["Movies.js"];
+        assert!(movies.contains(
+            r#"<VStack><Text padding>Movies</Text><ForEach v-for="movie in movies"><template v-for="index in [0]"><Text>{{ movie }}</Text><Text>{{index}}</Text></template></ForEach></VStack>"#
+        ));
+        assert!(!movies.contains("const header"));
+        assert!(!movies.contains("const row"));
+    }
+
+    #[test]
+    fn test_compile_view_functions() {
+        let source = r#"
+        struct Counter: View {
+            @State var count = 0
+            @State var history = [0]
+
+            func add(_ step: Int, times: Int = 1) {
+                guard step > 0 else {
+                    return
+                }
+                for _ in 0..<times {
+                    if count > 10 {
+                        self.count = 0
+                    } else {
+                        count += step
+                    }
+                }
+                let last = count
+                history.append(contentsOf: [last])
+            }
+
+            func reset(count: Int) {
+                self.count = count
+            }
+
+            func countdown() {
+                if count > 0 {
+                    var count = 10
+                    count += 1
+                    let history = [count]
+                    print(history)
+                }
+                count = 0
+                history.append(count)
+            }
+
+            var body: some View {
+                Text("\(count)").onTapGesture { add(1) }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Counter()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let counter = &artifacts.files["Counter.js"];
+        assert!(counter.contains("const history = ref([0]);"));
+        assert!(counter.contains(
+            r#"        const add = (step, times = 1) => {
+            if (!(step > 0)) {
+                return;
+            }
+            for (let _ = 0; _ < times; _++) {
+                if (count.value > 10) {
+                    count.value = 0;
+                } else {
+                    count.value += step;
+                }
+            }
+            const last = count.value;
+            history.value.push(...[last]);
+        };"#
+        ));
+        // the parameter hides the state
+        assert!(counter.contains("count.value = count;"));
+        // so do the locals until the end of their block
+        assert!(counter.contains(
+            r#"            if (count.value > 0) {
+                let count = 10;
+                count += 1;
+                const history = [count];
+                console.log(history);
+            }
+            count.value = 0;
+            history.value.push(count.value);"#
+        ));
+
+        let artifacts = compile_one(source, &sfc_typescript());
+        assert!(artifacts.files["Counter.vue"].contains("const add = (step: number, times: number = 1) => {"));
+
+        // a property without `@State` can't be changed
+        let source = source.replace("@State var history = [0]", "var step = 1\n            @State var history = [0]");
+        let source = source.replace("self.count = count\n", "self.count = count\n                step = 3\n");
+        let artifacts = compile_one(&source, &Default::default());
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].code, "UNSUPPORTED");
+        assert_eq!(artifacts.diagnostics[0].row, Some(23));
+    }
+
+    #[test]
+    fn test_compile_template_expressions() {
+        let source = r#"
+        struct Palette: View {
+            @State var colors = ["red", "green"]
+            @State var selection: [String] = []
+            @State var value = 0
+            @State var flag = false
+            @State var item: Item? = nil
+
+            var body: some View {
+                VStack {
+                    Text(colors[value].description)
+                    Text("\(selection.count) selected")
+                    Text(flag ? "a" : "b")
+                    Text(item?.name ?? "-")
+                    ForEach(0..<colors.count) { index in
+                        Text(colors[index].uppercased())
+                    }
+                    Toggle("Flag", isOn: $flag)
+                        .disabled(!selection.isEmpty && item != nil)
+                }
+            }
+        }
+
+        struct Item {
+            var name: String = ""
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Palette()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let palette = &artifacts.files["Palette.js"];
+        assert!(palette.contains("const item = ref(undefined);"));
+        assert!(palette.contains(
+            r#"<VStack><Text>{{ String(colors[value]) }}</Text><Text>{{selection.length}} selected</Text><Text>{{ flag ? "a" : "b" }}</Text><Text>{{ item?.name ?? "-" }}</Text><ForEach v-for="index in Array.from({ length: colors.length }, (_, i) => i)"><Text>{{ colors[index].toUpperCase() }}</Text></ForEach><Toggle title="Flag" v-model:isOn="flag" v-bind:disabled="!(selection.length === 0) && item != null"></Toggle></VStack>"#
+        ));
+    }
+
+    #[test]
+    fn test_compile_model_members() {
+        let source = r#"
+        struct Stats {
+            var count: Int
+            var first: String
+        }
+
+        class Counter: ObservableObject {
+            @Published var count = 0
+        }
+
+        struct StatsView: View {
+            @StateObject var counter = Counter()
+            @State var stats = Stats(count: 1, first: "a")
+            @State var names: [String] = []
+
+            var body: some View {
+                VStack {
+                    Text("\(counter.count) \(stats.first)")
+                    Text(names.last ?? "")
+                    Text("\(names.count)")
+                }
+            }
+
+            func reset() {
+                stats.count = names.isEmpty ? 0 : counter.count
+                if names.count > 3 {
+                    names = []
+                }
+                if stats != Stats(count: 0, first: "") {
+                    names = []
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                StatsView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let view = &artifacts.files["StatsView.js"];
+        assert!(view.contains("<Text>{{counter.count}} {{stats.first}}</Text>"));
+        assert!(view.contains(r#"<Text>{{ names.at(-1) ?? "" }}</Text>"#));
+        assert!(view.contains("stats.value.count = (names.value.length === 0) ? 0 : counter.count;"));
+        // `count` of `Stats` isn't the one of an array of strings
+        assert!(view.contains("<Text>{{names.length}}</Text>"));
+        assert!(view.contains("if (names.value.length > 3) {"));
+        // a struct is compared by its properties
+        assert!(view.contains(r#"if (!SwiftEquatable.equals(stats.value, Stats({count: 0, first: ""}))) {"#));
+    }
+
+    #[test]
+    fn test_compile_view_enums() {
+        let source = r#"
+        enum LoadState {
+            case idle
+            case loaded([String])
+            case failed(String)
+        }
+
+        struct LoadView: View {
+            @State var state: LoadState = .idle
+            @State var items: [String] = []
+
+            var body: some View {
+                Button("Load") {
+                    load(404)
+                }
+                .disabled(state == .idle)
+            }
+
+            func load(_ code: Int) {
+                if state != .idle {
+                    state = .loaded(items)
+                } else {
+                    state = .failed("Error \(code)")
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                LoadView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let view = &artifacts.files["LoadView.js"];
+        // `.idle` is a case of the type of the other side
+        assert!(view.contains(r#"v-bind:disabled="state === LoadState.idle""#));
+        assert!(view.contains("if (state.value !== LoadState.idle) {"));
+        // the associated values are expressions like the others
+        assert!(view.contains("state.value = LoadState.loaded(items.value);"));
+        assert!(view.contains(r#"state.value = LoadState.failed("Error " + (code));"#));
+    }
+
+    #[test]
+    fn test_compile_closures() {
+        let source = r##"
+        struct Counter: View {
+            @State var count = 0
+            @State var editing = false
+            @Binding var shown: Bool
+            let format: (Int) -> String = { "#\($0)" }
+            let reset = { (value: Int) in
+                count = value
+                print(value)
+            }
+
+            var body: some View {
+                VStack {
+                    Button("Add") {
+                        count += 1
+                    }
+                    Button(action: { shown = false }) {
+                        Text("Close")
+                    }
+                    Button {
+                        reset(0)
+                    } label: {
+                        Text("Reset")
+                    }
+                    TextField("Name", text: .constant(""), onEditingChanged: { editing in
+                        self.editing = editing
+                    })
+                    Text(format(count))
+                        .onTapGesture {
+                            count -= 1
+                        }
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Counter(shown: .constant(true))
+            }
+        }
+        "##;
+
+        let artifacts = compile_one(source, &Default::default());
+        let counter = &artifacts.files["Counter.js"];
+        assert!(counter.contains(r##"const format = ($0) => "#" + ($0);"##));
+        assert!(counter.contains(
+            r#"        const reset = (value) => {
+            count.value = value;
+            console.log(value);
+        };"#
+        ));
+        assert!(counter.contains(r#"<Button v-bind:action="() => { count += 1; }">Add</Button>"#));
+        assert!(counter.contains(r#"<Button v-bind:action="() => { $emit('update:shown', false); }"><Text>Close</Text></Button>"#));
+        assert!(counter.contains(r#"<Button v-bind:action="() => reset(0)"><Text>Reset</Text></Button>"#));
+        assert!(counter.contains(r#"v-bind:onEditingChanged="(editing_) => { editing = editing_; }""#));
+        assert!(counter.contains(r#"<Text @click="() => { count -= 1; }">{{ format(count) }}</Text>"#));
+    }
+
+    #[test]
+    fn test_compile_optionals() {
+        let source = r#"
+        struct Profile {
+            var name: String
+            var nickname: String?
+        }
+
+        struct ProfileView: View {
+            @State var profile: Profile?
+            @State var count = 0
+
+            var body: some View {
+                if let current = profile, let nickname = current.nickname {
+                    Text(nickname)
+                } else if count > 0 {
+                    Text("\(count)")
+                } else {
+                    Button("Load") {
+                        load()
+                    }
+                }
+            }
+
+            func load() {
+                guard let length = profile?.name.count else {
+                    return
+                }
+                if let profile {
+                    print(profile.name + profile.nickname!)
+                }
+                self.count = length
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                ProfileView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let view = &artifacts.files["ProfileView.js"];
+        assert!(view.contains("const profile = ref(undefined);"));
+        assert!(view.contains(
+            r#"<Group><template v-if="profile != null && (profile).nickname != null"><template v-for="current in [profile]"><template v-for="nickname in [current.nickname]"><Text>{{ nickname }}</Text></template></template></template><template v-else-if="count > 0"><Text>{{count}}</Text></template><template v-else>"#
+        ));
+        assert!(view.contains(
+            r#"            const length = profile.value?.name.length;
+            if (length == null) {
+                return;
+            }
+            if (profile.value != null) {
+                console.log(profile.value.name + SwiftOptional.unwrap(profile.value.nickname));
+            }"#
+        ));
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let source = r#"
+        enum LoadError: Error {
+            case notFound
+            case invalid(String)
+        }
+
+        func parse(_ text: String) throws -> Int {
+            guard let value = Int(text) else {
+                throw LoadError.invalid(text)
+            }
+            return value
+        }
+
+        struct LoadView: View {
+            @State var text = ""
+            @State var message = ""
+
+            var body: some View {
+                Button("Load") {
+                    load()
+                }
+            }
+
+            func load() throws {
+                do {
+                    let value = try parse(text)
+                    message = "\(value)"
+                } catch LoadError.invalid(let text) {
+                    message = text
+                }
+            }
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                LoadView()
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        let shared = &artifacts.files["shared.js"];
+        assert!(shared.contains("export class LoadError extends SwiftError {"));
+        assert!(shared.contains("        super(caseName);\n        this.case = caseName;"));
+        // `Int(text)` is nil for a text that isn't a number
+        assert!(shared.contains(
+            "    const value = SwiftOptional.int(text);\n    if (value == null) {\n        throw LoadError.invalid(text);\n    }"
+        ));
+        let view = &artifacts.files["LoadView.js"];
+        assert!(view.contains(
+            r#"            try {
+                const value = parse(text.value);
+                message.value = "" + (value);
+            } catch (error) {
+                if (SwiftError.is(error, "LoadError") && SwiftEnum.matches(error, "invalid")) {
+                    const [text] = SwiftEnum.values(error);
+                    message.value = text;
+                } else {
+                    throw error;
+                }
+            }"#
+        ));
+    }
+
+    #[test]
+    fn test_compile_model_diagnostics() {
+        let source = r#"
+        struct Profile {
+            var name: String
+            var label: String {
+                do {
+                    return try format(name)
+                } catch let error where error is LoadError {
+                    return name
+                }
+            }
+        }
+
+        struct Other {
+            var title = "A"
+        }
+
+        struct Previews: PreviewProvider {
+            static var previews: some View {
+                Text("A")
+            }
+        }
+        "#;
+
+        let artifacts = compile_one(source, &Default::default());
+        // the other models still work
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].code, "UNSUPPORTED");
+        assert_eq!(artifacts.diagnostics[0].view.as_deref(), Some("Profile"));
+        assert!(artifacts.files["shared.js"].contains("export function Other(arg) {"));
+    }
+
+    #[test]
+    fn test_compile_unsupported_view() {
+        let source = include_str!("../fixtures/Controls/DatePicker.swift");
+
+        let artifacts = compile_one(source, &Default::default());
+        // `DateComponents(year: 2021, ...)` of `RangeDatePicker`, the other views still work
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].code, "UNSUPPORTED");
+        assert_eq!(artifacts.diagnostics[0].view.as_deref(), Some("RangeDatePicker"));
+        assert!(artifacts.files["BasicDatePicker.js"].contains("const date = ref(SwiftDate());"));
+        assert!(artifacts.files.contains_key("bundle.js"));
+
+        // `return a ... b` of dates
+        let source = format!(
+            r#"
+        struct ContentView: View {{
+            let range: ClosedRange<Date> = {{
+                return Date()
+                    ...
+                    Date()
+            }}()
+
+            var body: some View {{
+                Text("A")
+            }}
+        }}
+        {}"#,
+            PREVIEW
+        );
+        let artifacts = compile_one(&source, &Default::default());
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].message, "暂不支持返回区间");
+        assert!(artifacts.files.contains_key("bundle.js"));
+
+        // the code we still get wrong falls back to the empty component too
+        let error = super::bundler::check_module("ColorsView.js", "const colors = [.orange, .red];").unwrap_err();
+        assert_eq!(error.code, super::error::ErrorCode::Bundle);
+        assert!(error.message.starts_with("生成的 ColorsView.js 第 1 行"));
+    }
+
+    #[test]
+    fn test_compile_project_duplicate_declaration() {
+        let files = vec![
+            super::SourceFile::new("a.swift", SOURCE),
+            super::SourceFile::new("b.swift", SOURCE),
+            super::SourceFile::new(
+                "Previews.swift",
+                "struct Previews: PreviewProvider { static var previews: some View { ContentView() } }",
+            ),
+        ];
+        let result = super::compile_project(files, &super::CompileOptions::default());
+        let diagnostics = match result {
+            Ok(artifacts) => artifacts.diagnostics,
+            Err(_) => panic!("duplicate declarations should not stop the build"),
+        };
+        assert_eq!(diagnostics[0].code, "DUPLICATE_DECLARATION");
+        assert_eq!(diagnostics[0].file.as_deref(), Some("b.swift"));
+    }
+
+    #[test]
+    fn test_compile_sfc() {
+        let source = format!("{}\n{}", SOURCE, PREVIEW);
+        let options = super::CompileOptions {
+            format: super::OutputFormat::Sfc,
+            ..Default::default()
+        };
+
+        let artifacts = compile_one(&source, &options);
+        assert_eq!(
+            artifacts.files["ContentView.vue"],
+            "<script setup>\nimport Text from './Text.vue'\n</script>\n\n<template>\n  <Text>Hello</Text>\n</template>\n\n<style scoped>\n</style>\n"
+        );
+        assert!(artifacts.files["App.vue"].contains("import ContentView from './ContentView.vue'"));
+        assert!(artifacts.files.contains_key("Text.vue"));
+        assert!(!artifacts.files.contains_key("bundle.js"));
+    }
+
+    #[test]
+    fn test_compile_sfc_typescript() {
+        let source = r#"
+struct Movie {
+    var title: String
+}
+
+struct MovieRow: View {
+    let movie: Movie
+    @State var count: Int = 0
+
+    var body: some View {
+        Text("Row")
+    }
+}
+
+struct Previews: PreviewProvider {
+    static var previews: some View {
+        MovieRow()
+    }
+}
+"#;
+        let artifacts = compile_one(source, &sfc_typescript());
+        let code = &artifacts.files["MovieRow.vue"];
+        assert!(code.starts_with("<script setup lang=\"ts\">"));
+        assert!(code.contains("import { ref } from 'vue'"));
+        assert!(code.contains("defineProps<{\n  movie: Movie\n}>()"));
+        assert!(code.contains("const count = ref<number>(0);"));
+
+        let shared = &artifacts.files["shared.ts"];
+        assert!(shared.contains("export interface Movie {\n    title: string;\n}"));
+        assert!(shared.contains("export function Movie(arg: { title: string }): Movie {"));
+        assert!(!artifacts.files.contains_key("shared.js"));
+    }
+
+    #[test]
+    fn test_compile_vite_project() {
+        let source = format!("{}\n{}", SOURCE, PREVIEW);
+        let options = super::CompileOptions {
+            format: super::OutputFormat::Project,
+            ..Default::default()
+        };
+
+        let artifacts = compile_one(&source, &options);
+        for name in ["package.json", "vite.config.js", "index.html", "src/main.js", "src/App.vue"] {
+            assert!(artifacts.files.contains_key(name), "{} is missing", name);
+        }
+        assert!(artifacts.files.contains_key("src/components/ContentView.vue"));
+        assert!(artifacts.files.contains_key("src/components/Text.vue"));
+        assert!(artifacts.files.contains_key("src/components/UUID.js"));
+        assert!(artifacts.files.contains_key("src/styles/common.css"));
+        assert!(artifacts.files["src/App.vue"].contains("import ContentView from './components/ContentView.vue'"));
+        assert!(artifacts.files["src/App.vue"].contains("@import './styles/common.css';"));
+        assert!(artifacts.files["index.html"].contains("src=\"/src/main.js\""));
     }
 }
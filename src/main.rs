@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, SystemTime},
+};
+
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+
+use swift2vue::{CompileError, CompileOptions, Diagnostic, Libraries, OutputFormat, SourceFile, WatchSession};
+
+/// Convert SwiftUI views into a Vue app
+#[derive(Parser)]
+#[command(name = "swift2vue", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compile the sources and write the app to the output directory
+    Build {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Build once, then rebuild whenever a source changes, only the views touched by an edit are converted again
+    Watch {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        output: Output,
+        /// How often the sources are checked for changes, in milliseconds
+        #[arg(long, default_value_t = 300)]
+        interval: u64,
+    },
+    /// Compile the sources and report problems without writing anything
+    Check {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Print the tree-sitter syntax tree of every file
+    DumpAst {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Print the view tree of every view and preview
+    DumpViewTree {
+        #[command(flatten)]
+        input: Input,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// Swift files, directories are searched recursively for `.swift` files
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Log the parsed structs and view trees
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Args)]
+struct Output {
+    /// Output directory, relative paths are resolved against the working directory
+    #[arg(short, long)]
+    outdir: PathBuf,
+    /// Minify the bundled script
+    #[arg(long)]
+    minify: bool,
+    /// Write into the output directory even if it already contains files
+    #[arg(long)]
+    overwrite: bool,
+    #[arg(long, value_enum, default_value_t = Format::Bundle)]
+    format: Format,
+    /// Emit TypeScript components and models, needs `--format sfc` or `--format project`
+    #[arg(long)]
+    typescript: bool,
+    /// How `index.html` loads Vue, naive-ui and Coloris, only for `--format bundle`
+    #[arg(long, value_enum, default_value_t = Library::Cdn)]
+    libraries: Library,
+    /// Map the generated JavaScript back to the Swift source, only for `--format bundle`
+    #[arg(long)]
+    source_map: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// A single `index.html` with everything bundled into it
+    Bundle,
+    /// A `.vue` Single-File Component per view
+    Sfc,
+    /// A Vite project with the Single-File Components, run `npm install && npm run dev` in it
+    Project,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Library {
+    /// Pinned versions from unpkg and jsdelivr
+    Cdn,
+    /// Put the vendored copies into `index.html`, it works offline
+    #[cfg(feature = "vendor")]
+    Inline,
+    /// Copy the vendored copies to `vendor/` next to `index.html`
+    #[cfg(feature = "vendor")]
+    Copy,
+    /// Import Vue and naive-ui through an import map of pinned versions
+    ImportMap,
+}
+
+impl Output {
+    fn options(&self, input: &Input) -> CompileOptions {
+        CompileOptions {
+            verbose: input.verbose,
+            minify: self.minify,
+            overwrite: self.overwrite,
+            format: match self.format {
+                Format::Bundle => OutputFormat::Bundle,
+                Format::Sfc => OutputFormat::Sfc,
+                Format::Project => OutputFormat::Project,
+            },
+            typescript: self.typescript,
+            libraries: match self.libraries {
+                Library::Cdn => Libraries::Cdn,
+                #[cfg(feature = "vendor")]
+                Library::Inline => Libraries::Inline,
+                #[cfg(feature = "vendor")]
+                Library::Copy => Libraries::Copy,
+                Library::ImportMap => Libraries::ImportMap,
+            },
+            source_maps: self.source_map,
+        }
+    }
+
+    /// The file to point the user to once it's built
+    fn entry(&self) -> &'static str {
+        match self.format {
+            Format::Bundle => "index.html",
+            Format::Sfc => "App.vue",
+            Format::Project => "package.json",
+        }
+    }
+
+    fn outdir(&self) -> io::Result<PathBuf> {
+        Ok(std::env::current_dir()?.join(&self.outdir))
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if let Command::Build { output, .. } | Command::Watch { output, .. } = &cli.command {
+        if output.typescript && matches!(output.format, Format::Bundle) {
+            Cli::command()
+                .error(ErrorKind::ArgumentConflict, "--typescript needs --format sfc or --format project")
+                .exit();
+        }
+        if output.source_map && !matches!(output.format, Format::Bundle) {
+            Cli::command()
+                .error(ErrorKind::ArgumentConflict, "--source-map needs --format bundle")
+                .exit();
+        }
+    }
+
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            print_diagnostic(&Diagnostic::from_error(&e, None, true));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, CompileError> {
+    match command {
+        Command::Build { input, output } => {
+            let files = swift2vue::read_sources(&input.inputs)?;
+            let options = output.options(&input);
+            let outdir = output.outdir()?;
+
+            let mut diagnostics = Vec::new();
+            let written = swift2vue::generate_to_dir(files, &outdir, &options, &mut diagnostics)?;
+            diagnostics.iter().for_each(print_diagnostic);
+
+            if input.verbose {
+                written.iter().for_each(|file| println!("{}", file));
+            }
+            println!("{} {}", "Generated".green(), outdir.join(output.entry()).display());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Watch {
+            input,
+            output,
+            interval,
+        } => watch(&input, &output, Duration::from_millis(interval)),
+        Command::Check { input } => {
+            let files = swift2vue::read_sources(&input.inputs)?;
+            let count = files.len();
+            let artifacts = swift2vue::compile_project(files, &CompileOptions::new(input.verbose))?;
+            artifacts.diagnostics.iter().for_each(print_diagnostic);
+
+            if artifacts.diagnostics.is_empty() {
+                println!("{} {} file(s)", "Checked".green(), count);
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::FAILURE)
+            }
+        }
+        Command::DumpAst { input } => {
+            let mut out = io::stdout().lock();
+            for file in swift2vue::read_sources(&input.inputs)? {
+                let tree = swift2vue::parse_swift(&file.source)
+                    .map_err(|e| e.in_file(&file.path))?;
+
+                let written = writeln!(out, "{}", file.path.bold())
+                    .and_then(|_| write_node(&mut out, &tree.root_node(), &file.source, 1));
+                match written {
+                    // e.g. piped into `head`
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+                    result => result?,
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::DumpViewTree { input } => {
+            let files = swift2vue::read_sources(&input.inputs)?;
+            for (name, tree) in swift2vue::view_trees(files, &CompileOptions::new(input.verbose))? {
+                println!("{}", name.bold());
+                println!("{}", tree);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn watch(input: &Input, output: &Output, interval: Duration) -> Result<ExitCode, CompileError> {
+    let options = output.options(input);
+    let outdir = output.outdir()?;
+    swift2vue::check_outdir(&outdir, &options)?;
+
+    let mut modified = HashMap::new();
+    let mut files = Vec::new();
+    for path in swift2vue::find_sources(&input.inputs)? {
+        modified.insert(path.clone(), modified_time(&path));
+        files.push(SourceFile::read(&path)?);
+    }
+
+    let mut session = WatchSession::new(files, options)?;
+    swift2vue::write_artifacts(&outdir, session.artifacts(), None)?;
+    session.artifacts().diagnostics.iter().for_each(print_diagnostic);
+    println!("{} {}", "Generated".green(), outdir.join(output.entry()).display());
+    println!("Watching {} file(s), press Ctrl-C to stop", modified.len());
+
+    loop {
+        std::thread::sleep(interval);
+
+        // a file may be half written or just moved away, try again next time
+        let Ok(paths) = swift2vue::find_sources(&input.inputs) else {
+            continue;
+        };
+
+        let mut changed = Vec::new();
+        for path in &paths {
+            let time = modified_time(path);
+            if modified.get(path) != Some(&time) {
+                if let Ok(file) = SourceFile::read(path) {
+                    modified.insert(path.clone(), time);
+                    changed.push(file);
+                }
+            }
+        }
+
+        let removed = modified
+            .keys()
+            .filter(|path| !paths.contains(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in &removed {
+            modified.remove(path);
+        }
+        let removed = removed.iter().map(|x| x.display().to_string()).collect::<Vec<_>>();
+
+        if changed.is_empty() && removed.is_empty() {
+            continue;
+        }
+
+        let previous = session.artifacts().clone();
+        match session.update(changed, &removed) {
+            Ok(report) => {
+                swift2vue::write_artifacts(&outdir, session.artifacts(), Some(&previous))?;
+                session.artifacts().diagnostics.iter().for_each(print_diagnostic);
+
+                if !report.bundled {
+                    println!("{}", "Nothing changed".dimmed());
+                } else if report.regenerated.is_empty() {
+                    println!("{}", "Rebuilt".green());
+                } else {
+                    println!("{} {}", "Rebuilt".green(), report.regenerated.join(", "));
+                }
+            }
+            Err(e) => print_diagnostic(&Diagnostic::from_error(&e, None, true)),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+fn write_node(out: &mut impl Write, node: &tree_sitter::Node, source: &str, level: usize) -> io::Result<()> {
+    let start = node.start_position();
+    let end = node.end_position();
+    let position = format!("[{}:{} - {}:{}]", start.row, start.column, end.row, end.column);
+
+    // only leaves carry text worth printing
+    let text = if node.child_count() == 0 {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or_default();
+        format!(" {:?}", text)
+    } else {
+        String::new()
+    };
+
+    writeln!(out, "{:indent$}{} {}{}", "", node.kind(), position.dimmed(), text.blue(), indent = level * 2)?;
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        write_node(out, &child, source, level + 1)?;
+    }
+    Ok(())
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    let severity = if diagnostic.fatal {
+        "error".red().bold()
+    } else {
+        "warning".yellow().bold()
+    };
+    eprintln!("{}[{}]: {}", severity, diagnostic.code, diagnostic.message);
+
+    let mut location = diagnostic.file.clone().unwrap_or_default();
+    if let (Some(row), Some(column)) = (diagnostic.row, diagnostic.column) {
+        // rows and columns are 0-based in diagnostics
+        location.push_str(&format!(":{}:{}", row + 1, column + 1));
+    }
+    if let Some(view) = &diagnostic.view {
+        location.push_str(&format!(" ({})", view));
+    }
+    if !location.is_empty() {
+        eprintln!("  {} {}", "-->".blue(), location.trim_start());
+    }
+
+    if let Some(snippet) = &diagnostic.snippet {
+        for line in snippet.lines().take(5) {
+            eprintln!("   {} {}", "|".blue(), line);
+        }
+    }
+}
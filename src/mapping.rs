@@ -0,0 +1,180 @@
+//! 源码映射：记录生成的代码片段来自 Swift 源码的哪个位置，用来生成 source map
+
+use tree_sitter::Point;
+
+/// Generated code with the Swift positions its pieces come from
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MappedCode {
+    pub code: String,
+    /// Byte offset in `code`, and the Swift position the code from there on is generated from
+    pub marks: Vec<(usize, Point)>,
+}
+
+impl From<String> for MappedCode {
+    fn from(code: String) -> Self {
+        Self {
+            code,
+            marks: Vec::new(),
+        }
+    }
+}
+
+impl MappedCode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_str(&mut self, code: &str) {
+        self.code.push_str(code);
+    }
+
+    /// The code pushed next is generated from `point`
+    pub fn mark(&mut self, point: Point) {
+        if let Some((offset, last)) = self.marks.last_mut() {
+            if *offset == self.code.len() {
+                *last = point;
+                return;
+            }
+        }
+        self.marks.push((self.code.len(), point));
+    }
+
+    pub fn push_mapped(&mut self, other: MappedCode) {
+        let start = self.code.len();
+        self.code.push_str(&other.code);
+        self.marks
+            .extend(other.marks.into_iter().map(|(offset, point)| (start + offset, point)));
+    }
+
+    /// Remove leading and trailing whitespace, like [`str::trim`]
+    pub fn trim(self) -> Self {
+        let start = self.code.len() - self.code.trim_start().len();
+        self.slice(start)
+    }
+
+    /// Remove trailing whitespace, like [`str::trim_end`]
+    pub fn trim_end(self) -> Self {
+        self.slice(0)
+    }
+
+    /// Keep the code from `start` up to the trailing whitespace
+    fn slice(mut self, start: usize) -> Self {
+        let end = self.code.trim_end().len().max(start);
+
+        self.code = self.code[start..end].to_string();
+        self.marks = self
+            .marks
+            .into_iter()
+            .map(|(offset, point)| (offset.clamp(start, end) - start, point))
+            .collect();
+        self.dedup();
+        self
+    }
+
+    /// Remove the line breaks, it's how a template is put into a JavaScript component
+    pub fn strip_newlines(mut self) -> Self {
+        let mut removed = Vec::new();
+        for (i, c) in self.code.char_indices() {
+            if c == '\n' {
+                removed.push(i);
+            }
+        }
+
+        self.code = self.code.replace('\n', "");
+        self.marks = self
+            .marks
+            .into_iter()
+            .map(|(offset, point)| (offset - removed.partition_point(|x| *x < offset), point))
+            .collect();
+        self.dedup();
+        self
+    }
+
+    /// Keep the last of marks at the same offset
+    fn dedup(&mut self) {
+        let mut marks: Vec<(usize, Point)> = Vec::new();
+        for (offset, point) in self.marks.drain(..) {
+            match marks.last_mut() {
+                Some(last) if last.0 == offset => *last = (offset, point),
+                _ => marks.push((offset, point)),
+            }
+        }
+        self.marks = marks;
+    }
+
+    /// A source map of `file`, the code, generated from the Swift file `swift_path`
+    pub fn to_source_map(&self, file: &str, swift_path: &str, swift_source: &str) -> sourcemap::SourceMap {
+        let mut builder = sourcemap::SourceMapBuilder::new(Some(file));
+        let src_id = builder.add_source(swift_path);
+        builder.set_source_contents(src_id, Some(swift_source));
+
+        let swift_lines = swift_source.split('\n').collect::<Vec<_>>();
+
+        for (offset, point) in &self.marks {
+            let (dst_line, dst_col) = utf16_position(&self.code, *offset);
+
+            // tree-sitter counts bytes, source maps count UTF-16 code units
+            let src_col = swift_lines
+                .get(point.row)
+                .and_then(|line| line.get(..point.column.min(line.len())))
+                .map(|x| x.encode_utf16().count())
+                .unwrap_or(point.column);
+
+            builder.add(
+                dst_line,
+                dst_col,
+                point.row as u32,
+                src_col as u32,
+                Some(swift_path),
+                None,
+                false,
+            );
+        }
+
+        builder.into_sourcemap()
+    }
+}
+
+/// Line and UTF-16 column of the byte `offset` of `code`
+pub fn utf16_position(code: &str, offset: usize) -> (u32, u32) {
+    let before = &code[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|x| x + 1).unwrap_or(0);
+
+    (line as u32, before[line_start..].encode_utf16().count() as u32)
+}
+
+/// Serialize `map` to the JSON of a `.map` file
+pub fn source_map_json(map: &sourcemap::SourceMap) -> String {
+    let mut buf = Vec::new();
+    // writing to a Vec doesn't fail
+    let _ = map.to_writer(&mut buf);
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use tree_sitter::Point;
+
+    use super::MappedCode;
+
+    #[test]
+    fn test_mapped_code() {
+        let mut code = MappedCode::from("\n".to_string());
+        code.mark(Point { row: 1, column: 4 });
+        code.push_str("<Text>\n");
+        let mut inner = MappedCode::new();
+        inner.mark(Point { row: 2, column: 8 });
+        inner.push_str("Hello</Text>\n");
+        code.push_mapped(inner);
+
+        let code = code.trim().strip_newlines();
+        assert_eq!(code.code, "<Text>Hello</Text>");
+        assert_eq!(code.marks, vec![(0, Point { row: 1, column: 4 }), (6, Point { row: 2, column: 8 })]);
+
+        let map = code.to_source_map("A.js", "A.swift", "\n    Text(\n        \"Hello\")");
+        let token = map.lookup_token(0, 7).unwrap();
+        assert_eq!((token.get_src_line(), token.get_src_col()), (2, 8));
+        assert_eq!(token.get_source(), Some("A.swift"));
+    }
+}
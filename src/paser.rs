@@ -1,10 +1,13 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+use indexmap::IndexMap;
+
 use tree_sitter::Node;
 
-use crate::utils::{find_first_node, log_node};
+use crate::error::{NodeExt, Result};
 #[allow(unused)]
 use crate::utils::log_node_tree;
+use crate::utils::{find_first_node, log_node};
 
 #[derive(Debug, Clone)]
 pub enum StructMember<'a> {
@@ -12,30 +15,147 @@ pub enum StructMember<'a> {
     Property {
         node: Node<'a>,
         modifier: Option<String>,
+        /// The `type_annotation` node, e.g. `: [Movie]`
+        ty: Option<Node<'a>>,
+    },
+    /// A stored property without a value, e.g. `let title: String`, it's set by whoever creates the struct
+    Stored {
+        ty: Node<'a>,
+        modifier: Option<String>,
+    },
+    /// `var total: Double { price * count }`, a value derived from the other members.
+    /// `body` and the other properties of views are [`StructMember::Property`]
+    Computed {
+        /// The `computed_property`
+        node: Node<'a>,
+        ty: Option<Node<'a>>,
+    },
+    /// `var header: some View { ... }` or `func row(_ item: Item) -> some View`, a part of `body`
+    /// split out of it, the template is inlined where it's used
+    ViewBuilder {
+        /// The `statements` of the getter or of the function body
+        statements: Node<'a>,
+        /// The names of the parameters in order, `item` of `_ item: Item`
+        params: Vec<String>,
     },
+    /// `@Environment(\.colorScheme) var colorScheme`, the value is read from the environment by its key
+    Environment {
+        key: String,
+        /// The `property_declaration`
+        node: Node<'a>,
+    },
+}
+
+/// The keyword a `class_declaration` starts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclKind {
+    Struct,
+    Class,
+    Enum,
+    Extension,
+}
+
+impl DeclKind {
+    fn from_node(node: &Node) -> Self {
+        match node.child(0).map(|x| x.kind()) {
+            Some("class") | Some("actor") => DeclKind::Class,
+            Some("enum") => DeclKind::Enum,
+            Some("extension") => DeclKind::Extension,
+            _ => DeclKind::Struct,
+        }
+    }
+}
+
+/// An `extension` block, it may be declared in another file than the type it extends
+#[derive(Debug, Clone)]
+pub struct Extension<'a> {
+    pub node: Node<'a>,
+    /// Path of the file it's declared in
+    pub path: Rc<str>,
+    /// Source of the file it's declared in
+    pub source: Rc<str>,
 }
 
 #[derive(Debug, Clone)]
 pub struct StructInfo<'a> {
     pub name: String,
-    pub source: String,
+    pub kind: DeclKind,
+    /// Path of the file it's declared in
+    pub path: Rc<str>,
+    /// Source of the file it's declared in
+    pub source: Rc<str>,
     pub node: Node<'a>,
-    pub members: HashMap<String, StructMember<'a>>,
+    pub members: IndexMap<String, StructMember<'a>>,
     pub inheritance: Option<String>,
     pub sub: Vec<Rc<RefCell<StructInfo<'a>>>>,
+    /// The `extension` blocks of the type, their members are merged into `members`
+    pub extensions: Vec<Extension<'a>>,
+    /// The index in `extensions` of the members an extension declares
+    pub extension_members: HashMap<String, usize>,
 }
 
 impl StructInfo<'_> {
     pub fn sub_names(&self) -> Vec<String> {
         self.sub.iter().map(|x| x.borrow().name.clone()).collect()
     }
+
+    /// Source of the file the member `name` is declared in
+    pub fn member_source(&self, name: &str) -> &Rc<str> {
+        match self.extension_members.get(name).and_then(|i| self.extensions.get(*i)) {
+            Some(ext) => &ext.source,
+            None => &self.source,
+        }
+    }
+
+    pub fn is_view(&self) -> bool {
+        self.inheritance.as_deref() == Some("View")
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.inheritance.as_deref() == Some("PreviewProvider")
+    }
+
+    /// A class conforming to `ObservableObject`, it may inherit from another class first
+    pub fn is_observable(&self) -> bool {
+        let mut cursor = self.node.walk();
+        let observable = self
+            .node
+            .children(&mut cursor)
+            .any(|x| x.kind() == "inheritance_specifier" && x.text(&self.source).ok() == Some("ObservableObject"));
+        self.kind == DeclKind::Class && observable
+    }
+
+    /// `@Binding` properties, the parent owns their value
+    pub fn bindings(&self) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|(_, member)| {
+                matches!(member, StructMember::Stored { modifier, .. } if modifier.as_deref() == Some("Binding"))
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// A `let`/`var` or a function declared at file scope
+#[derive(Debug, Clone)]
+pub struct GlobalDecl<'a> {
+    pub name: String,
+    /// Path of the file it's declared in
+    pub path: Rc<str>,
+    /// Source of the file it's declared in
+    pub source: Rc<str>,
+    /// The `property_declaration` or `function_declaration`
+    pub node: Node<'a>,
 }
 
 #[derive(Debug, Default)]
 pub struct State<'a> {
-    source: String,
+    path: Rc<str>,
+    source: Rc<str>,
     struct_def_level: usize,
     pub struct_list: Vec<Rc<RefCell<StructInfo<'a>>>>,
+    pub globals: Vec<GlobalDecl<'a>>,
     current_struct: Option<Rc<RefCell<StructInfo<'a>>>>,
     pub verbose: bool,
 
@@ -43,34 +163,34 @@ pub struct State<'a> {
 }
 
 impl<'a> State<'a> {
-    pub fn new(source: String, verbose: bool) -> Self {
-        let mut instance = Self::default();
-        instance.source = source;
-        instance.verbose = verbose;
-        instance
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            ..Default::default()
+        }
     }
 }
 
 impl<'a> State<'a> {
-    fn handle_struct_nodes(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> bool {
+    fn handle_struct_nodes(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<bool> {
         let node = cursor.node();
+        let source = &*self.source;
 
-        let mut struct_info = self.current_struct.as_ref().unwrap().borrow_mut();
+        let Some(current_struct) = self.current_struct.as_ref() else {
+            return Ok(true);
+        };
+        let mut struct_info = current_struct.borrow_mut();
 
-        if struct_info.name.is_empty() {
-            if node.kind() == "type_identifier" {
-                let name = node.utf8_text(self.source.as_bytes()).unwrap();
-                struct_info.name = name.to_string();
-                return false;
-            }
+        if struct_info.name.is_empty() && node.kind() == "type_identifier" {
+            let name = node.text(source)?;
+            struct_info.name = name.to_string();
+            return Ok(false);
         }
 
-        if struct_info.inheritance.is_none() {
-            if node.kind() == "inheritance_specifier" {
-                let inheritance = node.utf8_text(self.source.as_bytes()).unwrap();
-                struct_info.inheritance = Some(inheritance.to_string());
-                return false;
-            }
+        if struct_info.inheritance.is_none() && node.kind() == "inheritance_specifier" {
+            let inheritance = node.text(source)?;
+            struct_info.inheritance = Some(inheritance.to_string());
+            return Ok(false);
         }
 
         if node.kind() == "property_declaration" {
@@ -79,23 +199,27 @@ impl<'a> State<'a> {
             let mut name = String::new();
             let mut var_node: Option<Node> = None;
             let mut modifier: Option<String> = None;
+            let mut ty: Option<Node> = None;
+            let mut environment_key: Option<String> = None;
+            let mut computed: Option<Node> = None;
 
             for i in 0..node.child_count() {
-                let child = node.child(i).unwrap();
+                let child = node.child_at(i, source)?;
                 if child.kind() == "pattern" {
-                    let idnode = child.child(0).unwrap();
+                    let idnode = child.child_at(0, source)?;
                     if idnode.kind() == "simple_identifier" {
-                        name = idnode
-                            .utf8_text(self.source.as_bytes())
-                            .unwrap()
-                            .to_string();
+                        name = idnode.text(source)?.to_string();
                     }
+                } else if child.kind() == "type_annotation" {
+                    ty = Some(child);
                 } else if child.kind() == "computed_property" {
-                    if let Some(statements) = find_first_node(child.clone(), "statements", &self.source) {
+                    computed = Some(child);
+                    if let Some(statements) = find_first_node(child, "statements") {
                         // log_node_tree(&statements, 0, &self.source);
                         // TODO: 这里只处理了 computed_property 的第一个调用，对于 SwiftUI 足够了
-                        let call_node = statements.child(0).unwrap();
-                        if call_node.kind() == "call_expression" {
+                        let call_node = statements.child_at(0, source)?;
+                        // `header` of `var body: some View { header }`, a view builder
+                        if ["call_expression", "simple_identifier", "if_statement"].contains(&call_node.kind()) {
                             var_node = Some(call_node);
                         }
                     }
@@ -108,62 +232,109 @@ impl<'a> State<'a> {
                     var_node = Some(call_node);
                 } else if child.kind() == "modifiers" {
                     // TODO: we only support one attribute for now
-                    let attribute_node = child.child(0).unwrap();
+                    let attribute_node = child.child_at(0, source)?;
                     if attribute_node.kind() == "attribute" {
-                        let modifier_node = attribute_node.child(1).unwrap();
-                        modifier = Some(
-                            modifier_node
-                                .utf8_text(self.source.as_bytes())
-                                .unwrap()
-                                .to_string(),
-                        );
+                        let modifier_node = attribute_node.child_at(1, source)?;
+                        modifier = Some(modifier_node.text(source)?.to_string());
+
+                        // `\.colorScheme` of `@Environment(\.colorScheme)`
+                        if let Some(key) = find_first_node(attribute_node, "navigation_suffix").and_then(|x| x.named_child(0)) {
+                            environment_key = Some(key.text(source)?.to_string());
+                        }
                     }
                 } else {
                     continue;
                 }
             }
 
-            if let Some(var_node) = var_node {
+            // `some View` builds a part of the template
+            let view_type = match ty {
+                Some(ty) => {
+                    let ty = ty.text(source)?.trim_start_matches(':').trim();
+                    ty.starts_with("some ") || ty == "AnyView"
+                }
+                None => true,
+            };
+
+            // `previews` of a `PreviewProvider` is the body of the preview
+            let root_view = name == "body" || name == "previews";
+
+            if let (Some("Environment"), Some(key)) = (modifier.as_deref(), environment_key) {
+                struct_info.members.insert(name, StructMember::Environment { key, node });
+            } else if let Some(computed) = computed.filter(|_| !view_type && !root_view) {
+                struct_info.members.insert(name, StructMember::Computed { node: computed, ty });
+            } else if let Some(statements) = computed
+                .filter(|_| ty.is_some() && !root_view)
+                .and_then(|x| find_first_node(x, "statements"))
+            {
+                struct_info.members.insert(name, StructMember::ViewBuilder { statements, params: Vec::new() });
+            } else if let Some(var_node) = var_node {
                 let var = StructMember::Property {
                     node: var_node,
                     modifier,
+                    ty,
                 };
                 struct_info.members.insert(name, var);
+            } else if let Some(ty) = ty.filter(|x| modifier.as_deref() == Some("State") && find_first_node(*x, "optional_type").is_some()) {
+                // `@State var user: User?` starts as nil, the type annotation stands for the value
+                struct_info.members.insert(name, StructMember::Property { node: ty, modifier, ty: Some(ty) });
+            } else if let Some(ty) = ty {
+                struct_info.members.insert(name, StructMember::Stored { ty, modifier });
             }
 
-            return false;
+            return Ok(false);
         }
 
         if node.kind() == "function_declaration" {
             let mut name = String::new();
             let mut fn_node: Option<Node> = None;
+            let mut params = Vec::new();
+            let mut view_builder = false;
 
             for i in 0..node.child_count() {
-                let child = node.child(i).unwrap();
+                let child = node.child_at(i, source)?;
                 if child.kind() == "simple_identifier" {
-                    name = child.utf8_text(self.source.as_bytes()).unwrap().to_string();
+                    name = child.text(source)?.to_string();
                 } else if child.kind() == "function_body" {
-                    fn_node = Some(child.child(1).unwrap());
+                    fn_node = Some(child.child_at(1, source)?);
+                } else if child.kind() == "parameter" {
+                    // `at index: Int`, the last identifier is the name used in the body
+                    let mut cursor = child.walk();
+                    let param = child.named_children(&mut cursor).filter(|x| x.kind() == "simple_identifier").last();
+                    if let Some(param) = param {
+                        params.push(param.text(source)?.to_string());
+                    }
+                } else if child.kind() == "modifiers" {
+                    view_builder |= child.text(source)?.contains("@ViewBuilder");
+                } else if child.prev_sibling().map(|x| x.kind() == "->").unwrap_or(false) {
+                    let ty = child.text(source)?;
+                    view_builder |= ty.starts_with("some ") || ty == "AnyView";
                 } else {
                     continue;
                 }
             }
 
-            if let Some(fn_node) = fn_node {
-                struct_info
-                    .members
-                    .insert(name, StructMember::Function(fn_node));
+            match fn_node {
+                Some(statements) if view_builder => {
+                    struct_info.members.insert(name, StructMember::ViewBuilder { statements, params });
+                }
+                Some(fn_node) => {
+                    struct_info
+                        .members
+                        .insert(name, StructMember::Function(fn_node));
+                }
+                None => {}
             }
 
-            return false;
+            return Ok(false);
         }
 
         // log_node(&node, cursor.depth() as u32);
 
-        return true;
+        Ok(true)
     }
 
-    fn handle_node(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> bool {
+    fn handle_node(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<bool> {
         let node = cursor.node();
         if self.verbose {
             log_node(&node, cursor.depth(), &self.source);
@@ -174,19 +345,23 @@ impl<'a> State<'a> {
 
             let struct_info = StructInfo {
                 name: String::new(),
-                source: node.utf8_text(self.source.as_bytes()).unwrap().to_string(),
+                kind: DeclKind::from_node(&node),
+                path: self.path.clone(),
+                source: self.source.clone(),
                 node,
-                members: HashMap::new(),
+                members: IndexMap::new(),
                 inheritance: None,
                 sub: Vec::new(),
+                extensions: Vec::new(),
+                extension_members: HashMap::new(),
             };
 
             if self.struct_def_level == 1 {
                 self.struct_list.push(Rc::new(RefCell::new(struct_info)));
                 self.current_struct = self.struct_list.last().cloned();
                 self.first_level_struct = self.current_struct.clone();
-            } else {
-                let mut parent_struct = self.first_level_struct.as_ref().unwrap().borrow_mut();
+            } else if let Some(first_level_struct) = self.first_level_struct.as_ref() {
+                let mut parent_struct = first_level_struct.borrow_mut();
                 parent_struct.sub.push(Rc::new(RefCell::new(struct_info)));
                 self.current_struct = parent_struct.sub.last().cloned();
             }
@@ -198,7 +373,38 @@ impl<'a> State<'a> {
             return self.handle_struct_nodes(cursor);
         }
 
-        return true;
+        let top_level = node.parent().map(|x| x.kind() == "source_file").unwrap_or(false);
+        if top_level && ["property_declaration", "function_declaration"].contains(&node.kind()) {
+            self.handle_global(&node)?;
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    fn handle_global(&mut self, node: &Node<'a>) -> Result<()> {
+        let source = &*self.source;
+        let name = match node.kind() {
+            "property_declaration" => find_first_node(*node, "pattern")
+                .map(|x| x.text(source).map(|x| x.to_string()))
+                .transpose()?,
+            _ => {
+                let mut cursor = node.walk();
+                let name = node.children(&mut cursor).find(|x| x.kind() == "simple_identifier");
+                name.map(|x| x.text(source).map(|x| x.to_string())).transpose()?
+            }
+        };
+
+        if let Some(name) = name {
+            self.globals.push(GlobalDecl {
+                name,
+                path: self.path.clone(),
+                source: self.source.clone(),
+                node: *node,
+            });
+        }
+
+        Ok(())
     }
 
     fn handle_node_post(&mut self, cursor: &mut tree_sitter::TreeCursor) {
@@ -216,19 +422,25 @@ impl<'a> State<'a> {
         }
     }
 
-    pub fn handle_source(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) {
-        let go_on = self.handle_node(cursor);
+    /// Collect the declarations of a file, `cursor` must come from the tree of `source`
+    pub fn handle_file(&mut self, path: &str, source: &str, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<()> {
+        self.path = Rc::from(path);
+        self.source = Rc::from(source);
+        self.handle_source(cursor)
+    }
 
-        if go_on {
-            if cursor.goto_first_child() {
-                self.handle_source(cursor);
-                while cursor.goto_next_sibling() {
-                    self.handle_source(cursor);
-                }
-                cursor.goto_parent();
+    fn handle_source(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<()> {
+        let go_on = self.handle_node(cursor)?;
+
+        if go_on && cursor.goto_first_child() {
+            self.handle_source(cursor)?;
+            while cursor.goto_next_sibling() {
+                self.handle_source(cursor)?;
             }
+            cursor.goto_parent();
         }
 
         self.handle_node_post(cursor);
+        Ok(())
     }
 }
@@ -0,0 +1,177 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    error::{CompileError, ErrorCode, Result},
+    paser::{DeclKind, GlobalDecl, StructInfo, StructMember},
+};
+
+/// A Swift file of the project
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Used in diagnostics, it doesn't have to exist on disk
+    pub path: String,
+    pub source: String,
+}
+
+impl SourceFile {
+    pub fn new(path: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            CompileError::new(ErrorCode::Io, format!("{}: {}", path.display(), e))
+        })?;
+        Ok(Self::new(path.display().to_string(), source))
+    }
+}
+
+/// The given files, directories are searched recursively for `.swift` files
+pub fn find_sources(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            collect_swift_files(input, &mut paths)?;
+        } else {
+            paths.push(input.clone());
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Read the given files, directories are searched recursively for `.swift` files
+pub fn read_sources(inputs: &[PathBuf]) -> Result<Vec<SourceFile>> {
+    let files = find_sources(inputs)?
+        .iter()
+        .map(|path| SourceFile::read(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    if files.is_empty() {
+        return Err(CompileError::new(ErrorCode::Io, "No Swift source found"));
+    }
+
+    Ok(files)
+}
+
+fn collect_swift_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    // keep the output stable
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_swift_files(&path, paths)?;
+        } else if path.extension().map(|x| x == "swift").unwrap_or(false) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn parse_swift(source: &str) -> Result<tree_sitter::Tree> {
+    let lang = tree_sitter_swift::language();
+
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&lang)
+        .map_err(|e| CompileError::new(ErrorCode::Parse, format!("Error loading Swift grammar: {}", e)))?;
+
+    parser
+        .parse(source, None)
+        .ok_or_else(|| CompileError::new(ErrorCode::Parse, "Failed to parse the Swift source"))
+}
+
+/// Top level declarations of all files, used to resolve names across files
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SymbolTable {
+    pub views: BTreeSet<String>,
+    pub models: BTreeSet<String>,
+    pub enums: BTreeSet<String>,
+    /// `ObservableObject` classes, translated to reactive stores
+    pub stores: BTreeSet<String>,
+    /// Constants and variables declared at file scope
+    pub globals: BTreeSet<String>,
+    /// Functions declared at file scope
+    pub functions: BTreeSet<String>,
+    /// Properties of the models, stores and enums by type, `.count` of them isn't the length of an array
+    pub members: BTreeMap<String, BTreeSet<String>>,
+    /// Methods of the models, stores and enums, called with the arguments in order
+    pub methods: BTreeSet<String>,
+}
+
+impl SymbolTable {
+    pub fn from_declarations(list: &[Rc<RefCell<StructInfo>>], globals: &[GlobalDecl]) -> Self {
+        let mut table = Self::default();
+
+        for global in globals {
+            if global.node.kind() == "function_declaration" {
+                table.functions.insert(global.name.clone());
+            } else {
+                table.globals.insert(global.name.clone());
+            }
+        }
+
+        for st in list {
+            let st = st.borrow();
+            if st.is_view() {
+                table.views.insert(st.name.clone());
+                continue;
+            }
+            if st.is_preview() || st.kind == DeclKind::Extension {
+                continue;
+            }
+
+            let properties = st.members.iter().filter(|(_, x)| {
+                !matches!(x, StructMember::Function(_) | StructMember::ViewBuilder { .. })
+            });
+            let properties = properties.map(|(name, _)| name.clone());
+            table.members.entry(st.name.clone()).or_default().extend(properties);
+            let methods = st.members.iter().filter(|(_, x)| matches!(x, StructMember::Function(_)));
+            table.methods.extend(methods.map(|(name, _)| name.clone()));
+            if st.kind == DeclKind::Enum {
+                table.enums.insert(st.name.clone());
+            } else if st.is_observable() {
+                table.stores.insert(st.name.clone());
+            } else {
+                table.models.insert(st.name.clone());
+            }
+        }
+
+        table
+    }
+
+    /// `count` of `struct Stats { var count: Int }`
+    pub fn declares(&self, ty: &str, name: &str) -> bool {
+        self.members.get(ty).is_some_and(|x| x.contains(name))
+    }
+
+    pub fn is_view(&self, name: &str) -> bool {
+        self.views.contains(name)
+    }
+
+    /// Models, enums and stores, the types the TypeScript output can refer to
+    pub fn type_names(&self) -> Vec<String> {
+        let names = self.models.iter().chain(&self.enums).chain(&self.stores);
+        names.cloned().collect::<BTreeSet<_>>().into_iter().collect()
+    }
+
+    /// Models, enums, stores and the declarations at file scope, the names `shared.js` exports
+    pub fn shared_names(&self) -> Vec<String> {
+        let names = self.models.iter().chain(&self.enums).chain(&self.stores);
+        let names = names.chain(&self.globals).chain(&self.functions);
+        names.cloned().collect::<BTreeSet<_>>().into_iter().collect()
+    }
+}
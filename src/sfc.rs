@@ -0,0 +1,131 @@
+//! 单文件组件 (`.vue`) 输出用到的工具函数
+
+use crate::error::{CompileError, ErrorCode, Result};
+
+/// Vue APIs the generated code and builtin views use, they are globals in the bundled app
+const VUE_APIS: [&str; 8] = [
+    "createApp",
+    "ref",
+    "provide",
+    "inject",
+    "watch",
+    "reactive",
+    "computed",
+    "onMounted",
+];
+
+/// `true` if `name` shows up in `code` as a whole identifier
+pub fn uses_identifier(code: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+    code.match_indices(name).any(|(i, _)| {
+        let before = code[..i].chars().next_back();
+        let after = code[i + name.len()..].chars().next();
+        !before.map(is_ident).unwrap_or(false) && !after.map(is_ident).unwrap_or(false)
+    })
+}
+
+/// `import { ... } from 'vue'` for the Vue APIs called in `code`, `ref<number>(0)` of TypeScript too
+pub fn vue_import(code: &str) -> Option<String> {
+    let called = |api: &str| code.contains(&format!("{}(", api)) || code.contains(&format!("{}<", api));
+    let apis = VUE_APIS
+        .iter()
+        .filter(|api| called(api) && uses_identifier(code, api))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if apis.is_empty() {
+        None
+    } else {
+        Some(format!("import {{ {} }} from 'vue'", apis.join(", ")))
+    }
+}
+
+/// `template` is put in as is, it's expected to be indented already
+pub fn render_sfc(script: &str, template: &str, setup: bool, typescript: bool) -> String {
+    let script_tag = match (setup, typescript) {
+        (true, true) => "<script setup lang=\"ts\">",
+        (true, false) => "<script setup>",
+        (false, true) => "<script lang=\"ts\">",
+        (false, false) => "<script>",
+    };
+    let script = if script.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", script)
+    };
+
+    format!(
+        "{script_tag}\n{script}</script>\n\n<template>\n{template}\n</template>\n\n<style scoped>\n</style>\n"
+    )
+}
+
+/// Turn a builtin view, an object with a `template` string, into a Single-File Component.
+/// Imports of other builtin views are pointed to their `.vue` files
+pub fn builtin_view_to_sfc(name: &str, code: &str, builtin_views: &[String]) -> Result<String> {
+    let missing_template = || {
+        CompileError::new(
+            ErrorCode::Template,
+            format!("builtin view {} has no template string", name),
+        )
+    };
+
+    let start = code.rfind("template: `").ok_or_else(missing_template)?;
+    let template_start = start + "template: `".len();
+    let template_len = code[template_start..].find('`').ok_or_else(missing_template)?;
+
+    let template = &code[template_start..template_start + template_len];
+    let before = code[..start].trim_end();
+    let mut after = &code[template_start + template_len + 1..];
+    // the comma before the template is kept, a trailing comma is fine in an object literal
+    if before.ends_with(',') {
+        after = after.strip_prefix(',').unwrap_or(after);
+    }
+    let mut script = format!("{}{}", before, after);
+
+    for view in builtin_views {
+        for quote in ['"', '\''] {
+            script = script.replace(
+                &format!("{quote}./{view}.js{quote}"),
+                &format!("{quote}./{view}.vue{quote}"),
+            );
+        }
+    }
+
+    if let Some(import) = vue_import(&script) {
+        script = format!("{}\n{}", import, script);
+    }
+
+    Ok(render_sfc(script.trim(), template.trim(), false, false))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_uses_identifier() {
+        assert!(super::uses_identifier("const a = UUID()", "UUID"));
+        assert!(super::uses_identifier("<Text>{{ title }}</Text>", "title"));
+        assert!(!super::uses_identifier("const subtitle = 1", "title"));
+        assert!(!super::uses_identifier("const title_2 = 1", "title"));
+    }
+
+    #[test]
+    fn test_builtin_view_to_sfc() {
+        let code = r#"import SwiftCommon from "./SwiftCommon.js";
+
+export default {
+  components: { SwiftCommon },
+  setup() {
+    const text = computed(() => 1)
+    return { text }
+  },
+  template: `<SwiftCommon>{{ text }}</SwiftCommon>`,
+};"#;
+
+        let sfc = super::builtin_view_to_sfc("Text", code, &["SwiftCommon".to_string()]).unwrap();
+        assert!(sfc.starts_with("<script>\nimport { computed } from 'vue'\nimport SwiftCommon from \"./SwiftCommon.vue\";"));
+        assert!(sfc.contains("<template>\n<SwiftCommon>{{ text }}</SwiftCommon>\n</template>"));
+        assert!(!sfc.contains("template:"));
+        assert!(sfc.contains("return { text }\n  },\n};"));
+    }
+}
@@ -1,27 +1,111 @@
 use handlebars::Handlebars;
 
-pub static APP_JS_TEMPLATE: &'static str = include_str!("app.js.hbs");
-pub static TEMPLATE: &'static str = include_str!("template.hbs");
+use crate::error::Result;
 
-pub fn generate_template_html(styles: Vec<String>, script: String) -> String {
+pub static APP_JS_TEMPLATE: &str = include_str!("app.js.hbs");
+pub static TEMPLATE: &str = include_str!("template.hbs");
+pub static APP_VUE_TEMPLATE: &str = include_str!("App.vue.hbs");
+pub static MAIN_JS_TEMPLATE: &str = include_str!("main.js.hbs");
+pub static PACKAGE_JSON_TEMPLATE: &str = include_str!("package.json.hbs");
+pub static VITE_CONFIG: &str = include_str!("vite.config.hbs");
+pub static VITE_INDEX_HTML: &str = include_str!("vite-index.html.hbs");
+pub static TSCONFIG_JSON: &str = include_str!("tsconfig.json.hbs");
+
+/// How `index.html` loads the libraries, see [`crate::Libraries`]
+#[derive(Debug, Default)]
+pub struct PageLibraries {
+  /// URLs of the stylesheets to link
+  pub stylesheets: Vec<String>,
+  /// URLs of the classic scripts to load
+  pub scripts: Vec<String>,
+  /// Code of the classic scripts put into the page
+  pub inline_scripts: Vec<String>,
+  /// JSON of `<script type="importmap">`, Vue and naive-ui are imported from it instead of globals
+  pub import_map: Option<String>,
+}
+
+/// `styles` are put into the page as is, like `libraries.inline_scripts`
+pub fn generate_template_html(styles: Vec<String>, libraries: PageLibraries, script: String) -> Result<String> {
   let reg = Handlebars::new();
 
+  // a script can't contain its own end tag
+  let inline_scripts = libraries
+    .inline_scripts
+    .iter()
+    .map(|x| x.replace("</script", "<\\/script"))
+    .collect::<Vec<_>>();
+
   let context = serde_json::json!({
     "styles": styles,
+    "stylesheets": libraries.stylesheets,
+    "scripts": libraries.scripts,
+    "inline_scripts": inline_scripts,
+    "import_map": libraries.import_map,
     "script": script
   });
 
-  reg.render_template(TEMPLATE, &context).unwrap()
+  Ok(reg.render_template(TEMPLATE, &context)?)
 }
 
-pub fn generate_app_js(imports: Vec<String>, views: Vec<String>, index_template: String) -> String {
+/// `shared` are the names of `shared.js` the previews use, e.g. a store passed to `.environmentObject()`
+pub fn generate_app_js(imports: Vec<String>, views: Vec<String>, shared: Vec<String>, index_template: String) -> Result<String> {
   let reg = Handlebars::new();
 
   let context = serde_json::json!({
     "imports": imports,
     "views": views,
+    "shared": shared,
     "index_template": index_template
   });
 
-  reg.render_template(APP_JS_TEMPLATE, &context).unwrap()
-}
\ No newline at end of file
+  Ok(reg.render_template(APP_JS_TEMPLATE, &context)?)
+}
+
+/// The root component of the Single-File Component output, `styles` are the file names of the stylesheets.
+/// The views and stylesheets are imported from `components_dir` and `styles_dir`, e.g. `components/`,
+/// `shared` are the names the previews use from `shared_module`, e.g. `shared.js`
+pub fn generate_app_vue(
+  views: Vec<String>,
+  shared: Vec<String>,
+  shared_module: &str,
+  index_template: String,
+  styles: Vec<String>,
+  components_dir: &str,
+  styles_dir: &str,
+) -> Result<String> {
+  let reg = Handlebars::new();
+
+  let context = serde_json::json!({
+    "views": views,
+    "shared": shared,
+    "shared_module": shared_module,
+    "index_template": index_template,
+    "styles": styles,
+    "components_dir": components_dir,
+    "styles_dir": styles_dir
+  });
+
+  Ok(reg.render_template(APP_VUE_TEMPLATE, &context)?)
+}
+
+/// `main.js` mounting `App.vue`, `coloris` sets up the color picker the builtin `ColorPicker` uses
+pub fn generate_main_js(coloris: bool) -> Result<String> {
+  let reg = Handlebars::new();
+
+  let context = serde_json::json!({
+    "coloris": coloris
+  });
+
+  Ok(reg.render_template(MAIN_JS_TEMPLATE, &context)?)
+}
+
+/// `package.json` of the Vite project output
+pub fn generate_package_json(typescript: bool) -> Result<String> {
+  let reg = Handlebars::new();
+
+  let context = serde_json::json!({
+    "typescript": typescript
+  });
+
+  Ok(reg.render_template(PACKAGE_JSON_TEMPLATE, &context)?)
+}
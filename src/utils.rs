@@ -16,12 +16,12 @@ pub fn prettify_xml(xml: String) -> String {
 }
 
 pub fn inline_str(s: &str) -> String {
-  s.replace("\n", "")
+  s.replace('\n', "")
 }
 
 #[allow(dead_code)]
-pub fn log_node(node: &tree_sitter::Node, level: u32, source: &String) {
-  let content = node.utf8_text(source.as_bytes()).unwrap();
+pub fn log_node(node: &tree_sitter::Node, level: u32, source: &str) {
+  let content = node.utf8_text(source.as_bytes()).unwrap_or_default();
   // content without new line
   let content = inline_str(content).blue();
 
@@ -35,9 +35,9 @@ pub fn log_node(node: &tree_sitter::Node, level: u32, source: &String) {
 
   // log all attributes
   println!(
-      "{:indent$}  节点信息: {} id={}",
+      "{:indent$}  节点信息: {:?} id={}",
       "",
-      format!("{:?}", node),
+      node,
       node.id(),
       indent = level as usize * 3
   );
@@ -48,8 +48,8 @@ pub fn log_node(node: &tree_sitter::Node, level: u32, source: &String) {
 }
 
 #[allow(dead_code)]
-pub fn log_node_tree(node: &tree_sitter::Node, level: u32, source: &String) {
-  let content = node.utf8_text(source.as_bytes()).unwrap();
+pub fn log_node_tree(node: &tree_sitter::Node, level: u32, source: &str) {
+  let content = node.utf8_text(source.as_bytes()).unwrap_or_default();
   // content without new line
   let content = inline_str(content).blue();
 
@@ -63,9 +63,9 @@ pub fn log_node_tree(node: &tree_sitter::Node, level: u32, source: &String) {
 
   // log all attributes
   println!(
-      "{:indent$}  节点信息: {} id={}",
+      "{:indent$}  节点信息: {:?} id={}",
       "",
-      format!("{:?}", node),
+      node,
       node.id(),
       indent = level as usize * 3
   );
@@ -75,13 +75,13 @@ pub fn log_node_tree(node: &tree_sitter::Node, level: u32, source: &String) {
   }
 }
 
-pub fn find_first_node<'a>(node: tree_sitter::Node<'a>, kind: &str, source: &String) -> Option<tree_sitter::Node<'a>> {
+pub fn find_first_node<'a>(node: tree_sitter::Node<'a>, kind: &str) -> Option<tree_sitter::Node<'a>> {
   for i in 0..node.child_count() {
-    let child = node.child(i).unwrap();
+    let child = node.child(i)?;
     if child.kind() == kind {
       return Some(child);
     }
-    if let Some(r) = find_first_node(child, kind, source) {
+    if let Some(r) = find_first_node(child, kind) {
       return Some(r);
     }
   }
@@ -89,14 +89,10 @@ pub fn find_first_node<'a>(node: tree_sitter::Node<'a>, kind: &str, source: &Str
   None
 }
 
-pub fn find_first_simple_identifier(node: &tree_sitter::Node, source: &String) -> Option<String> {
-  for i in 0..node.child_count() {
-    let child = node.child(i).unwrap();
-    if child.kind() == "simple_identifier" {
-      return Some(child.utf8_text(source.as_bytes()).unwrap().to_string());
-    }
-    return find_first_simple_identifier(&child, source);
+pub fn find_first_simple_identifier(node: &tree_sitter::Node, source: &str) -> Option<String> {
+  let child = node.child(0)?;
+  if child.kind() == "simple_identifier" {
+    return child.utf8_text(source.as_bytes()).ok().map(|x| x.to_string());
   }
-
-  None
+  find_first_simple_identifier(&child, source)
 }
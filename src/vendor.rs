@@ -0,0 +1,110 @@
+//! 页面依赖的第三方库：固定版本的 CDN 地址，以及 `vendor/` 里打包进 crate 的离线副本
+
+#[cfg(feature = "vendor")]
+use include_dir::{include_dir, Dir};
+
+use crate::error::{CompileError, ErrorCode, Result};
+
+/// Filled by `vendor/fetch.sh`, `build.rs` checks it's complete when the `vendor` feature is on
+#[cfg(feature = "vendor")]
+static VENDOR_DIR: Dir = include_dir!("./vendor");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryKind {
+    Script,
+    Stylesheet,
+}
+
+/// A library `index.html` loads before the bundled app
+#[derive(Debug)]
+pub struct Library {
+    /// File name in `vendor/`
+    pub file_name: &'static str,
+    /// The same file of the same version on a CDN
+    pub url: &'static str,
+    pub kind: LibraryKind,
+    /// Module specifier and the URL of the ES module build, for the import map.
+    /// `None` if the page keeps loading it as a classic script
+    pub module: Option<(&'static str, &'static str)>,
+}
+
+/// In load order, naive-ui needs the `Vue` global
+pub static LIBRARIES: [Library; 4] = [
+    Library {
+        file_name: "vue.global.prod.js",
+        url: "https://unpkg.com/vue@3.4.27/dist/vue.global.prod.js",
+        kind: LibraryKind::Script,
+        // the full build, the components compile their template strings at runtime
+        module: Some(("vue", "https://esm.sh/vue@3.4.27/dist/vue.esm-browser.prod.js")),
+    },
+    Library {
+        file_name: "naive-ui.prod.js",
+        url: "https://unpkg.com/naive-ui@2.38.2/dist/index.prod.js",
+        kind: LibraryKind::Script,
+        module: Some(("naive-ui", "https://esm.sh/naive-ui@2.38.2?external=vue")),
+    },
+    Library {
+        file_name: "coloris.min.js",
+        url: "https://cdn.jsdelivr.net/gh/mdbassit/Coloris@v0.24.0/dist/coloris.min.js",
+        kind: LibraryKind::Script,
+        module: None,
+    },
+    Library {
+        file_name: "coloris.min.css",
+        url: "https://cdn.jsdelivr.net/gh/mdbassit/Coloris@v0.24.0/dist/coloris.min.css",
+        kind: LibraryKind::Stylesheet,
+        module: None,
+    },
+];
+
+impl Library {
+    /// The vendored copy
+    #[cfg(feature = "vendor")]
+    pub fn contents(&self) -> Result<&'static str> {
+        VENDOR_DIR
+            .get_file(self.file_name)
+            .and_then(|x| x.contents_utf8())
+            .ok_or_else(|| {
+                CompileError::new(
+                    ErrorCode::Vendor,
+                    format!(
+                        "vendor/{} is not embedded, run vendor/fetch.sh and rebuild swift2vue",
+                        self.file_name
+                    ),
+                )
+            })
+    }
+
+    #[cfg(not(feature = "vendor"))]
+    pub fn contents(&self) -> Result<&'static str> {
+        Err(CompileError::new(
+            ErrorCode::Vendor,
+            format!(
+                "vendor/{} is not embedded, run vendor/fetch.sh and build swift2vue with `--features vendor`",
+                self.file_name
+            ),
+        ))
+    }
+}
+
+/// `<script type="importmap">` contents for the libraries with an ES module build
+pub fn import_map() -> String {
+    let imports = LIBRARIES
+        .iter()
+        .filter_map(|x| x.module)
+        .map(|(specifier, url)| (specifier.to_string(), serde_json::Value::from(url)))
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::json!({ "imports": imports }).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_import_map() {
+        let map: serde_json::Value = serde_json::from_str(&super::import_map()).unwrap();
+        assert!(map["imports"]["vue"].as_str().unwrap().contains("vue@3.4.27"));
+        assert!(map["imports"]["naive-ui"].is_string());
+        assert!(map["imports"]["coloris"].is_null());
+    }
+}
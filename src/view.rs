@@ -1,52 +1,70 @@
-use std::collections::HashMap;
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use id_tree::{InsertBehavior, NodeId, Tree, TreeBuilder};
+use indexmap::IndexMap;
 
-use crate::common;
+use crate::common::{self, expr::ExprFn, statement::StatementWriter};
+use crate::error::{CompileError, NodeExt, Result};
+use crate::mapping::MappedCode;
+use crate::sfc;
+use crate::project::SymbolTable;
 #[allow(unused)]
-use crate::utils::log_node;
+use crate::utils::{find_first_node, log_node};
 #[allow(unused)]
 use crate::{
     paser::StructMember,
     utils::{log_node_tree, prettify_xml},
 };
 
-#[derive(Debug)]
-pub struct ViewParseError {
-    pub message: String,
-    pub node_code: String,
-    pub row: usize,
-    pub col: usize,
-}
-
-impl ViewParseError {
-    pub fn new(message: String, node: &tree_sitter::Node, source: &String) -> Self {
-        let row = node.start_position().row;
-        let col = node.start_position().column;
-        let node_code = node.utf8_text(source.as_bytes()).unwrap().to_string();
-        Self {
-            message,
-            node_code,
-            row,
-            col,
-        }
-    }
-}
-
 #[derive(Debug)]
 struct ViewNode {
     tag: String,
-    modifier: HashMap<String, String>,
+    modifier: IndexMap<String, String>,
     str_content: Option<String>,
+    /// Where the view is created in the Swift source
+    position: Option<tree_sitter::Point>,
+    /// `.environmentObject(store)`, the type names and the objects provided to the view
+    environment: Vec<(String, String)>,
+    /// `.environment(\.locale, value)`, the keys and the values provided to the view
+    environment_values: Vec<(String, String)>,
 }
 
 impl ViewNode {
     fn new(tag: String) -> Self {
         Self {
             tag,
-            modifier: HashMap::new(),
+            modifier: IndexMap::new(),
             str_content: None,
+            position: None,
+            environment: Vec::new(),
+            environment_values: Vec::new(),
+        }
+    }
+
+    /// `SwiftCommon` provides the environment objects and values, it's wrapped around the view
+    /// so the view itself can inject them
+    fn environment_wrapper(&self) -> Option<(String, String)> {
+        let object_literal = |entries: &[(String, String)]| {
+            let entries = entries.iter().map(|(key, value)| format!("{}: {}", key, value));
+            format!("{{ {} }}", entries.collect::<Vec<_>>().join(", "))
+        };
+
+        let mut attrs = Vec::new();
+        if !self.environment.is_empty() {
+            attrs.push(format!("v-bind:environmentObjects=\"{}\"", object_literal(&self.environment)));
         }
+        if !self.environment_values.is_empty() {
+            attrs.push(format!("v-bind:environmentValues=\"{}\"", object_literal(&self.environment_values)));
+        }
+        if attrs.is_empty() {
+            return None;
+        }
+
+        Some((format!("<SwiftCommon {}>", attrs.join(" ")), "</SwiftCommon>".to_string()))
     }
 
     fn attr_str(&self) -> String {
@@ -68,8 +86,18 @@ impl ViewNode {
     }
 }
 
+/// An init parameter of a view, the parent passes it in as a Vue prop
+struct Prop<'a> {
+    name: String,
+    /// The `type_annotation` node
+    ty: Option<tree_sitter::Node<'a>>,
+    /// The value of `var rank = 1`, the prop is optional then
+    default: Option<tree_sitter::Node<'a>>,
+}
+
 pub struct ViewParser<'a> {
-    source: String,
+    source: Rc<str>,
+    symbols: Rc<SymbolTable>,
 
     struct_info: crate::paser::StructInfo<'a>,
     view_tree: Tree<ViewNode>,
@@ -84,12 +112,27 @@ pub struct ViewParser<'a> {
     id_to_tree_id: HashMap<usize, NodeId>,
 
     parent_node_id: Option<NodeId>,
+
+    /// Emit TypeScript in [`Self::generate_sfc_code`]
+    typescript: bool,
+
+    /// Parameters of the function being written, they hide the members of the same name
+    shadowed: RefCell<Vec<String>>,
+    /// Parameters of the template closure being written that have the name of a member,
+    /// they are renamed with a `_` suffix so `self.name` is still the member
+    renamed: RefCell<Vec<String>>,
+    /// The names bound by the previous `let` of `if let a = x, let b = a.y` in the template and their values,
+    /// they are not declared yet in the `v-if`
+    aliases: RefCell<Vec<(String, String)>>,
+    /// The names of [`Self::props`], looked up for every member
+    prop_names: OnceCell<HashSet<String>>,
 }
 
 impl<'a> ViewParser<'a> {
-    pub fn from_struct(st: crate::paser::StructInfo<'a>, source: String) -> Self {
+    pub fn from_struct(st: crate::paser::StructInfo<'a>, symbols: Rc<SymbolTable>) -> Self {
         Self {
-            source,
+            source: st.source.clone(),
+            symbols,
             view_tree: TreeBuilder::new().build(),
             struct_info: st,
             in_call_expression: 0,
@@ -98,74 +141,68 @@ impl<'a> ViewParser<'a> {
             ignore_nodes: vec![],
             id_to_tree_id: HashMap::new(),
             navigation_component_node_id: None,
+            typescript: false,
+            shadowed: RefCell::new(Vec::new()),
+            renamed: RefCell::new(Vec::new()),
+            aliases: RefCell::new(Vec::new()),
+            prop_names: OnceCell::new(),
         }
     }
 }
 
+/// The `statements` and the parameters of a view builder, with the source of the file it's declared in
+type ViewBuilder<'a> = (tree_sitter::Node<'a>, Vec<String>, Rc<str>);
+
+/// SwiftUI enums the runtime environment keeps as the names of their cases
+const ENVIRONMENT_ENUMS: [&str; 3] = ["ColorScheme", "EditMode", "UserInterfaceSizeClass"];
+/// Environment keys of [`ENVIRONMENT_ENUMS`] values
+const ENVIRONMENT_ENUM_KEYS: [&str; 4] = ["colorScheme", "editMode", "horizontalSizeClass", "verticalSizeClass"];
+
 impl<'a> ViewParser<'a> {
+    pub fn generate_template(&mut self) -> Result<String> {
+        Ok(self.generate_template_mapped()?.code)
+    }
 
-    pub fn generate_template(&mut self) -> String {
+    /// Same as [`Self::generate_template`], each tag is mapped to the view it's generated from
+    fn generate_template_mapped(&mut self) -> Result<MappedCode> {
         if self.struct_info.inheritance != Some("View".to_string()) {
-            return "".to_string();
+            return Ok(MappedCode::new());
         }
 
         if let Some(StructMember::Property {
-            node: body,
-            modifier: _,
+            node: body, ..
         }) = self.struct_info.members.get("body")
         {
             let mut cursor = body.walk();
-            self.handle_struct(&mut cursor);
+            self.handle_struct(&mut cursor)?;
         }
 
-        let mut fmt_tree = String::new();
-        self.view_tree.write_formatted(&mut fmt_tree).unwrap();
-        println!("{}", fmt_tree);
+        Ok(self.generate_code_from_tree())
+    }
 
-        return self.generate_code_from_tree();
+    /// The view tree built by [`Self::generate_template`], one node per line
+    pub fn view_tree_text(&self) -> String {
+        let mut fmt_tree = String::new();
+        // writing to a String doesn't fail
+        let _ = self.view_tree.write_formatted(&mut fmt_tree);
+        fmt_tree
     }
 
-    /// 处理函数调用的转换
-    fn handle_fn(&self, node: &tree_sitter::Node) -> String {
-        let mut code = String::new();
-        assert_eq!(node.kind(), "statements");
+    /// The statements of a function body, each with the position it starts at
+    fn handle_fn(&self, node: &tree_sitter::Node) -> Result<Vec<(String, tree_sitter::Point)>> {
+        let source = &*self.source;
+        let mut statements = Vec::new();
+        node.expect_kind("statements", source)?;
 
         for i in 0..node.child_count() {
-            let child = node.child(i).unwrap();
-            let child_code = child.utf8_text(self.source.as_bytes()).unwrap();
-
-            match child.kind() {
-                "assignment" => {
-                    let target = child.child(0).unwrap();
-                    let target = target.utf8_text(self.source.as_bytes()).unwrap();
-                    if let Some(StructMember::Property { node: _, modifier }) =
-                        self.struct_info.members.get(target)
-                    {
-                        // 处理 vue3 ref
-                        if modifier == &Some("State".to_string()) {
-                            let target = format!("{}.value", target);
-                            let op = child.child(1).unwrap();
-                            let op = op.utf8_text(self.source.as_bytes()).unwrap();
-                            let value = child.child(2).unwrap();
-                            let value = value.utf8_text(self.source.as_bytes()).unwrap();
-
-                            code.push_str(format!("{} {op} {};\n", target, value).as_str());
-                        }
-                    } else {
-                        code.push_str(child_code);
-                        code.push_str("\n");
-                    }
-                }
-                _ => {
-                    // TODO: avoid hardcode
-                    // [Item(title: "A"), Item(title: "B")] --> [{ title: "A" }, { title: "B" }]
-                    // using regex
-                    let regex = regex::Regex::new(r#"(\w+)\(([^()]+:[^)]+)\)"#).unwrap();
-                    let child_code = regex.replace_all(&child_code, r#"{ $2 }"#).to_string();
+            let child = node.child_at(i, source)?;
+            if child.kind().ends_with("comment") {
+                continue;
+            }
+            let code = self.statement(&child, 0)?;
 
-                    code.push_str(&child_code);
-                    code.push_str("\n");
-                }
+            if !code.is_empty() {
+                statements.push((code.trim_end().to_string(), child.start_position()));
             }
         }
 
@@ -173,32 +210,217 @@ impl<'a> ViewParser<'a> {
         // handle swift interpolated_expression like "Hello, \(name)!"
         // try handle this using regex
 
-        code.to_string()
+        Ok(statements)
+    }
+
+    /// The enum a member is declared as, or initialized with, e.g. `LoadState` of `var state: LoadState = .idle`
+    fn member_enum_type(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.member_type(name)?.filter(|x| self.symbols.enums.contains(x)))
+    }
+
+    /// The Swift type of the member `name`, from its annotation or its initial value, e.g. `Counter` of `Counter()`
+    fn member_type(&self, name: &str) -> Result<Option<String>> {
+        let source = &*self.source;
+        let ty = match self.struct_info.members.get(name) {
+            Some(StructMember::Property { ty: Some(ty), .. }) | Some(StructMember::Stored { ty, .. }) => {
+                ty.text(source)?.trim_start_matches(':').trim().trim_end_matches(['?', '!']).to_string()
+            }
+            Some(StructMember::Property { node, .. }) => {
+                let callee = if node.kind() == "call_expression" { node.child_at(0, source)? } else { *node };
+                let value = callee.text(source)?;
+                value.split('.').next().unwrap_or_default().to_string()
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(ty))
+    }
+
+    /// The Swift type of `counter` or `self.counter` of a member, see [`Self::member_type`]
+    fn type_of(&self, node: &tree_sitter::Node) -> Result<Option<String>> {
+        let source = &*self.source;
+        let name = match node.kind() {
+            "simple_identifier" if !self.shadowed.borrow().iter().any(|x| x == node.text(source).unwrap_or_default()) => {
+                node.text(source)?
+            }
+            "navigation_expression" if node.child(0).map(|x| x.kind()) == Some("self_expression") => {
+                node.child_at(1, source)?.child_at(1, source)?.text(source)?
+            }
+            _ => return Ok(None),
+        };
+        self.member_type(name)
+    }
+
+
+    fn is_state(&self, name: &str) -> bool {
+        matches!(
+            self.struct_info.members.get(name),
+            Some(StructMember::Property { modifier, .. }) if modifier.as_deref() == Some("State")
+        )
+    }
+
+    /// `colorScheme` of `@Environment(\.colorScheme) var scheme`
+    fn environment_key(&self, name: &str) -> Option<&str> {
+        match self.struct_info.members.get(name) {
+            Some(StructMember::Environment { key, .. }) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// `'dark'` of `.dark` compared with `colorScheme`, the environment enums are strings in the runtime
+    fn environment_case(&self, node: &tree_sitter::Node) -> Result<Option<String>> {
+        let source = &*self.source;
+        let case = |node: &tree_sitter::Node| -> Result<Option<String>> {
+            if node.kind() != "prefix_expression" || node.child(0).map(|x| x.kind()) != Some(".") {
+                return Ok(None);
+            }
+            Ok(Some(format!("'{}'", node.child_at(1, source)?.text(source)?)))
+        };
+
+        let Some(parent) = node.parent().filter(|x| x.kind() == "equality_expression") else {
+            return Ok(None);
+        };
+        let mut cursor = parent.walk();
+        let other = parent.named_children(&mut cursor).find(|x| x.id() != node.id());
+        let Some(other) = other.filter(|x| x.kind() == "simple_identifier") else {
+            return Ok(None);
+        };
+
+        let name = other.text(source)?;
+        let environment_enum = match self.struct_info.members.get(name) {
+            Some(StructMember::Environment { key, .. }) => ENVIRONMENT_ENUM_KEYS.contains(&key.as_str()),
+            Some(StructMember::Property { ty: Some(ty), .. }) | Some(StructMember::Stored { ty, .. }) => {
+                ENVIRONMENT_ENUMS.contains(&ty.text(source)?.trim_start_matches(':').trim())
+            }
+            _ => false,
+        };
+        if environment_enum {
+            case(node)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The value `.environment(\.key, value)` provides, evaluated in the template
+    fn environment_value(&self, node: &tree_sitter::Node) -> Result<String> {
+        let source = &*self.source;
+        let code = node.text(source)?;
+
+        // the binding is read only below the view
+        if let Some(name) = code.strip_prefix('$') {
+            return Ok(name.to_string());
+        }
+        if node.kind() == "prefix_expression" && node.child(0).map(|x| x.kind()) == Some(".") {
+            return Ok(format!("'{}'", node.child_at(1, source)?.text(source)?));
+        }
+        // `Locale(identifier: "zh_CN")` is the language tag `navigator.language` uses
+        if code.starts_with("Locale(") {
+            if let Some(identifier) = find_first_node(*node, "line_str_text") {
+                return Ok(format!("'{}'", identifier.text(source)?.replace('_', "-")));
+            }
+        }
+
+        self.template_expr(node)
+    }
+
+    /// Models a member expression may construct, nested ones and the ones declared at top level
+    fn model_names(&self) -> Vec<String> {
+        let mut names = self.struct_info.sub_names();
+        names.extend(self.symbols.models.iter().cloned());
+        names
+    }
+
+    /// Types the TypeScript output can refer to by name
+    fn known_types(&self) -> Vec<String> {
+        let mut names = self.struct_info.sub_names();
+        names.extend(self.symbols.type_names());
+        names
+    }
+
+    /// `Topping.cookies` of `var topping: Topping = .cookies`, if `Topping` is an enum
+    fn implicit_member(&self, node: &tree_sitter::Node, ty: Option<&tree_sitter::Node>) -> Result<Option<String>> {
+        let source = &*self.source;
+        if node.kind() != "prefix_expression" || node.child(0).map(|x| x.kind()) != Some(".") {
+            return Ok(None);
+        }
+        let Some(ty) = ty else {
+            return Ok(None);
+        };
+
+        let ty = ty.text(source)?.trim_start_matches(':').trim();
+        if ENVIRONMENT_ENUMS.contains(&ty) {
+            return Ok(Some(format!("'{}'", node.child_at(1, source)?.text(source)?)));
+        }
+        if !self.symbols.enums.contains(ty) {
+            return Ok(None);
+        }
+        Ok(Some(format!("{}.{}", ty, node.child_at(1, source)?.text(source)?)))
+    }
+
+    /// User views used in the template, except self
+    fn used_views(&self) -> Vec<String> {
+        self.used_tags(|tag| self.symbols.is_view(tag))
+    }
+
+    /// Builtin and user views used in the template, except self
+    pub fn used_components(&self, builtin_views: &[String]) -> Vec<String> {
+        self.used_tags(|tag| self.symbols.is_view(tag) || builtin_views.iter().any(|x| x == tag))
+    }
+
+    fn used_tags(&self, filter: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut views = Vec::new();
+
+        if let Some(root_id) = self.view_tree.root_node_id() {
+            if let Ok(nodes) = self.view_tree.traverse_pre_order(root_id) {
+                for node in nodes {
+                    let tag = &node.data().tag;
+                    if filter(tag) && tag != &self.struct_info.name && !views.contains(tag) {
+                        views.push(tag.clone());
+                    }
+
+                    let wrapper = "SwiftCommon".to_string();
+                    if node.data().environment_wrapper().is_some() && filter(&wrapper) && !views.contains(&wrapper) {
+                        views.push(wrapper);
+                    }
+                }
+            }
+        }
+
+        views.sort();
+        views
     }
 
     /// 处理属性 = 符号的右边
-    fn handle_member_expression(&self, node: &tree_sitter::Node) -> String {
+    fn handle_member_expression(&self, node: &tree_sitter::Node, ty: Option<&tree_sitter::Node>) -> Result<MappedCode> {
         // TODO: avoid hardcode
         if node.kind() == "array_literal" {
             // TODO: 根据类型标注获得前缀表达式 context
-            let names = self.struct_info.sub_names();
-            return common::array::array2js_call_with_obj_context(node, &self.source, "".to_string(), names);
+            let names = self.model_names();
+            return common::array::array2mapped(node, &self.source, "".to_string(), names);
         }
 
-        if node.kind() == "call_expression" {
-            let names = self.struct_info.sub_names();
-            return common::object::callexp2object_with_context(node, &self.source, names);
+        // `{ ... }()` isn't a model
+        if node.kind() == "call_expression" && node.child(0).map(|x| x.kind()) != Some("lambda_literal") {
+            let names = self.model_names();
+            return common::object::callexp2mapped(node, &self.source, names);
         }
 
-        return node.utf8_text(self.source.as_bytes()).unwrap().to_string();
-    }
-
-    fn generate_setup_code(&self, runtimes: Vec<String>) -> Result<String, ViewParseError> {
-        if self.struct_info.inheritance != Some("View".to_string()) {
-            return Ok("".to_string());
+        let mut code = MappedCode::new();
+        code.mark(node.start_position());
+        // `@State var user: User?` without a value is nil
+        if node.kind() == "type_annotation" {
+            code.push_str("undefined");
+            return Ok(code);
         }
+        match self.implicit_member(node, ty)? {
+            Some(member) => code.push_str(&member),
+            None => code.push_str(&self.expr(node)?),
+        }
+        Ok(code)
+    }
 
-        let mut setup_code = String::new();
+    /// `const` definitions of the members indented by `indent`, with the identifiers they declare
+    fn generate_setup_definitions(&self, indent: usize) -> Result<(MappedCode, Vec<String>)> {
+        let mut setup_code = MappedCode::new();
         let mut exported_identifier: Vec<String> = vec![];
 
         for (key, value) in self.struct_info.members.iter() {
@@ -206,110 +428,505 @@ impl<'a> ViewParser<'a> {
                 continue;
             }
 
-            let member_code = match value {
-                StructMember::Property { node, modifier } => {
-                    let var_name = key;
-                    exported_identifier.push(var_name.clone());
+            let source = self.struct_info.member_source(key);
+            let member_code = if Rc::ptr_eq(source, &self.source) {
+                self.setup_definition(key, value, indent, &mut exported_identifier)?
+            } else {
+                // declared by an extension in another file, the source map only covers the file of the view
+                let mut st = self.struct_info.clone();
+                st.source = source.clone();
+                let mut parser = ViewParser::from_struct(st, self.symbols.clone());
+                parser.typescript = self.typescript;
+                let code = parser.setup_definition(key, value, indent, &mut exported_identifier)?;
+                code.map(|x| MappedCode::from(x.code))
+            };
+            let Some(member_code) = member_code else {
+                continue;
+            };
+
+            // push indent
+            setup_code.push_str(format!("{:indent$}", "", indent = indent).as_str());
+            setup_code.push_mapped(member_code);
+            setup_code.push_str("\n");
+        }
 
-                    let ref_literal = ["line_string_literal", "integer_literal"];
+        Ok((setup_code.trim_end(), exported_identifier))
+    }
 
-                    if modifier == &Some("State".to_string()) {
-                        if ref_literal.contains(&node.kind()) {
-                            let var_code = node.utf8_text(self.source.as_bytes()).unwrap();
-                            format!(
-                                "const {var_name} = ref({var_code});",
-                                var_name = var_name,
-                                var_code = var_code
-                            )
-                        } else {
-                            let var_code = self.handle_member_expression(node);
-
-                            let directive = if node.kind() == "array_literal" {
-                                "reactive"
-                            } else {
-                                "ref"
-                            };
-
-                            format!(
-                                // TODO: when to use ref? v-model not work with reactive
-                                "const {var_name} = {directive}({var_code});",
-                                var_name = var_name,
-                                var_code = var_code
-                            )
-                        }
+    /// The `const` definition of the member `key`, `None` if it isn't defined in `setup()`.
+    /// The names it declares are pushed to `exported`
+    fn setup_definition(
+        &self,
+        key: &String,
+        value: &StructMember<'a>,
+        indent: usize,
+        exported: &mut Vec<String>,
+    ) -> Result<Option<MappedCode>> {
+        let source = &*self.source;
+        let mut member_code = MappedCode::new();
+        // set by the parent, see `Self::props`
+        if matches!(value, StructMember::Property { .. }) && self.is_prop(key)? {
+            return Ok(None);
+        }
+        match value {
+            StructMember::Property { node, modifier, ty } => {
+                let var_name = key;
+                exported.push(var_name.clone());
+
+                let ref_literal = ["line_string_literal", "integer_literal"];
+                // `<T>` of `ref<T>()`, `: T` of a constant, only with a type annotation
+                let ts_type = match ty {
+                    Some(ty) if self.typescript => {
+                        Some(common::types::swift_type2ts(ty, source, &self.known_types())?)
+                    }
+                    _ => None,
+                };
+
+                if let Some(declaration) = node.parent() {
+                    member_code.mark(declaration.start_position());
+                }
+
+                if modifier == &Some("State".to_string()) {
+                    let generic = ts_type.map(|x| format!("<{}>", x)).unwrap_or_default();
+
+                    if ref_literal.contains(&node.kind()) {
+                        member_code.push_str(&format!("const {var_name} = ref{generic}("));
+                        member_code.mark(node.start_position());
+                        member_code.push_str(node.text(source)?);
+                        member_code.push_str(");");
                     } else {
-                        if node.kind() == "call_expression" {
-                            if let Some(first_child) = node.child(0) {
-                                if first_child.kind() == "lambda_literal" {
-                                    let parent_node = node.parent().unwrap();
-                                    let error = ViewParseError::new(
-                                        "目前不支持闭包属性".to_string(),
-                                        &parent_node,
-                                        &self.source,
-                                    );
-                                    return Err(error);
-                                }
-                            }
+                        let var_code = self.handle_member_expression(node, ty.as_ref())?;
+
+                        // a ref even for arrays, it's read and written through `.value` like the others
+                        member_code.push_str(&format!("const {var_name} = ref{generic}("));
+                        member_code.push_mapped(var_code);
+                        member_code.push_str(");");
+                    }
+                } else {
+                    let annotation = ts_type.map(|x| format!(": {}", x)).unwrap_or_default();
+                    member_code.push_str(&format!("const {var_name}{annotation} = "));
+                    member_code.mark(node.start_position());
+                    match self.implicit_member(node, ty.as_ref())? {
+                        Some(member) => member_code.push_str(&member),
+                        // a closure property is an arrow function, `{ ... }()` is called right away
+                        None if node.kind() == "lambda_literal" => member_code.push_str(&self.closure(node, Some(indent))?),
+                        None if node.kind() == "call_expression"
+                            && node.child(0).is_some_and(|x| x.kind() == "lambda_literal")
+                            && node.child(1).map(|x| x.text(source)).transpose()? == Some("()") =>
+                        {
+                            let closure = node.child_at(0, source)?;
+                            member_code.push_str(&format!("{}()", self.closure(&closure, Some(indent))?));
                         }
+                        None => member_code.push_str(&self.expr(node)?),
+                    }
+                    member_code.push_str(";");
+                }
+            }
+            // provided by `.environmentObject()` of an ancestor, see `ViewNode::environment_wrapper`
+            StructMember::Stored { ty, modifier } if modifier.as_deref() == Some("EnvironmentObject") => {
+                exported.push(key.clone());
+                if let Some(declaration) = ty.parent() {
+                    member_code.mark(declaration.start_position());
+                }
 
-                        let var_code = node.utf8_text(self.source.as_bytes()).unwrap();
-                        format!(
-                            "const {var_name} = {var_code};",
-                            var_name = var_name,
-                            var_code = var_code
-                        )
+                let name = ty.text(source)?.trim_start_matches(':').trim();
+                let cast = if self.typescript {
+                    format!(" as {}", common::types::swift_type2ts(ty, source, &self.known_types())?)
+                } else {
+                    String::new()
+                };
+                member_code.push_str(&format!("const {key} = inject('{name}'){cast};"));
+            }
+            StructMember::Stored { .. } => return Ok(None),
+            // inlined in the template, see `Self::inline_view_builder`
+            StructMember::ViewBuilder { .. } => return Ok(None),
+            StructMember::Computed { node, ty } => {
+                exported.push(key.clone());
+                if let Some(declaration) = node.parent() {
+                    member_code.mark(declaration.start_position());
+                }
+
+                let generic = match ty {
+                    Some(ty) if self.typescript => {
+                        format!("<{}>", common::types::swift_type2ts(ty, source, &self.known_types())?)
                     }
+                    _ => String::new(),
+                };
+                let computed = self.computed2js(node, indent)?;
+                member_code.push_str(&format!("const {key} = computed{generic}({computed});"));
+            }
+            // a ref, `.environment()` of an ancestor or the default of the browser
+            StructMember::Environment { key: env_key, node } => {
+                exported.push(key.clone());
+                member_code.mark(node.start_position());
+                member_code.push_str(&format!("const {key} = SwiftEnvironment.read('{env_key}');"));
+            }
+            StructMember::Function(node) => {
+                // log_node_tree(&node, 0);
+                let fn_name = key;
+                exported.push(fn_name.clone());
+
+                if let Some(declaration) = node.parent().and_then(|x| x.parent()) {
+                    member_code.mark(declaration.start_position());
                 }
-                StructMember::Function(node) => {
-                    // log_node_tree(&node, 0);
-                    let fn_name = key;
-                    exported_identifier.push(fn_name.clone());
-
-                    let fn_code = self.handle_fn(node);
-
-                    let code_with_indent = fn_code
-                        .split("\n")
-                        .map(|x| x.trim())
-                        .filter(|x| x.len() > 0)
-                        .map(|line| format!("{:indent$}{}", "", line, indent = 12))
-                        .collect::<Vec<String>>()
-                        .join("\n");
-
-                    format!(
-                        "const {fn_name} = () => {{\n{code_with_indent}\n{:indent$}}};",
-                        "",
-                        indent = 8
-                    )
-                    .trim_end()
-                    .to_string()
+                let (params, names) = self.function_params(node)?;
+                member_code.push_str(&format!("const {fn_name} = ({params}) => {{\n"));
+
+                *self.shadowed.borrow_mut() = names;
+                let statements = self.handle_fn(node);
+                self.shadowed.borrow_mut().clear();
+
+                let mut first_line = true;
+                for (statement, point) in statements? {
+                    for (i, line) in statement.split('\n').enumerate() {
+                        let trimmed = line.trim_end();
+                        if trimmed.is_empty() {
+                            continue;
+                        }
+
+                        if !first_line {
+                            member_code.push_str("\n");
+                        }
+                        first_line = false;
+
+                        member_code.push_str(&format!("{:indent$}", "", indent = indent + 4));
+                        // the lines after the first one are indented relative to the statement
+                        let column = if i == 0 {
+                            point.column
+                        } else {
+                            point.column + line.len() - line.trim_start().len()
+                        };
+                        member_code.mark(tree_sitter::Point {
+                            row: point.row + i,
+                            column,
+                        });
+                        member_code.push_str(trimmed);
+                    }
                 }
-            };
 
-            // push indent
-            setup_code.push_str(format!("{:indent$}", "", indent = 8).as_str());
-            setup_code.push_str(&member_code);
-            setup_code.push_str("\n");
+                member_code.push_str(&format!("\n{:indent$}}};", "", indent = indent));
+            }
         }
 
+        Ok(Some(member_code))
+    }
+
+    fn generate_setup_code(&self, runtimes: Vec<String>) -> Result<MappedCode> {
+        if self.struct_info.inheritance != Some("View".to_string()) {
+            return Ok(MappedCode::new());
+        }
+
+        let (defs, mut exported_identifier) = self.generate_setup_definitions(8)?;
         exported_identifier.extend(runtimes);
+        exported_identifier.extend(self.symbols.shared_names());
 
-        let defs = setup_code.trim_end().to_string();
         let exported = exported_identifier.join(", ");
         let exported_code = format!("{:indent$}return {{ {} }};", "", exported, indent = 8);
 
-        Ok(format!("{}\n{}", defs, exported_code))
+        let mut code = defs;
+        code.push_str(&format!("\n{}", exported_code));
+        Ok(code)
     }
 
-    fn generate_sub_struct(&self) -> String {
-        let mut out = String::new();
+    fn generate_sub_struct(&self) -> Result<MappedCode> {
+        let mut out = MappedCode::new();
 
         for sub in self.struct_info.sub.iter() {
-            let code = common::model::date_model2js_fn(&sub.borrow().node, &self.source);
+            let sub = sub.borrow();
+            let node = sub.node;
+            // an extension in another file may declare it
+            let code = if self.typescript {
+                common::model::date_model2ts_fn(&node, &sub.source, &[], &self.known_types(), &self.symbols, false)?
+            } else {
+                common::model::date_model2js_fn(&node, &sub.source, &[], &self.symbols)?
+            };
+            out.mark(node.start_position());
             out.push_str(&code);
             out.push_str("\n");
         }
 
-        out.trim().to_string()
+        Ok(out.trim())
+    }
+
+    /// The init parameters of the view: stored properties without a value, the ones the environment provides
+    /// are left out, and the `var`s with one which aren't private
+    fn props(&self) -> Result<Vec<Prop<'a>>> {
+        let source = &*self.source;
+        let mut props = Vec::new();
+
+        for (name, member) in self.struct_info.members.iter() {
+            // `static var` belongs to the type
+            let declaration = match member {
+                StructMember::Stored { ty: node, .. } | StructMember::Property { node, .. } => node.parent(),
+                _ => None,
+            };
+            if let Some(modifiers) = declaration.and_then(|x| x.named_child(0)).filter(|x| x.kind() == "modifiers") {
+                if modifiers.text(source)?.split_whitespace().any(|x| x == "static") {
+                    continue;
+                }
+            }
+            match member {
+                // the view owns its state, the parent can't pass it in
+                StructMember::Stored { ty, modifier } if matches!(modifier.as_deref(), Some("State") | Some("StateObject")) => {
+                    return Err(CompileError::unsupported("暂不支持没有初始值的 @State 属性", ty, source));
+                }
+                StructMember::Stored { ty, modifier }
+                    if !matches!(modifier.as_deref(), Some("Environment") | Some("EnvironmentObject")) =>
+                {
+                    props.push(Prop {
+                        name: name.clone(),
+                        ty: Some(*ty),
+                        default: None,
+                    });
+                }
+                StructMember::Property { node, modifier: None, ty } => {
+                    // computed properties like `body` are members as well
+                    let Some(declaration) = node.parent().filter(|x| x.kind() == "property_declaration") else {
+                        continue;
+                    };
+
+                    let mut is_var = false;
+                    let mut private = false;
+                    for i in 0..declaration.child_count() {
+                        let child = declaration.child_at(i, source)?;
+                        match child.kind() {
+                            "value_binding_pattern" => is_var = child.text(source)? == "var",
+                            "modifiers" => private = child.text(source)?.contains("private"),
+                            _ => {}
+                        }
+                    }
+
+                    if is_var && !private {
+                        props.push(Prop {
+                            name: name.clone(),
+                            ty: *ty,
+                            default: Some(*node),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(props)
+    }
+
+    fn is_prop(&self, name: &str) -> Result<bool> {
+        if self.prop_names.get().is_none() {
+            let names = self.props()?.into_iter().map(|x| x.name).collect();
+            let _ = self.prop_names.set(names);
+        }
+        Ok(self.prop_names.get().is_some_and(|x| x.contains(name)))
+    }
+
+    fn is_computed(&self, name: &str) -> bool {
+        matches!(self.struct_info.members.get(name), Some(StructMember::Computed { .. }))
+    }
+
+    /// The argument of `computed()`, a getter function or `{ get, set }` if the property has a setter.
+    /// The closing line is indented by `indent`
+    fn computed2js(&self, node: &tree_sitter::Node, indent: usize) -> Result<String> {
+        let accessors = common::global::accessors(node, &self.source)?;
+        let function = |statements: Option<tree_sitter::Node>, indent: usize| -> Result<String> {
+            let Some(statements) = statements else {
+                return Ok("{}".to_string());
+            };
+            match common::global::implicit_return(&statements) {
+                Some(expr) => self.expr(&expr),
+                None => Ok(format!("{{\n{}{:indent$}}}", self.statements(&statements, indent + 4)?, "", indent = indent)),
+            }
+        };
+
+        let getter = format!("() => {}", function(accessors.getter, indent)?);
+        let Some((value, setter)) = accessors.setter else {
+            return Ok(getter);
+        };
+
+        let getter = format!("() => {}", function(accessors.getter, indent + 4)?);
+        let setter = match setter {
+            Some(setter) => format!("{{\n{}{:indent$}}}", self.statements(&setter, indent + 8)?, "", indent = indent + 4),
+            None => "{}".to_string(),
+        };
+        Ok(format!(
+            "{{\n{:inner$}get: {},\n{:inner$}set: ({}) => {},\n{:indent$}}}",
+            "",
+            getter,
+            "",
+            value,
+            setter,
+            "",
+            inner = indent + 4,
+            indent = indent
+        ))
+    }
+
+    /// `offset, to` of `func move(at offset: Int, to: Int)`, the statements are the body of the function.
+    /// Typed in TypeScript, with the default values, and the names of the parameters
+    fn function_params(&self, statements: &tree_sitter::Node) -> Result<(String, Vec<String>)> {
+        let source = &*self.source;
+        let Some(declaration) = statements.parent().and_then(|x| x.parent()) else {
+            return Ok((String::new(), Vec::new()));
+        };
+
+        let mut params: Vec<String> = Vec::new();
+        let mut names = Vec::new();
+        let mut cursor = declaration.walk();
+        for child in declaration.children(&mut cursor) {
+            match child.kind() {
+                "parameter" => {
+                    let mut cursor = child.walk();
+                    let children = child.named_children(&mut cursor).collect::<Vec<_>>();
+                    let Some(name) = children.iter().rfind(|x| x.kind() == "simple_identifier") else {
+                        continue;
+                    };
+                    let ty = children.iter().find(|x| x.kind() != "simple_identifier");
+                    names.push(name.text(source)?.to_string());
+                    match ty {
+                        Some(ty) if self.typescript => params.push(format!(
+                            "{}: {}",
+                            name.text(source)?,
+                            common::types::swift_type2ts(ty, source, &self.known_types())?
+                        )),
+                        _ => params.push(name.text(source)?.to_string()),
+                    }
+                }
+                _ if child.prev_sibling().map(|x| x.kind() == "=").unwrap_or(false) => {
+                    if let Some(param) = params.last_mut() {
+                        param.push_str(&format!(" = {}", self.expr(&child)?));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok((params.join(", "), names))
+    }
+
+    /// `count.value` of a ref or `props.title` of a prop, `None` for the other names
+    fn member_expr(&self, name: &str) -> Result<Option<String>> {
+        Ok(if self.is_state(name) || self.is_computed(name) || self.environment_key(name).is_some() {
+            Some(format!("{}.value", name))
+        } else if self.is_prop(name)? {
+            Some(format!("props.{}", name))
+        } else {
+            None
+        })
+    }
+
+    fn is_binding(&self, name: &str) -> bool {
+        self.struct_info.bindings().iter().any(|x| x == name)
+    }
+
+    /// `update:isOn` of `@Binding var isOn: Bool`, the parent updates its state with `v-model:isOn`
+    fn emits(&self) -> Vec<String> {
+        self.struct_info.bindings().iter().map(|x| format!("update:{}", x)).collect()
+    }
+
+    /// `defineEmits` of [`Self::emits`], typed with the types of the bindings in TypeScript
+    fn generate_emits(&self) -> Result<String> {
+        let bindings = self.struct_info.bindings();
+        if bindings.is_empty() {
+            return Ok(String::new());
+        }
+
+        if !self.typescript {
+            let events = self.emits().iter().map(|x| format!("'{}'", x)).collect::<Vec<_>>();
+            return Ok(format!("defineEmits([{}])", events.join(", ")));
+        }
+
+        let mut events = Vec::new();
+        for name in bindings {
+            let ty = match self.struct_info.members.get(&name) {
+                Some(StructMember::Stored { ty, .. }) => common::types::swift_type2ts(ty, &self.source, &self.known_types())?,
+                _ => "any".to_string(),
+            };
+            events.push(format!("  'update:{}': [value: {}]\n", name, ty));
+        }
+        Ok(format!("defineEmits<{{\n{}}}>()", events.concat()))
+    }
+
+    /// `v-model:isOn="isOn"` can't write to the prop of a `@Binding`, the update is passed on to the parent instead
+    fn forward_binding(&self, key: String, value: String) -> Vec<(String, String)> {
+        match key.strip_prefix("v-model:") {
+            Some(arg) if self.is_binding(&value) => vec![
+                (format!("v-bind:{}", arg), value.clone()),
+                (format!("v-on:update:{}", arg), format!("$emit('update:{}', $event)", value)),
+            ],
+            _ => vec![(key, value)],
+        }
+    }
+
+    /// The default value of a prop, objects are created by a factory so every instance gets its own
+    fn prop_default(&self, prop: &Prop) -> Result<Option<String>> {
+        let Some(node) = prop.default else {
+            return Ok(None);
+        };
+
+        let primitive = node.kind().ends_with("_literal") && !["array_literal", "dictionary_literal"].contains(&node.kind());
+        if primitive {
+            return Ok(Some(node.text(&self.source)?.to_string()));
+        }
+
+        let code = self.handle_member_expression(&node, prop.ty.as_ref())?;
+        Ok(Some(format!("() => {}", code.code)))
+    }
+
+    /// The runtime declaration of the props, the `props` option of the component or the argument of `defineProps`
+    fn props_declaration(&self) -> Result<Option<String>> {
+        let props = self.props()?;
+        if props.is_empty() {
+            return Ok(None);
+        }
+
+        if props.iter().all(|x| x.default.is_none()) {
+            let names = props.iter().map(|x| format!("'{}'", x.name)).collect::<Vec<_>>();
+            return Ok(Some(format!("[{}]", names.join(", "))));
+        }
+
+        let mut fields = Vec::new();
+        for prop in &props {
+            match self.prop_default(prop)? {
+                Some(default) => fields.push(format!("{}: {{ default: {} }}", prop.name, default)),
+                None => fields.push(format!("{}: null", prop.name)),
+            }
+        }
+        Ok(Some(format!("{{ {} }}", fields.join(", "))))
+    }
+
+    /// `defineProps` of [`Self::props`], with `withDefaults` for the TypeScript ones with a value
+    fn generate_props(&self) -> Result<String> {
+        let props = self.props()?;
+        if props.is_empty() {
+            return Ok(String::new());
+        }
+
+        if !self.typescript {
+            let declaration = self.props_declaration()?.unwrap_or_default();
+            return Ok(format!("defineProps({})", declaration));
+        }
+
+        let mut fields = Vec::new();
+        let mut defaults = Vec::new();
+        for prop in &props {
+            let ty = match (&prop.ty, &prop.default) {
+                (Some(ty), _) => common::types::swift_type2ts(ty, &self.source, &self.known_types())?,
+                (None, Some(value)) => common::types::infer_ts_type(value, &self.source, &self.known_types())?,
+                (None, None) => "any".to_string(),
+            };
+            let optional = if ty.ends_with("| undefined") || prop.default.is_some() { "?" } else { "" };
+            fields.push(format!("  {}{}: {}\n", prop.name, optional, ty));
+
+            if let Some(default) = self.prop_default(prop)? {
+                defaults.push(format!("  {}: {},\n", prop.name, default));
+            }
+        }
+
+        let define = format!("defineProps<{{\n{}}}>()", fields.concat());
+        if defaults.is_empty() {
+            Ok(define)
+        } else {
+            Ok(format!("withDefaults({}, {{\n{}}})", define, defaults.concat()))
+        }
     }
 
     /// It's usually used for error component
@@ -331,13 +948,13 @@ export default {{
         ).trim().to_string()
     }
 
-    pub fn generate_component_code(&mut self, builtin: Vec<String>, views: Vec<String>) -> Result<String, ViewParseError> {
+    pub fn generate_component_code(&mut self, runtimes: Vec<String>, builtin_views: Vec<String>) -> Result<MappedCode> {
         if self.struct_info.inheritance != Some("View".to_string()) {
-            return Ok("".to_string());
+            return Ok(MappedCode::new());
         }
 
-        let template_code = self.generate_template().replace("\n", "");
-        let setup_code = self.generate_setup_code(builtin.clone())?;
+        let template_code = self.generate_template_mapped()?.strip_newlines();
+        let setup_code = self.generate_setup_code(runtimes.clone())?;
 
         // example: my-component.js
         // import { ref } from 'vue'
@@ -349,11 +966,18 @@ export default {{
         //   template: `<div>Count is: {{ count }}</div>`
         // }
 
-        let builtin_imports = builtin
+        let mut views = builtin_views;
+        views.extend(self.used_views());
+
+        let mut builtin_imports = runtimes
             .iter()
             .map(|name| format!("import {} from './{}.js'", name, name))
             .collect::<Vec<String>>()
             .join("\n");
+        if !self.symbols.shared_names().is_empty() {
+            let models = self.symbols.shared_names().join(", ");
+            builtin_imports.push_str(&format!("\nimport {{ {} }} from './shared.js'", models));
+        }
         let view_imports = views
             .iter()
             .map(|name| format!("import {} from './{}.js'", name, name))
@@ -361,48 +985,167 @@ export default {{
             .join("\n");
         let components = views.join(", ");
 
-        let sub_struct_code = self.generate_sub_struct();
+        let sub_struct_code = self.generate_sub_struct()?;
+
+        let mut code = MappedCode::from(format!("\n{builtin_imports}\n{view_imports}\n\n"));
+        code.push_mapped(sub_struct_code);
+        let (mut props, mut setup_args) = match self.props_declaration()? {
+            Some(props) => (format!("\n    props: {},", props), "props"),
+            None => (String::new(), ""),
+        };
+        let emits = self.emits();
+        if !emits.is_empty() {
+            let events = emits.iter().map(|x| format!("'{}'", x)).collect::<Vec<_>>();
+            props.push_str(&format!("\n    emits: [{}],", events.join(", ")));
+            setup_args = "props, { emit }";
+        }
+        code.push_str(&format!(
+            "\n\nexport default {{\n    components: {{\n        {components}\n    }},{props}\n    setup({setup_args}) {{\n"
+        ));
+        code.push_mapped(setup_code);
+        code.push_str("\n    },\n    template: ");
+        // the bundler maps the whole template literal from where it starts
+        if let Some((_, point)) = template_code.marks.first() {
+            code.mark(*point);
+        }
+        code.push_str("`");
+        code.push_mapped(template_code);
+        code.push_str("`\n}\n        ");
 
-        Ok(format!(
-            r#"
-{builtin_imports}
-{view_imports}
+        Ok(code.trim())
+    }
 
-{sub_struct_code}
+    /// It's usually used for error component
+    pub fn generate_empty_sfc(self, typescript: bool) -> String {
+        if self.struct_info.inheritance != Some("View".to_string()) {
+            return "".to_string();
+        }
 
-export default {{
-    components: {{
-        {components}
-    }},
-    setup() {{
-{setup_code}
-    }},
-    template: `{template_code}`
-}}
-        "#
-        )
-        .trim()
-        .to_string())
+        sfc::render_sfc("", "  <div></div>", true, typescript)
+    }
+
+    /// A Single-File Component using `<script setup>`, it only imports what it uses.
+    /// With `typescript`, the script is `lang="ts"` and typed from the Swift declarations
+    pub fn generate_sfc_code(
+        &mut self,
+        runtimes: Vec<String>,
+        builtin_views: Vec<String>,
+        typescript: bool,
+    ) -> Result<String> {
+        if self.struct_info.inheritance != Some("View".to_string()) {
+            return Ok("".to_string());
+        }
+        self.typescript = typescript;
+
+        let template_code = self.generate_indented_template(1)?;
+        let (defs, _) = self.generate_setup_definitions(0)?;
+        let sub_struct_code = self.generate_sub_struct()?;
+        let mut props = self.generate_props()?;
+        if sfc::uses_identifier(&defs.code, "props") {
+            props = format!("const props = {}", props);
+        }
+        let mut emits = self.generate_emits()?;
+        if sfc::uses_identifier(&defs.code, "emit") {
+            emits = format!("const emit = {}", emits);
+        }
+        if !emits.is_empty() {
+            props = format!("{}\n{}", props, emits);
+        }
+
+        let code = [sub_struct_code.code, props, defs.code]
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let used = |name: &str| sfc::uses_identifier(&code, name) || sfc::uses_identifier(&template_code, name);
+
+        let mut imports = Vec::new();
+        imports.extend(sfc::vue_import(&code));
+        for name in runtimes.iter().filter(|x| used(x)) {
+            imports.push(format!("import {} from './{}.js'", name, name));
+        }
+
+        let models = self
+            .symbols
+            .shared_names()
+            .into_iter()
+            .filter(|x| used(x))
+            .collect::<Vec<_>>();
+        if !models.is_empty() {
+            let shared = if typescript { "./shared" } else { "./shared.js" };
+            imports.push(format!("import {{ {} }} from '{}'", models.join(", "), shared));
+        }
+
+        for name in self.used_components(&builtin_views) {
+            imports.push(format!("import {} from './{}.vue'", name, name));
+        }
+
+        let script = format!("{}\n\n{}", imports.join("\n"), code);
+        Ok(sfc::render_sfc(script.trim(), &template_code, true, typescript))
     }
 
     /// generate html code from view tree
-    fn generate_code_from_tree(&self) -> String {
-        let mut code = String::new();
+    fn generate_code_from_tree(&self) -> MappedCode {
+        let mut code = MappedCode::new();
 
-        let root_id = self.view_tree.root_node_id().unwrap();
-        self.handle_view_tree_node(root_id, &mut code);
+        if let Some(root_id) = self.view_tree.root_node_id() {
+            self.handle_view_tree_node(root_id, &mut code, None);
+        }
 
         // prettify_xml(code)
         code
     }
 
-    fn handle_view_tree_node(&self, id: &NodeId, code: &mut String) {
-        let node = self.view_tree.get(id).unwrap();
+    /// Same as [`Self::generate_template`], one tag per line indented from `level`, for code read by humans
+    pub fn generate_indented_template(&mut self, level: usize) -> Result<String> {
+        self.generate_template()?;
+
+        let mut code = MappedCode::new();
+        if let Some(root_id) = self.view_tree.root_node_id() {
+            self.handle_view_tree_node(root_id, &mut code, Some(level));
+        }
+
+        Ok(code.code.trim_end().to_string())
+    }
+
+    fn handle_view_tree_node(&self, id: &NodeId, code: &mut MappedCode, level: Option<usize>) {
+        let Ok(node) = self.view_tree.get(id) else {
+            return;
+        };
+        let Some((open, close)) = node.data().environment_wrapper() else {
+            self.handle_view_node(id, code, level);
+            return;
+        };
+
+        match level {
+            Some(level) => {
+                let indent = |level: usize| format!("{:indent$}", "", indent = level * 2);
+                code.push_str(&format!("{}{}\n{}<template #child>\n", indent(level), open, indent(level + 1)));
+                self.handle_view_node(id, code, Some(level + 2));
+                code.push_str(&format!("{}</template>\n{}{}\n", indent(level + 1), indent(level), close));
+            }
+            None => {
+                code.push_str(&format!("{}<template #child>", open));
+                self.handle_view_node(id, code, None);
+                code.push_str(&format!("</template>{}\n", close));
+            }
+        }
+    }
+
+    fn handle_view_node(&self, id: &NodeId, code: &mut MappedCode, level: Option<usize>) {
+        let Ok(node) = self.view_tree.get(id) else {
+            return;
+        };
         let view_node = node.data();
+        let indent = format!("{:indent$}", "", indent = level.unwrap_or(0) * 2);
 
         // handle node pre
+        code.push_str(&indent);
+        if let Some(position) = view_node.position {
+            code.mark(position);
+        }
         code.push_str(&format!("<{}", view_node.tag));
-        if view_node.modifier.len() > 0 {
+        if !view_node.modifier.is_empty() {
             code.push_str(" ");
             code.push_str(&view_node.attr_str());
         }
@@ -410,9 +1153,18 @@ export default {{
 
         if let Some(str_child) = &view_node.str_content {
             code.push_str(str_child);
-        } else {
-            for child in self.view_tree.children_ids(id).unwrap() {
-                self.handle_view_tree_node(&child, code);
+        } else if let Ok(children) = self.view_tree.children_ids(id) {
+            let mut children = children.peekable();
+            if level.is_some() && children.peek().is_some() {
+                code.push_str("\n");
+                for child in children {
+                    self.handle_view_tree_node(child, code, level.map(|x| x + 1));
+                }
+                code.push_str(&indent);
+            } else {
+                for child in children {
+                    self.handle_view_tree_node(child, code, level);
+                }
             }
         }
 
@@ -421,83 +1173,564 @@ export default {{
     }
 
     fn insert_view_node(&mut self, view_node: ViewNode) -> NodeId {
-        let node_id: NodeId = match self.parent_node_id.as_ref() {
-            Some(node_id) => self.view_tree.insert(
-                id_tree::Node::new(view_node),
-                InsertBehavior::UnderNode(&node_id),
-            ),
-            None => self
-                .view_tree
-                .insert(id_tree::Node::new(view_node), InsertBehavior::AsRoot),
-        }
-        .unwrap();
+        let behavior = match self.parent_node_id.as_ref() {
+            Some(node_id) => InsertBehavior::UnderNode(node_id),
+            None => InsertBehavior::AsRoot,
+        };
+
+        // inserting under a node we got from this tree never fails
+        let node_id = self
+            .view_tree
+            .insert(id_tree::Node::new(view_node), behavior)
+            .expect("parent view node should exist in the view tree");
         self.parent_node_id = Some(node_id.clone());
         node_id
     }
 
     fn post_insert_view_node(&mut self) {
         if let Some(parent_node) = self.parent_node_id.as_ref() {
-            if let Some(parent) = self.view_tree.ancestor_ids(parent_node).unwrap().next() {
-                self.parent_node_id = Some(parent.clone());
-            } else {
-                self.parent_node_id = None;
+            self.parent_node_id = self
+                .view_tree
+                .ancestor_ids(parent_node)
+                .ok()
+                .and_then(|mut ancestors| ancestors.next().cloned());
+        }
+    }
+
+    /// The type an object passed to `.environmentObject()` is provided as,
+    /// `Store` of `Store()` or of a member declared or created as one
+    fn environment_type(&self, node: &tree_sitter::Node) -> Result<String> {
+        let source = &*self.source;
+        let callee = |node: &tree_sitter::Node| -> Result<Option<String>> {
+            if node.kind() != "call_expression" {
+                return Ok(None);
             }
+            let callee = node.child_at(0, source)?;
+            (callee.kind() == "simple_identifier").then(|| callee.text(source).map(|x| x.to_string())).transpose()
+        };
+
+        let ty = match node.kind() {
+            "call_expression" => callee(node)?,
+            "simple_identifier" => match self.struct_info.members.get(node.text(source)?) {
+                Some(StructMember::Property { ty: Some(ty), .. }) | Some(StructMember::Stored { ty, .. }) => {
+                    Some(ty.text(source)?.trim_start_matches(':').trim().to_string())
+                }
+                Some(StructMember::Property { node, .. }) => callee(node)?,
+                _ => None,
+            },
+            _ => None,
+        };
+
+        ty.ok_or_else(|| CompileError::unsupported("无法确定 environmentObject 的类型，请添加类型标注", node, source))
+    }
+
+    /// A Swift expression evaluated in the template, the refs are unwrapped by Vue
+    fn template_js(&self, node: &tree_sitter::Node) -> Result<String> {
+        let source = &*self.source;
+        let models = self.model_names();
+        let enums = self.symbols.enums.iter().cloned().collect::<Vec<_>>();
+
+        common::statement::rewrite_expr(node, source, &mut |node| {
+            if let Some(code) = common::expr::swift2js(node, source, &self.symbols, &|x| self.template_js(x), &|x| self.type_of(x))? {
+                return Ok(Some(code));
+            }
+
+            Ok(match node.kind() {
+                "call_expression" => {
+                    let callee = node.named_child(0).map(|x| x.text(source)).transpose()?.unwrap_or_default();
+                    if let Some(code) = self.toggle2js(node, true)? {
+                        Some(code)
+                    } else if models.iter().any(|x| x == callee) {
+                        Some(common::object::callexp2object_with_context(node, source, models.clone())?)
+                    } else if let Some(code) = common::enums::construct2js(node, source, None, &enums, &|x| self.template_js(x))? {
+                        Some(code)
+                    } else {
+                        self.positional_call(node, &|x| self.template_js(x))?
+                    }
+                }
+                // `self.count` is `count` in the template
+                "navigation_expression" if node.child(0).map(|x| x.kind()) == Some("self_expression") => {
+                    node.child(1).and_then(|x| x.named_child(0)).map(|x| x.text(source)).transpose()?.map(str::to_string)
+                }
+                "prefix_expression" => self.environment_case(node)?,
+                "array_literal" => Some(common::array::array2js_call_with_obj_context(node, source, String::new(), models.clone())?),
+                "lambda_literal" => Some(self.template_closure(node)?),
+                "simple_identifier" => {
+                    let name = node.text(source)?;
+                    let parent = node.parent().map(|x| x.kind());
+                    if matches!(parent, Some("navigation_suffix") | Some("value_argument_label")) {
+                        return Ok(None);
+                    }
+                    if let Some((_, value)) = self.aliases.borrow().iter().rev().find(|(alias, _)| alias == name) {
+                        return Ok(Some(format!("({})", value)));
+                    }
+                    self.renamed.borrow().iter().any(|x| x == name).then(|| format!("{}_", name))
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// `isOn.toggle()`, a binding is updated by the parent and the other values are assigned their negation.
+    /// In the `template` the refs are unwrapped and `$emit` is the `emit` of the script
+    fn toggle2js(&self, node: &tree_sitter::Node, template: bool) -> Result<Option<String>> {
+        let source = &*self.source;
+        let callee = node.named_child(0).filter(|x| x.kind() == "navigation_expression");
+        let arguments = node.named_child(1).map(|x| x.text(source)).transpose()?;
+        let (Some(callee), Some("()")) = (callee, arguments) else {
+            return Ok(None);
+        };
+        let method = callee.named_child(1).and_then(|x| x.named_child(0)).map(|x| x.text(source)).transpose()?;
+        let Some(target) = callee.named_child(0).filter(|_| method == Some("toggle")) else {
+            return Ok(None);
+        };
+
+        let text = target.text(source)?;
+        let name = text.strip_prefix("self.").unwrap_or(text);
+        // a parameter or a local of the same name
+        let locals = if template { &self.renamed } else { &self.shadowed };
+        let member = name != text || !locals.borrow().iter().any(|x| x == name);
+        if member && self.is_binding(name) {
+            return Ok(Some(match template {
+                true => format!("$emit('update:{0}', !{0})", name),
+                false => format!("emit('update:{0}', !props.{0})", name),
+            }));
         }
+        if member && matches!(self.struct_info.members.get(name), Some(StructMember::Property { modifier, .. }) if modifier.as_deref() != Some("State")) {
+            return Err(CompileError::unsupported("暂不支持给不是 @State 的属性赋值", node, source));
+        }
+
+        let value = if template { self.template_js(&target)? } else { self.expr(&target)? };
+        Ok(Some(format!("{0} = !{0}", value)))
     }
 
-    fn extract_view_tag(&self, node: &tree_sitter::Node) -> Option<String> {
-        assert_eq!(node.kind(), "call_expression");
-        let child = node.child(0).unwrap();
+    /// `f(a, b)` of a call of a function of the view, a method or a function declared at file scope,
+    /// the labels are dropped. The labels of the other calls can't be, e.g. `DateComponents(year: 2021)`
+    fn positional_call(&self, node: &tree_sitter::Node, expr: ExprFn) -> Result<Option<String>> {
+        let source = &*self.source;
+        let callee = node.named_child(0);
+        let callee_code = callee.map(|x| x.text(source)).transpose()?.unwrap_or_default();
+        let suffix = node.named_child(1).filter(|x| x.kind() == "call_suffix");
+        let arguments = suffix.and_then(|x| x.named_child(0)).filter(|x| x.kind() == "value_arguments");
+        let (Some(callee), Some(arguments)) = (callee, arguments) else {
+            return Ok(None);
+        };
+        // `calendar.date(from: components)`, a method of the project or of `self`
+        let method = match callee.kind() {
+            "navigation_expression" => {
+                let name = callee.named_child(1).and_then(|x| x.named_child(0));
+                let name = name.map(|x| x.text(source)).transpose()?.unwrap_or_default();
+                Some(callee.named_child(0).is_some_and(|x| x.kind() == "self_expression") || self.symbols.methods.contains(name))
+            }
+            _ => None,
+        };
+        let function = method == Some(true)
+            || self.symbols.functions.contains(callee_code)
+            || matches!(self.struct_info.members.get(callee_code), Some(StructMember::Function(_)));
+        let mut cursor = arguments.walk();
+        let labeled = arguments
+            .named_children(&mut cursor)
+            .any(|x| x.named_child(0).is_some_and(|x| x.kind() == "value_argument_label"));
+        if labeled && !function {
+            return Err(CompileError::unsupported(
+                format!("暂不支持 {} 的带标签参数的调用", callee_code),
+                node,
+                source,
+            ));
+        }
+        if suffix.map(|x| x.named_child_count()) != Some(1) || !(function || method.is_some()) {
+            return Ok(None);
+        }
+
+        let mut args = Vec::new();
+        let mut cursor = arguments.walk();
+        for argument in arguments.named_children(&mut cursor) {
+            let mut cursor = argument.walk();
+            if let Some(value) = argument.named_children(&mut cursor).last() {
+                args.push(expr(&value)?);
+            }
+        }
+        Ok(Some(format!("{}({})", expr(&callee)?, args.join(", "))))
+    }
+
+    /// An arrow function of a closure, its parameters hide the members of the same name
+    fn closure(&self, node: &tree_sitter::Node, indent: Option<usize>) -> Result<String> {
+        let len = self.shadowed.borrow().len();
+        self.shadowed.borrow_mut().extend(common::statement::closure_params(node, &self.source)?);
+        let code = common::statement::closure2js(self, node, indent);
+        self.shadowed.borrow_mut().truncate(len);
+        code
+    }
+
+    /// An arrow function of a closure in the template, see [`Self::renamed`]
+    fn template_closure(&self, node: &tree_sitter::Node) -> Result<String> {
+        let params = common::statement::closure_params(node, &self.source)?;
+        let renamed = params.iter().filter(|x| self.struct_info.members.contains_key(*x)).cloned().collect::<Vec<_>>();
+
+        let names = params
+            .iter()
+            .map(|x| if renamed.contains(x) { format!("{}_", x) } else { x.clone() })
+            .collect::<Vec<_>>();
+
+        let len = self.renamed.borrow().len();
+        self.renamed.borrow_mut().extend(renamed);
+        let code = common::statement::closure2js_with_params(&TemplateWriter { parser: self }, node, &names, None);
+        self.renamed.borrow_mut().truncate(len);
+        code
+    }
+
+    /// [`Self::template_js`] put in a double quoted attribute
+    fn template_expr(&self, node: &tree_sitter::Node) -> Result<String> {
+        Ok(common::expr::attribute(self.template_js(node)?))
+    }
+
+    /// The attribute a labeled argument of a user view is passed as
+    fn bind_prop(&self, argument: &tree_sitter::Node) -> Result<Option<(String, String)>> {
+        let source = &*self.source;
+        let mut cursor = argument.walk();
+        let children = argument.named_children(&mut cursor).collect::<Vec<_>>();
+        let (Some(label), Some(value)) = (children.iter().find(|x| x.kind() == "value_argument_label"), children.last())
+        else {
+            return Ok(None);
+        };
+        let label = label.text(source)?;
+        let value_code = value.text(source)?;
+
+        let static_string = value.kind() == "line_string_literal" && find_first_node(*value, "interpolated_expression").is_none();
+        // `.constant(true)` of a `@Binding` that never changes
+        let constant = value_code
+            .strip_prefix(".constant(")
+            .and_then(|_| find_first_node(*value, "value_argument"))
+            .and_then(|x| x.named_child(x.named_child_count().saturating_sub(1)));
+
+        Ok(Some(if let Some(name) = value_code.strip_prefix('$') {
+            (format!("v-model:{}", label), name.to_string())
+        } else if let Some(constant) = constant {
+            (format!("v-bind:{}", label), self.template_expr(&constant)?)
+        } else if static_string {
+            (label.to_string(), value_code.trim_matches('"').to_string())
+        } else {
+            (format!("v-bind:{}", label), self.template_expr(value)?)
+        }))
+    }
+
+    fn extract_view_tag(&self, node: &tree_sitter::Node) -> Result<Option<String>> {
+        node.expect_kind("call_expression", &self.source)?;
+        let child = node.child_at(0, &self.source)?;
 
         match child.kind() {
             "simple_identifier" => {
-                let identifier_text = child.utf8_text(self.source.as_bytes()).unwrap();
-                Some(identifier_text.to_string())
+                let identifier_text = child.text(&self.source)?;
+                Ok(Some(identifier_text.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The trailing closure of `Button("Add") { ... }` or `Button { ... } label: { ... }` is the action,
+    /// it's the label if there is an `action:` argument
+    fn button_action(&self, tag: &str, node: &tree_sitter::Node<'a>) -> Result<Option<tree_sitter::Node<'a>>> {
+        let source = &*self.source;
+        let Some(suffix) = node.child(1).filter(|x| x.kind() == "call_suffix" && tag == "Button") else {
+            return Ok(None);
+        };
+        if let Some(arguments) = suffix.child(0).filter(|x| x.kind() == "value_arguments") {
+            let mut cursor = arguments.walk();
+            for argument in arguments.named_children(&mut cursor) {
+                let label = argument.named_child(0).filter(|x| x.kind() == "value_argument_label");
+                if label.map(|x| x.text(source)).transpose()? == Some("action") {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let mut cursor = suffix.walk();
+        let action = suffix.children(&mut cursor).find(|x| x.kind() == "lambda_literal");
+        Ok(action)
+    }
+
+    /// The `statements` and the parameters of the view builder `header` or `row(item)` refers to,
+    /// with the source of the file it's declared in
+    fn view_builder(&self, node: &tree_sitter::Node) -> Result<Option<ViewBuilder<'a>>> {
+        let source = &*self.source;
+        let name = match node.kind() {
+            "call_expression" => node.child(0).filter(|x| x.kind() == "simple_identifier"),
+            // `header` alone or with modifiers, `header.padding()`
+            "simple_identifier" => {
+                let parent = node.parent();
+                let view = match parent.map(|x| x.kind()) {
+                    Some("statements") => true,
+                    Some("navigation_expression") => parent.and_then(|x| x.child(0)).map(|x| x.id()) == Some(node.id()),
+                    _ => false,
+                };
+                view.then_some(*node)
             }
             _ => None,
+        };
+        let Some(name) = name else {
+            return Ok(None);
+        };
+
+        let name = name.text(source)?;
+        Ok(match self.struct_info.members.get(name) {
+            Some(StructMember::ViewBuilder { statements, params }) => {
+                Some((*statements, params.clone(), self.struct_info.member_source(name).clone()))
+            }
+            _ => None,
+        })
+    }
+
+    /// The views of a view builder are put in place of the call. An argument is bound to the name of its
+    /// parameter with a `v-for` over itself, unless it's passed in by the same name
+    fn inline_view_builder(&mut self, node: &tree_sitter::Node, builder: ViewBuilder<'a>) -> Result<()> {
+        let (statements, params, builder_source) = builder;
+        let source = self.source.clone();
+        let source = &*source;
+
+        let mut wrappers = 0;
+        let arguments = node.child(1).and_then(|x| x.child(0)).filter(|x| x.kind() == "value_arguments");
+        if let Some(arguments) = arguments {
+            let mut cursor = arguments.walk();
+            let values = arguments
+                .named_children(&mut cursor)
+                .filter_map(|x| x.named_child(x.named_child_count().saturating_sub(1)))
+                .collect::<Vec<_>>();
+            for (param, value) in params.iter().zip(values) {
+                if value.text(source)? == param {
+                    continue;
+                }
+                let mut wrapper = ViewNode::new("template".to_string());
+                wrapper.position = Some(value.start_position());
+                wrapper.modifier.insert("v-for".to_string(), format!("{} in [{}]", param, self.template_expr(&value)?));
+                self.insert_view_node(wrapper);
+                wrappers += 1;
+            }
+        }
+
+        // an extension in another file declares it
+        let mut cursor = statements.walk();
+        let call_source = std::mem::replace(&mut self.source, builder_source);
+        let result = self.handle_struct(&mut cursor);
+        self.source = call_source;
+        result?;
+
+        // the modifiers of `header.padding()` go to the last view of the builder
+        let root = match self.parent_node_id.as_ref() {
+            Some(parent) => self.view_tree.children_ids(parent).ok().and_then(|x| x.last().cloned()),
+            None => self.view_tree.root_node_id().cloned(),
+        };
+        for _ in 0..wrappers {
+            self.post_insert_view_node();
         }
+        if let Some(root) = root {
+            self.id_to_tree_id.insert(node.id(), root);
+            if node.parent_node(source)?.kind() == "navigation_expression" {
+                self.navigation_component_node_id = Some(node.id());
+            }
+        }
+
+        Ok(())
     }
 
-    fn handle_node(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> bool {
+    /// The `v-if` of the conditions of an `if` in the template and the names bound by its `if let`, with the
+    /// values they are bound to. `if let user = user` only checks the member
+    fn template_conditions(&self, node: &tree_sitter::Node<'a>, nodes: &[tree_sitter::Node<'a>]) -> Result<(String, Vec<(String, String)>)> {
+        use common::statement::Condition;
+
+        let source = &*self.source;
+        if nodes.iter().any(|x| x.kind() == "case") {
+            return Err(CompileError::unsupported("暂不支持视图中的 if case", node, source));
+        }
+
+        let parts = common::statement::condition_parts(nodes, source)?;
+        let len = self.aliases.borrow().len();
+        let mut codes = Vec::new();
+        let mut aliases = Vec::new();
+        for part in &parts {
+            let code = match part {
+                Condition::Binding(binding) => {
+                    let value = binding.value.as_ref().unwrap_or(&binding.name);
+                    let code = self.template_js(value)?;
+                    if !binding.checks_only(source)? {
+                        let name = binding.name.text(source)?.to_string();
+                        // the `v-for` of the alias is inside the `v-if`, the value refers to the previous aliases
+                        aliases.push((name.clone(), self.template_js_without_aliases(value, len)?));
+                        self.aliases.borrow_mut().push((name, code.clone()));
+                    }
+                    format!("{} != null", code)
+                }
+                Condition::Expr(condition) => {
+                    let code = self.template_js(condition)?;
+                    if parts.len() > 1 && ["disjunction_expression", "ternary_expression"].contains(&condition.kind()) {
+                        format!("({})", code)
+                    } else {
+                        code
+                    }
+                }
+            };
+            codes.push(code);
+        }
+        self.aliases.borrow_mut().truncate(len);
+
+        if codes.is_empty() {
+            return Err(CompileError::unexpected("expect a condition", node, source));
+        }
+        Ok((common::expr::attribute(codes.join(" && ")), aliases))
+    }
+
+    /// [`Self::template_js`] with only the first `len` aliases
+    fn template_js_without_aliases(&self, node: &tree_sitter::Node, len: usize) -> Result<String> {
+        let aliases = self.aliases.borrow_mut().split_off(len);
+        let code = self.template_js(node);
+        self.aliases.borrow_mut().extend(aliases);
+        code
+    }
+
+    /// `if a { ... } else if b { ... } else { ... }` of a view body, each branch is a `<template>` with `v-if`,
+    /// `v-else-if` or `v-else`. A name of `if let` is bound with a `v-for` over its value inside, like the
+    /// parameters of [`Self::inline_view_builder`]
+    fn conditional_views(&mut self, node: &tree_sitter::Node<'a>, directive: &str) -> Result<()> {
+        let source = self.source.clone();
+        let source = &*source;
+        let mut cursor = node.walk();
+        let children = node.children(&mut cursor).collect::<Vec<_>>();
+        let open = children
+            .iter()
+            .position(|x| x.kind() == "{")
+            .ok_or_else(|| CompileError::unexpected("expect `{`", node, source))?;
+
+        let (condition, aliases) = self.template_conditions(node, &children[1..open])?;
+        let mut wrapper = ViewNode::new("template".to_string());
+        wrapper.position = Some(node.start_position());
+        wrapper.modifier.insert(directive.to_string(), condition);
+        self.insert_view_node(wrapper);
+        for (name, value) in &aliases {
+            let mut wrapper = ViewNode::new("template".to_string());
+            wrapper.modifier.insert("v-for".to_string(), format!("{} in [{}]", name, common::expr::attribute(value.clone())));
+            self.insert_view_node(wrapper);
+        }
+
+        let close = self.branch_views(&children, open)?;
+        for _ in 0..=aliases.len() {
+            self.post_insert_view_node();
+        }
+
+        if children.get(close + 1).map(|x| x.kind()) == Some("else") {
+            match children.get(close + 2) {
+                Some(else_if) if else_if.kind() == "if_statement" => self.conditional_views(else_if, "v-else-if")?,
+                Some(_) => {
+                    let mut wrapper = ViewNode::new("template".to_string());
+                    wrapper.position = children.get(close + 1).map(|x| x.start_position());
+                    wrapper.modifier.insert("v-else".to_string(), String::new());
+                    self.insert_view_node(wrapper);
+                    self.branch_views(&children, close + 2)?;
+                    self.post_insert_view_node();
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The views of the `statements` after the `{` at `open`, the index of its `}`
+    fn branch_views(&mut self, children: &[tree_sitter::Node<'a>], open: usize) -> Result<usize> {
+        let mut next = open + 1;
+        while let Some(child) = children.get(next) {
+            match child.kind() {
+                "statements" => {
+                    let mut cursor = child.walk();
+                    self.handle_struct(&mut cursor)?;
+                }
+                "}" => break,
+                _ => {}
+            }
+            next += 1;
+        }
+        Ok(next)
+    }
+
+    fn handle_node(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<bool> {
         let node = cursor.node();
+        let source = self.source.clone();
+        let source = &*source;
 
         if node.kind() == "comment" {
-            return false;
+            return Ok(false);
+        }
+
+        if node.kind() == "if_statement" {
+            // the conditional views at the root are in a `Group`, the template has a single root
+            let root = self.parent_node_id.is_none();
+            if root {
+                let mut group = ViewNode::new("Group".to_string());
+                group.position = Some(node.start_position());
+                self.insert_view_node(group);
+            }
+            self.conditional_views(&node, "v-if")?;
+            if root {
+                self.post_insert_view_node();
+            }
+            return Ok(false);
+        }
+
+        if let Some(builder) = self.view_builder(&node)? {
+            if node.kind() == "call_expression" {
+                self.in_call_expression += 1;
+            }
+            self.inline_view_builder(&node, builder)?;
+            return Ok(false);
         }
 
         if node.kind() == "call_expression" {
             self.in_call_expression += 1;
 
-            if let Some(tag) = self.extract_view_tag(&node) {
+            if let Some(tag) = self.extract_view_tag(&node)? {
                 let mut view_node = ViewNode::new(tag.clone());
+                view_node.position = Some(node.start_position());
+
+                // the labeled arguments of a user view are its props, they are not views
+                let arguments = node.child(1).and_then(|x| x.child(0)).filter(|x| x.kind() == "value_arguments");
+                let arguments = arguments.filter(|_| self.symbols.is_view(&tag));
+                if let Some(arguments) = arguments {
+                    let mut cursor = arguments.walk();
+                    for argument in arguments.named_children(&mut cursor) {
+                        if let Some((key, value)) = self.bind_prop(&argument)? {
+                            view_node.modifier.extend(self.forward_binding(key, value));
+                        }
+                    }
+                    self.ignore_nodes.push(arguments);
+                }
+                if let Some(action) = self.button_action(&tag, &node)? {
+                    let handler = self.template_expr(&action)?;
+                    view_node.modifier.insert("v-bind:action".to_string(), handler);
+                    self.ignore_nodes.push(action);
+                }
 
                 for i in 0..node.child_count() {
-                    let child = node.child(i).unwrap();
+                    let child = node.child_at(i, source)?;
                     if child.kind() == "call_suffix" {
-                        if child.child(0).unwrap().kind() == "lambda_literal" {
+                        if child.child_at(0, source)?.kind() == "lambda_literal" || arguments.is_some() {
                             continue;
                         }
 
                         for j in 0..child.child_count() {
-                            let call_suffix_child = child.child(j).unwrap();
+                            let call_suffix_child = child.child_at(j, source)?;
                             if call_suffix_child.kind() == "value_arguments" {
-                                for k in 0..(call_suffix_child.child_count() - 2) {
-                                    let arg_node = call_suffix_child.child(k + 1).unwrap();
+                                for k in 0..call_suffix_child.child_count().saturating_sub(2) {
+                                    let arg_node = call_suffix_child.child_at(k + 1, source)?;
 
                                     if arg_node.kind() == "," {
                                         continue;
                                     }
 
-                                    if let Some((key, value)) = crate::component::compute_modifier(
-                                        tag.clone(),
-                                        &arg_node,
-                                        &self.source,
-                                    ) {
+                                    if let Some((key, value)) =
+                                        crate::component::compute_modifier(tag.clone(), &arg_node, source, &|x| self.template_js(x))?
+                                    {
                                         // println!("{}: {}", key, value);
                                         if key.as_str() == "child" {
                                             view_node.str_content = Some(value);
                                         } else {
-                                            view_node.modifier.insert(key, value);
+                                            view_node.modifier.extend(self.forward_binding(key, value));
                                         }
                                     }
                                     // log_node_tree(&arg_node, 0);
@@ -513,40 +1746,41 @@ export default {{
                 let insert_id = self.insert_view_node(view_node);
                 self.id_to_tree_id.insert(node.id(), insert_id);
 
-                if node.parent().unwrap().kind() == "navigation_expression" {
+                if node.parent_node(source)?.kind() == "navigation_expression" {
                     self.navigation_component_node_id = Some(node.id());
                 }
             }
         }
 
         if node.kind() == "navigation_expression"
-            && node.parent().unwrap().kind() == "call_expression"
+            && node.parent_node(source)?.kind() == "call_expression"
         {
-            let related_call_suffix = node.next_sibling().unwrap();
-            assert_eq!(related_call_suffix.kind(), "call_suffix");
+            let related_call_suffix = node.next_node(source)?;
+            related_call_suffix.expect_kind("call_suffix", source)?;
             self.navigation_expression_level.push(related_call_suffix);
         }
 
         if node.kind() == "navigation_suffix"
-            && node.parent().unwrap().parent().unwrap().kind() == "call_expression"
+            && node.parent_node(source)?.parent_node(source)?.kind() == "call_expression"
         {
-            let last_navigation = self.navigation_expression_level.pop().unwrap();
+            let last_navigation = self
+                .navigation_expression_level
+                .pop()
+                .ok_or_else(|| CompileError::unexpected("expect a call suffix for the modifier", &node, source))?;
             self.ignore_nodes.push(last_navigation);
 
-            let call_suffix_identifier = node.child(1).unwrap();
-            let mut call_suffix_name = call_suffix_identifier
-                .utf8_text(self.source.as_bytes())
-                .unwrap();
+            let call_suffix_identifier = node.child_at(1, source)?;
+            let mut call_suffix_name = call_suffix_identifier.text(source)?;
 
-            let args_node = last_navigation.child(0).unwrap();
+            let args_node = last_navigation.child_at(0, source)?;
 
             // TODO: only support one arg for now
             // TODO: no handling of lambda_literal for now, it's usually for children
             let arg_node = if args_node.child_count() > 2 && args_node.kind() != "lambda_literal" {
-                let node = args_node.child(1).unwrap();
-                let value_node = node.child(0).unwrap();
-                if node.child(0).unwrap().kind() == "value_argument_label" {
-                    Some(value_node.next_sibling().unwrap().next_sibling().unwrap())
+                let node = args_node.child_at(1, source)?;
+                let value_node = node.child_at(0, source)?;
+                if value_node.kind() == "value_argument_label" {
+                    Some(value_node.next_node(source)?.next_node(source)?)
                 } else {
                     Some(value_node)
                 }
@@ -556,13 +1790,9 @@ export default {{
 
             let arg_value = if let Some(arg_node) = arg_node {
                 if arg_node.kind() == "prefix_expression" {
-                    arg_node
-                        .child(1)
-                        .unwrap()
-                        .utf8_text(self.source.as_bytes())
-                        .unwrap()
+                    arg_node.child_at(1, source)?.text(source)?
                 } else {
-                    arg_node.utf8_text(self.source.as_bytes()).unwrap()
+                    arg_node.text(source)?
                 }
             } else {
                 ""
@@ -572,55 +1802,288 @@ export default {{
             if call_suffix_name == "onTapGesture" {
                 call_suffix_name = "@click";
             }
+            // `.onTapGesture { ... }` or `.onTapGesture(perform: { ... })`
+            let mut cursor = last_navigation.walk();
+            let closure = match arg_node {
+                Some(arg_node) if arg_node.kind() == "lambda_literal" => Some(arg_node),
+                _ => last_navigation.children(&mut cursor).find(|x| x.kind() == "lambda_literal"),
+            };
+            let handler = match closure {
+                Some(closure) if call_suffix_name == "@click" => Some(self.template_expr(&closure)?),
+                _ => None,
+            };
+
+            // `.disabled(items.isEmpty)` reads the state, `.font(.title)` and literals are static
+            let bound = match arg_node {
+                Some(arg_node) if !call_suffix_name.starts_with('@') && is_state_expression(&arg_node, source) => {
+                    Some((format!("v-bind:{}", call_suffix_name), self.template_expr(&arg_node)?))
+                }
+                _ => None,
+            };
 
-            // TODO: ignroe contextMenu for now
-            if call_suffix_name != "contextMenu" {
-                let related_call_exp = self.navigation_component_node_id.unwrap();
-                let related_tree_id = self.id_to_tree_id.get(&related_call_exp).unwrap();
-                let realted_view_node = self.view_tree.get_mut(related_tree_id).unwrap();
-                realted_view_node
-                    .data_mut()
-                    .modifier
-                    .insert(call_suffix_name.to_string(), arg_value.to_string());
+            let environment = match arg_node {
+                Some(arg_node) if call_suffix_name == "environmentObject" => {
+                    Some((self.environment_type(&arg_node)?, self.template_expr(&arg_node)?))
+                }
+                _ => None,
+            };
+            // `\.locale` and the value of `.environment(\.locale, value)`
+            let environment_value = match find_first_node(args_node, "navigation_suffix").and_then(|x| x.named_child(0)) {
+                Some(key) if call_suffix_name == "environment" => {
+                    let mut cursor = args_node.walk();
+                    let value = args_node
+                        .named_children(&mut cursor)
+                        .filter(|x| x.kind() == "value_argument")
+                        .nth(1)
+                        .and_then(|x| x.named_child(x.named_child_count().saturating_sub(1)))
+                        .ok_or_else(|| CompileError::unexpected("expect an environment value", &args_node, source))?;
+                    Some((key.text(source)?.to_string(), self.environment_value(&value)?))
+                }
+                _ => None,
+            };
+
+            if environment.is_some() || environment_value.is_some() {
+                let related_view_node = self
+                    .navigation_component_node_id
+                    .and_then(|id| self.id_to_tree_id.get(&id))
+                    .and_then(|tree_id| self.view_tree.get_mut(tree_id).ok())
+                    .ok_or_else(|| {
+                        CompileError::unexpected("modifier is not attached to a view", &node, source)
+                    })?;
+                let view_node = related_view_node.data_mut();
+                view_node.environment.extend(environment);
+                view_node.environment_values.extend(environment_value);
+            } else if call_suffix_name != "contextMenu" {
+                // TODO: ignroe contextMenu for now
+                let related_view_node = self
+                    .navigation_component_node_id
+                    .and_then(|id| self.id_to_tree_id.get(&id))
+                    .and_then(|tree_id| self.view_tree.get_mut(tree_id).ok())
+                    .ok_or_else(|| {
+                        CompileError::unexpected("modifier is not attached to a view", &node, source)
+                    })?;
+                let value = handler.unwrap_or_else(|| arg_value.to_string());
+                let (key, value) = bound.unwrap_or_else(|| (call_suffix_name.to_string(), value));
+                related_view_node.data_mut().modifier.insert(key, value);
             }
         }
 
-        if node.kind() == "call_suffix" {
-            if self.ignore_nodes.contains(&node) {
-                return false;
-            }
+        if ["call_suffix", "value_arguments", "lambda_literal"].contains(&node.kind()) && self.ignore_nodes.contains(&node) {
+            return Ok(false);
         }
 
         // log_node(&node, cursor.depth());
 
-        true
+        Ok(true)
     }
 
-    fn handle_node_post(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) {
+    fn handle_node_post(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<()> {
         let node = cursor.node();
 
         if node.kind() == "call_expression" {
             self.in_call_expression -= 1;
 
-            if let Some(_) = self.extract_view_tag(&node) {
+            // the views of a view builder are inserted and closed by `Self::inline_view_builder`
+            if self.extract_view_tag(&node)?.is_some() && self.view_builder(&node)?.is_none() {
                 self.post_insert_view_node();
             }
         }
+
+        Ok(())
     }
 
-    fn handle_struct(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) {
-        let go_on = self.handle_node(cursor);
+    fn handle_struct(&mut self, cursor: &mut tree_sitter::TreeCursor<'a>) -> Result<()> {
+        let go_on = self.handle_node(cursor)?;
 
-        if go_on {
-            if cursor.goto_first_child() {
-                self.handle_struct(cursor);
-                while cursor.goto_next_sibling() {
-                    self.handle_struct(cursor);
-                }
-                cursor.goto_parent();
+        if go_on && cursor.goto_first_child() {
+            self.handle_struct(cursor)?;
+            while cursor.goto_next_sibling() {
+                self.handle_struct(cursor)?;
+            }
+            cursor.goto_parent();
+        }
+
+        self.handle_node_post(cursor)
+    }
+}
+
+/// Writes the closures of the template, the refs are unwrapped by Vue and a binding is updated by `$emit`
+struct TemplateWriter<'p, 'a> {
+    parser: &'p ViewParser<'a>,
+}
+
+impl StatementWriter for TemplateWriter<'_, '_> {
+    fn source(&self) -> &str {
+        &self.parser.source
+    }
+
+    fn expr(&self, node: &tree_sitter::Node) -> Result<String> {
+        self.parser.template_js(node)
+    }
+
+    fn statement(&self, node: &tree_sitter::Node, indent: usize) -> Result<String> {
+        let source = self.source();
+        if node.kind() == "assignment" {
+            let target = node.child_at(0, source)?;
+            let target = target.text(source)?;
+            let target = target.strip_prefix("self.").unwrap_or(target);
+            if self.parser.is_binding(target) {
+                let op = node.child_at(1, source)?;
+                let value = self.expr(&node.child_at(2, source)?)?;
+                let value = match op.text(source)?.strip_suffix('=').filter(|x| !x.is_empty()) {
+                    Some(op) => format!("{} {} ({})", target, op, value),
+                    None => value,
+                };
+                return Ok(format!("{:indent$}$emit('update:{}', {});\n", "", target, value, indent = indent));
             }
         }
+        common::statement::statement2js(self, node, indent)
+    }
+}
+
+/// Whether the argument of a modifier is evaluated in the template,
+/// `.case`, members of a type like `Color.blue` and the literals are passed as they are
+fn is_state_expression(node: &tree_sitter::Node, source: &str) -> bool {
+    match node.kind() {
+        "simple_identifier" => !source[node.byte_range()].starts_with(|c: char| c.is_ascii_uppercase()),
+        "conjunction_expression" | "disjunction_expression" | "equality_expression" | "comparison_expression"
+        | "infix_expression" => true,
+        "navigation_expression" => node.child(0).is_some_and(|x| is_state_expression(&x, source)),
+        "prefix_expression" => node.child(0).map(|x| x.kind()) == Some("bang"),
+        _ => false,
+    }
+}
+
+impl StatementWriter for ViewParser<'_> {
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn scoped_statements(&self, node: &tree_sitter::Node, indent: usize, names: &[String]) -> Result<String> {
+        let len = self.shadowed.borrow().len();
+        self.shadowed.borrow_mut().extend(names.iter().cloned());
+        let code = self.statements(node, indent);
+        self.shadowed.borrow_mut().truncate(len);
+        code
+    }
+
+    fn declare(&self, names: &[String]) {
+        self.shadowed.borrow_mut().extend(names.iter().cloned());
+    }
+
+    /// The Swift expression, with `.value` of the state and the enum cases it constructs
+    fn expr(&self, node: &tree_sitter::Node) -> Result<String> {
+        let source = &*self.source;
+        let enums = self.symbols.enums.iter().cloned().collect::<Vec<_>>();
+
+        common::statement::rewrite_expr(node, source, &mut |node| {
+            if let Some(code) = common::expr::swift2js(node, source, &self.symbols, &|x| self.expr(x), &|x| self.type_of(x))? {
+                return Ok(Some(code));
+            }
+            let parent_kind = node.parent().map(|x| x.kind());
+
+            match node.kind() {
+                "call_expression" => {
+                    if let Some(code) = self.toggle2js(node, false)? {
+                        return Ok(Some(code));
+                    }
+                    let callee = node.named_child(0);
+                    let callee_code = callee.map(|x| x.text(source)).transpose()?.unwrap_or_default();
+                    let models = self.model_names();
+                    if models.iter().any(|x| x == callee_code) {
+                        return Ok(Some(common::object::callexp2object_with_context(node, source, models)?));
+                    }
+                    if let Some(code) = common::enums::construct2js(node, source, None, &enums, &|x| self.expr(x))? {
+                        return Ok(Some(code));
+                    }
+
+                    self.positional_call(node, &|x| self.expr(x))
+                }
+                // `self.count` is `count.value`
+                "navigation_expression" if node.child(0).map(|x| x.kind()) == Some("self_expression") => {
+                    let member = node.child(1).and_then(|x| x.named_child(0)).map(|x| x.text(source)).transpose()?;
+                    let Some(member) = member else {
+                        return Ok(None);
+                    };
+                    Ok(Some(self.member_expr(member)?.unwrap_or_else(|| member.to_string())))
+                }
+                "prefix_expression" => match self.environment_case(node)? {
+                    Some(case) => Ok(Some(case)),
+                    None => common::enums::construct2js(node, source, None, &enums, &|x| self.expr(x)),
+                },
+                "lambda_literal" => Ok(Some(self.closure(node, None)?)),
+                "simple_identifier" if !matches!(parent_kind, Some("navigation_suffix") | Some("value_argument_label")) => {
+                    let name = node.text(source)?;
+                    // `self.name` is still the member
+                    if self.shadowed.borrow().iter().any(|x| x == name) {
+                        return Ok(None);
+                    }
+                    self.member_expr(name)
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn statement(&self, node: &tree_sitter::Node, indent: usize) -> Result<String> {
+        let source = &*self.source;
+
+        let code = match node.kind() {
+            "assignment" => {
+                let target = node.child_at(0, source)?;
+                let text = target.text(source)?;
+                let target = text.strip_prefix("self.").unwrap_or(text);
+                // a local `var` of the same name
+                if target == text && self.shadowed.borrow().iter().any(|x| x == target) {
+                    return common::statement::statement2js(self, node, indent);
+                }
+                if self.is_binding(target) {
+                    // the parent owns the value, it's updated by `v-model`
+                    let op = node.child_at(1, source)?;
+                    let op = op.text(source)?;
+                    let value = self.expr(&node.child_at(2, source)?)?;
+                    let value = match op.strip_suffix('=').filter(|x| !x.is_empty()) {
+                        Some(op) => format!("props.{} {} ({})", target, op, value),
+                        None => value,
+                    };
+                    format!("emit('update:{}', {});", target, value)
+                } else if self.is_computed(target) {
+                    // the setter of a writable computed
+                    let op = node.child_at(1, source)?;
+                    let value = self.expr(&node.child_at(2, source)?)?;
+                    format!("{}.value {} {};", target, op.text(source)?, value)
+                } else if let Some(StructMember::Property { modifier, .. }) = self.struct_info.members.get(target) {
+                    // 处理 vue3 ref
+                    if modifier != &Some("State".to_string()) {
+                        return Err(CompileError::unsupported("暂不支持给不是 @State 的属性赋值", node, source));
+                    }
+
+                    let enum_type = self.member_enum_type(target)?;
+                    let enums = self.symbols.enums.iter().cloned().collect::<Vec<_>>();
+
+                    let op = node.child_at(1, source)?;
+                    let op = op.text(source)?;
+                    let value = node.child_at(2, source)?;
+                    let value = match common::enums::construct2js(&value, source, enum_type.as_deref(), &enums, &|x| self.expr(x))? {
+                        Some(code) => code,
+                        None => self.expr(&value)?,
+                    };
+
+                    format!("{}.value {op} {};", target, value)
+                } else {
+                    return common::statement::statement2js(self, node, indent);
+                }
+            }
+            // the names of `guard let` hide the members until the end of the function
+            "guard_statement" => {
+                let code = common::statement::statement2js(self, node, indent);
+                self.shadowed.borrow_mut().extend(common::statement::optional_bindings(node, source)?);
+                return code;
+            }
+            // control flow and the other statements, `.value` of the refs is added by `Self::expr`
+            _ => return common::statement::statement2js(self, node, indent),
+        };
 
-        self.handle_node_post(cursor);
+        Ok(format!("{:indent$}{}\n", "", code, indent = indent))
     }
 }
@@ -0,0 +1,387 @@
+//! 监听模式：保留每个文件的语法树，修改时只重新解析、转换改动到的部分
+
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+use crate::{
+    error::{CompileError, ErrorCode, Result},
+    project::{SourceFile, SymbolTable},
+    Artifacts, CompileOptions, OutputFormat,
+};
+
+/// What an update of the [`WatchSession`] did
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    /// Views converted again, the others reused their previous code
+    pub regenerated: Vec<String>,
+    /// `false` when no module changed, `bundle.js`, `index.html` and the vendored libraries are left as they were.
+    /// Nothing is bundled for the Single-File Component formats, it only tells whether a module changed
+    pub bundled: bool,
+}
+
+/// Keeps the syntax trees and generated code between builds,
+/// so an edit only converts the views it touched
+pub struct WatchSession {
+    options: CompileOptions,
+    parser: Parser,
+    files: Vec<SourceFile>,
+    trees: Vec<Tree>,
+    symbols: Rc<SymbolTable>,
+    artifacts: Artifacts,
+}
+
+impl WatchSession {
+    /// Compile all files once, like [`crate::compile_project`]
+    pub fn new(files: Vec<SourceFile>, options: CompileOptions) -> Result<Self> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_swift::language())
+            .map_err(|e| CompileError::new(ErrorCode::Parse, format!("Error loading Swift grammar: {}", e)))?;
+
+        let trees = crate::parse_files(&files)?;
+        let mut session = Self {
+            options,
+            parser,
+            files,
+            trees,
+            symbols: Rc::new(SymbolTable::default()),
+            artifacts: Artifacts::default(),
+        };
+        session.rebuild(&HashMap::new())?;
+
+        Ok(session)
+    }
+
+    /// The result of the latest successful build
+    pub fn artifacts(&self) -> &Artifacts {
+        &self.artifacts
+    }
+
+    /// Apply new contents of `changed` files, files not seen before are added, `removed` paths are dropped
+    pub fn update(&mut self, changed: Vec<SourceFile>, removed: &[String]) -> Result<BuildReport> {
+        // byte ranges of each file whose views have to be converted again
+        let mut dirty: HashMap<String, Vec<Range<usize>>> = HashMap::new();
+
+        for path in removed {
+            if let Some(i) = self.files.iter().position(|x| &x.path == path) {
+                self.files.remove(i);
+                self.trees.remove(i);
+            }
+        }
+
+        for file in changed {
+            let Some(i) = self.files.iter().position(|x| x.path == file.path) else {
+                let tree = Self::parse(&mut self.parser, &file, None)?;
+                self.files.push(file);
+                self.trees.push(tree);
+                continue;
+            };
+
+            let Some(edit) = input_edit(&self.files[i].source, &file.source) else {
+                continue;
+            };
+
+            self.trees[i].edit(&edit);
+            let tree = Self::parse(&mut self.parser, &file, Some(&self.trees[i]))?;
+
+            // a text change inside a leaf doesn't change the syntax, only the edit itself tells about it
+            let ranges = dirty.entry(file.path.clone()).or_default();
+            ranges.push(edit.start_byte..edit.new_end_byte);
+            ranges.extend(self.trees[i].changed_ranges(&tree).map(|x| x.start_byte..x.end_byte));
+
+            self.files[i] = file;
+            self.trees[i] = tree;
+        }
+
+        let reuse = self.reusable_components(&dirty)?;
+        self.rebuild(&reuse)
+    }
+
+    fn parse(parser: &mut Parser, file: &SourceFile, old_tree: Option<&Tree>) -> Result<Tree> {
+        parser.parse(&file.source, old_tree).ok_or_else(|| {
+            CompileError::new(ErrorCode::Parse, "Failed to parse the Swift source").in_file(&file.path)
+        })
+    }
+
+    /// Code of the views which are not touched by the `dirty` ranges
+    fn reusable_components(&self, dirty: &HashMap<String, Vec<Range<usize>>>) -> Result<HashMap<String, String>> {
+        let (struct_list, _, symbols) =
+            crate::parse_project(&self.files, &self.trees, &self.options, &mut Vec::new())?;
+
+        // a view can import any other view or model, all of them are converted again
+        if *symbols != *self.symbols {
+            return Ok(HashMap::new());
+        }
+
+        let mut reuse = HashMap::new();
+        for st in struct_list {
+            let st = st.borrow();
+            if !st.is_view() {
+                continue;
+            }
+
+            // its extensions are part of it
+            let mut nodes = std::iter::once((&st.path, &st.node)).chain(st.extensions.iter().map(|x| (&x.path, &x.node)));
+            // the source map has the lines of the view and the whole file, any edit of the file makes it stale
+            let source_maps = self.options.source_maps && self.options.format == OutputFormat::Bundle;
+            let touched = nodes.any(|(path, node)| {
+                let range = node.byte_range();
+                dirty.get(&**path).is_some_and(|ranges| {
+                    source_maps || ranges.iter().any(|x| x.start <= range.end && range.start <= x.end)
+                })
+            });
+            // keep reporting the problems of a view until it's fixed
+            let failed = self
+                .artifacts
+                .diagnostics
+                .iter()
+                .any(|x| x.view.as_deref() == Some(st.name.as_str()));
+
+            if touched || failed {
+                continue;
+            }
+
+            if let Some(code) = self.artifacts.files.get(&self.options.format.view_file(&st.name)) {
+                reuse.insert(st.name.clone(), code.clone());
+            }
+        }
+
+        Ok(reuse)
+    }
+
+    fn rebuild(&mut self, reuse: &HashMap<String, String>) -> Result<BuildReport> {
+        let mut artifacts = Artifacts::default();
+        let (struct_list, globals, symbols) =
+            crate::parse_project(&self.files, &self.trees, &self.options, &mut artifacts.diagnostics)?;
+
+        let regenerated = struct_list
+            .iter()
+            .map(|st| st.borrow())
+            .filter(|st| st.is_view() && !reuse.contains_key(&st.name))
+            .map(|st| st.name.clone())
+            .collect();
+
+        crate::generate_modules(struct_list, globals, symbols.clone(), &self.options, reuse, &mut artifacts)?;
+        // the source map of a reused component is still right, its file didn't change
+        for name in reuse.keys() {
+            let map = format!("{}.map", self.options.format.view_file(name));
+            if let Some(code) = self.artifacts.files.get(&map) {
+                artifacts.files.insert(map, code.clone());
+            }
+        }
+
+        let generated = |files: &std::collections::BTreeMap<String, String>| {
+            files
+                .iter()
+                .filter(|(name, _)| !crate::is_bundle_output(name))
+                .map(|(name, code)| (name.clone(), code.clone()))
+                .collect::<Vec<_>>()
+        };
+        let unchanged = generated(&artifacts.files) == generated(&self.artifacts.files);
+
+        if unchanged {
+            let previous = std::mem::take(&mut self.artifacts.files);
+            artifacts
+                .files
+                .extend(previous.into_iter().filter(|(name, _)| crate::is_bundle_output(name)));
+        } else if self.options.format == OutputFormat::Bundle {
+            crate::bundle_artifacts(&mut artifacts, &self.options)?;
+        }
+
+        self.symbols = symbols;
+        self.artifacts = artifacts;
+
+        Ok(BuildReport {
+            regenerated,
+            bundled: !unchanged,
+        })
+    }
+}
+
+/// The edit turning `old` into `new`, `None` if they are the same
+fn input_edit(old: &str, new: &str) -> Option<InputEdit> {
+    if old == new {
+        return None;
+    }
+
+    let mut start = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(start) || !new.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    // the common suffix can't overlap the common prefix
+    let max_suffix = old.len().min(new.len()) - start;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+
+    let old_end = old.len() - suffix;
+    let new_end = new.len() - suffix;
+
+    Some(InputEdit {
+        start_byte: start,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old, start),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    })
+}
+
+fn point_at(source: &str, byte: usize) -> Point {
+    let before = &source[..byte];
+    let row = before.matches('\n').count();
+    let column = before.rfind('\n').map(|x| byte - x - 1).unwrap_or(byte);
+
+    Point { row, column }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONTENT_VIEW: &str = r#"
+struct ContentView: View {
+    var body: some View {
+        VStack {
+            Text("Hello")
+            Row()
+        }
+    }
+}
+
+struct Previews: PreviewProvider {
+    static var previews: some View {
+        ContentView()
+    }
+}
+"#;
+
+    const ROW: &str = r#"
+struct Row: View {
+    var body: some View {
+        Text("Row")
+    }
+}
+"#;
+
+    fn session() -> WatchSession {
+        let files = vec![
+            SourceFile::new("ContentView.swift", CONTENT_VIEW),
+            SourceFile::new("Row.swift", ROW),
+        ];
+        WatchSession::new(files, CompileOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_input_edit() {
+        let edit = input_edit("let a = 1\nlet b = 2\n", "let a = 1\nlet b = 42\n").unwrap();
+        assert_eq!(edit.start_byte, 18);
+        assert_eq!(edit.old_end_byte, 18);
+        assert_eq!(edit.new_end_byte, 19);
+        assert_eq!(edit.start_position, Point { row: 1, column: 8 });
+
+        let edit = input_edit("aaa", "aa").unwrap();
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (2, 3, 2));
+
+        assert!(input_edit("same", "same").is_none());
+    }
+
+    #[test]
+    fn test_update_only_regenerates_changed_view() {
+        let mut session = session();
+
+        let source = ROW.replace("\"Row\"", "\"Changed\"");
+        let report = session
+            .update(vec![SourceFile::new("Row.swift", source)], &[])
+            .unwrap();
+
+        assert_eq!(report.regenerated, vec!["Row"]);
+        assert!(report.bundled);
+        assert!(session.artifacts().files["index.html"].contains("Changed"));
+    }
+
+    #[test]
+    fn test_update_without_change_skips_bundle() {
+        let mut session = session();
+
+        let source = format!("{}\n// a comment\n", ROW);
+        let report = session
+            .update(vec![SourceFile::new("Row.swift", source)], &[])
+            .unwrap();
+
+        assert!(report.regenerated.is_empty());
+        assert!(!report.bundled);
+        assert!(session.artifacts().files.contains_key("bundle.js"));
+    }
+
+    #[test]
+    fn test_sfc_update_reuses_components() {
+        let files = vec![
+            SourceFile::new("ContentView.swift", CONTENT_VIEW),
+            SourceFile::new("Row.swift", ROW),
+        ];
+        let options = CompileOptions {
+            format: OutputFormat::Project,
+            ..Default::default()
+        };
+        let mut session = WatchSession::new(files, options).unwrap();
+
+        let source = ROW.replace("\"Row\"", "\"Changed\"");
+        let report = session
+            .update(vec![SourceFile::new("Row.swift", source)], &[])
+            .unwrap();
+
+        assert_eq!(report.regenerated, vec!["Row"]);
+        assert!(session.artifacts().files["src/components/Row.vue"].contains("Changed"));
+    }
+
+    #[test]
+    fn test_update_regenerates_source_maps_of_file() {
+        let files = vec![
+            SourceFile::new("ContentView.swift", CONTENT_VIEW),
+            SourceFile::new("Row.swift", format!("{}\n{}", ROW, ROW.replace("Row", "Cell"))),
+        ];
+        let options = CompileOptions {
+            source_maps: true,
+            ..Default::default()
+        };
+        let mut session = WatchSession::new(files, options).unwrap();
+
+        // `Cell` moves down a line
+        let source = format!("{}\n{}", ROW.replace("\"Row\")", "\"Row\")\n            .bold()"), ROW.replace("Row", "Cell"));
+        let report = session
+            .update(vec![SourceFile::new("Row.swift", source.clone())], &[])
+            .unwrap();
+
+        assert_eq!(report.regenerated, vec!["Row", "Cell"]);
+        let map = sourcemap::SourceMap::from_slice(session.artifacts().files["Cell.js.map"].as_bytes()).unwrap();
+        assert_eq!(map.get_source_contents(0), Some(source.as_str()));
+        let row = source.lines().position(|x| x.contains("Text(\"Cell\")")).unwrap() as u32;
+        assert!(map.tokens().any(|x| x.get_src_line() == row));
+    }
+
+    #[test]
+    fn test_new_view_regenerates_all() {
+        let mut session = session();
+
+        let source = format!("{}\nstruct Other: View {{ var body: some View {{ Text(\"x\") }} }}", ROW);
+        let report = session
+            .update(vec![SourceFile::new("Row.swift", source)], &[])
+            .unwrap();
+
+        assert_eq!(report.regenerated, vec!["ContentView", "Row", "Other"]);
+    }
+}